@@ -1,7 +1,42 @@
+// The IDL instructions Anchor 0.31 generates still call AccountInfo::realloc
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions as instructions_sysvar};
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("FoHCiuKzML2o3b6GkG1kE27XaTRAiTAnzSSwobJpiR6S");
 
+/// Time after expiry the owner has to reveal a lottery secret before the
+/// pool fails and every ticket becomes refundable.
+pub const LOTTERY_REVEAL_WINDOW: i64 = 3 * 24 * 60 * 60;
+
+/// Slots between a lottery reveal and the slot whose hash completes the
+/// seed, so nobody knows the seed when the secret is revealed.
+pub const LOTTERY_SEED_DELAY: u64 = 10;
+
+/// Time after expiry the owner of a governed pool has to open release
+/// proposals. Escrow still unreleased afterwards is refunded pro rata.
+pub const GOVERNANCE_WINDOW: i64 = 180 * 24 * 60 * 60;
+
+/// Target slot duration, used to convert between the two time bases.
+pub const ESTIMATED_SLOT_MS: i64 = 400;
+
+/// Longest a pool can run, from creation to expiry: a year. Keeps every
+/// deadline derived from the expiry far from overflowing.
+pub const MAX_POOL_DURATION: i64 = 365 * 24 * 60 * 60;
+
+/// Longest `min_slot_gap` a pool can set: a day of slots.
+pub const MAX_SLOT_GAP: u64 = 24 * 60 * 60 * 1000 / ESTIMATED_SLOT_MS as u64;
+
+/// Layout version of every event, stored in its `header`. The header comes
+/// last, so decoders tell the layouts apart by length: version 0 events,
+/// logged before headers existed, lack the header, `PoolCreated` also lacks
+/// the caps and time basis and `DepositMade` lacks `payer`.
+pub const EVENT_VERSION: u8 = 1;
+
 #[program]
 pub mod presale_program {
     use super::*;
@@ -11,13 +46,27 @@ pub mod presale_program {
         expiry_timestamp: i64,
         min_deposit: u64,
         max_deposit: u64,
+        soft_cap: u64,
+        hard_cap: u64,
+        time_basis: TimeBasis,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         let clock = Clock::get()?;
 
-        require!(expiry_timestamp > clock.unix_timestamp, PresaleError::InvalidExpiryTime);
+        // Every deadline of the pool is measured in its time basis from here on
+        pool.time_basis = time_basis;
+        let now = pool.now(&clock);
+        require!(
+            expiry_timestamp > now && expiry_timestamp - now <= pool.in_time_basis(MAX_POOL_DURATION),
+            PresaleError::InvalidExpiryTime
+        );
         require!(min_deposit > 0, PresaleError::InvalidDepositAmount);
         require!(max_deposit >= min_deposit, PresaleError::InvalidDepositAmount);
+        // A hard cap of zero leaves the pool uncapped
+        require!(
+            hard_cap == 0 || (hard_cap >= soft_cap && hard_cap >= min_deposit),
+            PresaleError::InvalidCaps
+        );
 
         pool.owner = ctx.accounts.owner.key();
         pool.expiry_timestamp = expiry_timestamp;
@@ -27,6 +76,30 @@ pub mod presale_program {
         pool.is_claimed = false;
         pool.depositor_count = 0;
         pool.bump = ctx.bumps.pool;
+        pool.token_mint = Pubkey::default();
+        pool.total_tokens = 0;
+        pool.merkle_root = [0; 32];
+        pool.is_finalized = false;
+        pool.soft_cap = soft_cap;
+        pool.hard_cap = hard_cap;
+        pool.total_refunded = 0;
+        pool.attestation_authority = Pubkey::default();
+        pool.whitelist_root = [0; 32];
+        pool.whitelist_end = 0;
+        pool.min_slot_gap = 0;
+        pool.reject_cpi = false;
+        pool.lottery_winners = 0;
+        pool.lottery_commitment = [0; 32];
+        pool.lottery_entropy = [0; 32];
+        pool.lottery_seed = [0; 32];
+        pool.lottery_drawn = false;
+        pool.version = Pool::VERSION;
+        pool.counted = true;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.bump = ctx.bumps.stats;
+        stats.total_pools += 1;
+        stats.active_pools += 1;
 
         emit!(PoolCreated {
             pool: pool.key(),
@@ -34,52 +107,807 @@ pub mod presale_program {
             expiry_timestamp,
             min_deposit,
             max_deposit,
+            soft_cap,
+            hard_cap,
+            time_basis,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_attestation_authority(ctx: Context<ConfigurePool>, authority: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+
+        // Pubkey::default() turns attestation off again
+        pool.attestation_authority = authority;
+
+        emit!(AttestationAuthoritySet {
+            pool: pool.key(),
+            authority,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    pub fn configure_anti_bot(
+        ctx: Context<ConfigurePool>,
+        whitelist_root: [u8; 32],
+        whitelist_end: i64,
+        min_slot_gap: u64,
+        reject_cpi: bool,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(whitelist_end <= pool.expiry_timestamp, PresaleError::InvalidWhitelistWindow);
+        require!(min_slot_gap <= MAX_SLOT_GAP, PresaleError::InvalidSlotGap);
+
+        pool.whitelist_root = whitelist_root;
+        pool.whitelist_end = whitelist_end;
+        pool.min_slot_gap = min_slot_gap;
+        pool.reject_cpi = reject_cpi;
+
+        emit!(AntiBotConfigured {
+            pool: pool.key(),
+            whitelist_root,
+            whitelist_end,
+            min_slot_gap,
+            reject_cpi,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Restricts deposits to wallets holding at least `min_amount` of the SPL
+    /// mint `mint` (`TokenHolder`), or an NFT of the verified collection
+    /// `mint` (`NftCollection`). `GateKind::None` lifts the gate.
+    pub fn configure_gate(ctx: Context<ConfigurePool>, kind: GateKind, mint: Pubkey, min_amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        require!(
+            match kind {
+                GateKind::None => true,
+                GateKind::TokenHolder => mint != Pubkey::default() && min_amount > 0,
+                GateKind::NftCollection => mint != Pubkey::default(),
+            },
+            PresaleError::InvalidGateConfig
+        );
+
+        pool.gate_kind = kind;
+        pool.gate_mint = if kind == GateKind::None { Pubkey::default() } else { mint };
+        // A collection gate asks for one NFT whatever the owner passed
+        pool.gate_min_amount = match kind {
+            GateKind::None => 0,
+            GateKind::TokenHolder => min_amount,
+            GateKind::NftCollection => 1,
+        };
+
+        emit!(GateConfigured {
+            pool: pool.key(),
+            kind,
+            mint: pool.gate_mint,
+            min_amount: pool.gate_min_amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Adds limits in micro-USD (6 decimals) on top of the lamport ones.
+    /// Each deposit is valued at the SOL/USD price of `price_oracle`, read
+    /// as `kind` and no older than `max_price_age` seconds, and must fall
+    /// within `min_deposit` and `max_deposit`; the pool's running USD total
+    /// is held against `soft_cap` and `hard_cap`. Zero maximums and caps are
+    /// unlimited. `OracleKind::None` removes the USD limits.
+    pub fn configure_usd_limits(
+        ctx: Context<ConfigureUsdLimits>,
+        kind: OracleKind,
+        max_price_age: i64,
+        min_deposit: u64,
+        max_deposit: u64,
+        soft_cap: u64,
+        hard_cap: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        // Lottery tickets have one lamport price, which a moving SOL price cannot keep
+        require!(
+            kind == OracleKind::None
+                || (max_price_age > 0
+                    && (max_deposit == 0 || min_deposit <= max_deposit)
+                    && (hard_cap == 0 || soft_cap <= hard_cap)
+                    && !pool.is_lottery()),
+            PresaleError::InvalidUsdConfig
+        );
+
+        let enabled = kind != OracleKind::None;
+        pool.oracle_kind = kind;
+        pool.price_oracle = match &ctx.accounts.price_oracle {
+            Some(oracle) if enabled => oracle.key(),
+            _ => Pubkey::default(),
+        };
+        pool.max_price_age = if enabled { max_price_age } else { 0 };
+        pool.usd_min_deposit = if enabled { min_deposit } else { 0 };
+        pool.usd_max_deposit = if enabled { max_deposit } else { 0 };
+        pool.usd_soft_cap = if enabled { soft_cap } else { 0 };
+        pool.usd_hard_cap = if enabled { hard_cap } else { 0 };
+
+        // Reading a fresh price now catches a wrong account or format before deposits do
+        if enabled {
+            read_usd_price(pool, ctx.accounts.price_oracle.as_deref(), Clock::get()?.unix_timestamp)?;
+        }
+
+        emit!(UsdLimitsConfigured {
+            pool: pool.key(),
+            kind,
+            price_oracle: pool.price_oracle,
+            max_price_age: pool.max_price_age,
+            min_deposit: pool.usd_min_deposit,
+            max_deposit: pool.usd_max_deposit,
+            soft_cap: pool.usd_soft_cap,
+            hard_cap: pool.usd_hard_cap,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Turns the pool into a lottery: `winner_count` tickets win, the rest are
+    /// refunded. `commitment` is sha256 of the owner's secret, revealed after
+    /// expiry with `reveal_lottery`.
+    pub fn configure_lottery(ctx: Context<ConfigurePool>, winner_count: u32, commitment: [u8; 32]) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        // Tickets have one fixed price so every winner pays and receives the same
+        require!(
+            winner_count > 0
+                && pool.min_deposit == pool.max_deposit
+                && !pool.is_governed()
+                && !pool.is_streaming()
+                && !pool.has_bonus()
+                && !pool.has_usd_limits(),
+            PresaleError::InvalidLotteryConfig
+        );
+
+        pool.lottery_winners = winner_count;
+        pool.lottery_commitment = commitment;
+
+        emit!(LotteryConfigured {
+            pool: pool.key(),
+            winner_count,
+            commitment,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Puts the raised SOL under depositor governance: instead of claiming it,
+    /// the owner proposes releases that depositors vote on, weighted by their
+    /// deposits. `voting_period` is in the pool's time basis; a zero period
+    /// turns governance off again.
+    pub fn configure_governance(
+        ctx: Context<ConfigurePool>,
+        quorum_bps: u16,
+        approval_bps: u16,
+        voting_period: i64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        // Lottery pools already hand the losing tickets' SOL back, so the escrow is not the raise
+        require!(
+            voting_period == 0
+                || (voting_period > 0
+                    && quorum_bps <= 10_000
+                    && approval_bps > 0
+                    && approval_bps <= 10_000
+                    && !pool.is_lottery()
+                    && !pool.is_streaming()),
+            PresaleError::InvalidGovernanceConfig
+        );
+
+        pool.voting_period = voting_period;
+        pool.quorum_bps = if voting_period == 0 { 0 } else { quorum_bps };
+        pool.approval_bps = if voting_period == 0 { 0 } else { approval_bps };
+
+        emit!(GovernanceConfigured {
+            pool: pool.key(),
+            quorum_bps: pool.quorum_bps,
+            approval_bps: pool.approval_bps,
+            voting_period,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Streams the raised SOL to the owner instead of `claim_funds`: from
+    /// expiry, the raise accrues linearly over `duration` (in the pool's time
+    /// basis) and the owner withdraws what has accrued. Depositors holding
+    /// `halt_bps` of the raise can halt the stream and have the rest refunded.
+    /// A zero duration turns streaming off again.
+    pub fn configure_stream(ctx: Context<ConfigurePool>, duration: i64, halt_bps: u16) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        // Both streams and governance release the escrow, so only one may
+        require!(
+            duration == 0
+                || (duration > 0
+                    && halt_bps > 0
+                    && halt_bps <= 10_000
+                    && !pool.is_lottery()
+                    && !pool.is_governed()),
+            PresaleError::InvalidStreamConfig
+        );
+
+        pool.stream_duration = duration;
+        pool.halt_bps = if duration == 0 { 0 } else { halt_bps };
+
+        emit!(StreamConfigured {
+            pool: pool.key(),
+            duration,
+            halt_bps: pool.halt_bps,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Caps each wallet's total deposit by its stake of `stake_mint`: a
+    /// wallet with at least `min_stakes[i]` staked may deposit up to
+    /// `max_allocations[i]`. Empty lists turn tiers off again.
+    pub fn configure_tiers(
+        ctx: Context<ConfigureTiers>,
+        min_stakes: Vec<u64>,
+        max_allocations: Vec<u64>,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        // Both lists ascend, so a larger stake never lowers the allocation
+        require!(
+            min_stakes.len() == max_allocations.len()
+                && min_stakes.len() <= Pool::MAX_TIERS
+                && min_stakes.iter().all(|&stake| stake > 0)
+                && max_allocations.iter().all(|&allocation| allocation >= pool.min_deposit)
+                && min_stakes.windows(2).all(|pair| pair[0] < pair[1])
+                && max_allocations.windows(2).all(|pair| pair[0] <= pair[1]),
+            PresaleError::InvalidTiers
+        );
+
+        let stake_mint = if min_stakes.is_empty() { Pubkey::default() } else { ctx.accounts.stake_mint.key() };
+        pool.tier_mint = stake_mint;
+        pool.tier_count = min_stakes.len() as u8;
+        pool.tier_min_stakes = [0; Pool::MAX_TIERS];
+        pool.tier_max_allocations = [0; Pool::MAX_TIERS];
+        pool.tier_min_stakes[..min_stakes.len()].copy_from_slice(&min_stakes);
+        pool.tier_max_allocations[..max_allocations.len()].copy_from_slice(&max_allocations);
+
+        emit!(TiersConfigured {
+            pool: pool.key(),
+            stake_mint,
+            min_stakes,
+            max_allocations,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Rewards early deposits: a deposit before `ends[i]` counts
+    /// `bonus_bps[i]` extra towards the depositor's token allocation. Ends
+    /// ascend in the pool's time basis and bonuses decay; after the last end
+    /// deposits count at face value. Empty lists turn the bonus off again.
+    pub fn configure_bonus(ctx: Context<ConfigurePool>, ends: Vec<i64>, bonus_bps: Vec<u16>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        // Lottery tickets win equal shares and curve buyers get their tokens at once
+        require!(
+            ends.len() == bonus_bps.len()
+                && ends.len() <= Pool::MAX_BONUS_STEPS
+                && ends.last().is_none_or(|&end| end <= pool.expiry_timestamp)
+                && ends.windows(2).all(|pair| pair[0] < pair[1])
+                && bonus_bps.iter().all(|&bps| bps > 0 && bps <= 10_000)
+                && bonus_bps.windows(2).all(|pair| pair[0] >= pair[1])
+                && (ends.is_empty() || (!pool.is_lottery() && !pool.is_curve())),
+            PresaleError::InvalidBonusConfig
+        );
+
+        pool.bonus_count = ends.len() as u8;
+        pool.bonus_ends = [0; Pool::MAX_BONUS_STEPS];
+        pool.bonus_bps = [0; Pool::MAX_BONUS_STEPS];
+        pool.bonus_ends[..ends.len()].copy_from_slice(&ends);
+        pool.bonus_bps[..bonus_bps.len()].copy_from_slice(&bonus_bps);
+
+        emit!(BonusConfigured {
+            pool: pool.key(),
+            ends,
+            bonus_bps,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Stakes `amount` of `stake_mint` into the staker's own vault. Pools
+    /// tiered on that mint size the staker's allocation from the total.
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        require!(amount > 0, PresaleError::InvalidStakeAmount);
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.staker_token_account.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.staker.to_account_info(),
+            },
+        );
+        token::transfer(cpi_context, amount)?;
+
+        let stake_record = &mut ctx.accounts.stake_record;
+        stake_record.mint = ctx.accounts.stake_mint.key();
+        stake_record.staker = ctx.accounts.staker.key();
+        stake_record.bump = ctx.bumps.stake_record;
+        stake_record.amount += amount;
+
+        emit!(Staked {
+            mint: stake_record.mint,
+            staker: stake_record.staker,
+            amount,
+            total_staked: stake_record.amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws staked tokens; only possible while none of the staker's
+    /// positions in tiered pools is still locked.
+    pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+        let stake_record = &mut ctx.accounts.stake_record;
+
+        require!(stake_record.active_positions == 0, PresaleError::StakeLocked);
+        require!(amount > 0 && amount <= stake_record.amount, PresaleError::InvalidStakeAmount);
+
+        // The vault's authority is the stake record PDA
+        let seeds: &[&[u8]] = &[
+            b"stake",
+            stake_record.mint.as_ref(),
+            stake_record.staker.as_ref(),
+            &[stake_record.bump],
+        ];
+        let signer_seeds = &[seeds];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.stake_vault.to_account_info(),
+                to: ctx.accounts.staker_token_account.to_account_info(),
+                authority: stake_record.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(cpi_context, amount)?;
+
+        stake_record.amount -= amount;
+
+        emit!(Unstaked {
+            mint: stake_record.mint,
+            staker: stake_record.staker,
+            amount,
+            total_staked: stake_record.amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Unlocks the stake behind a position in a tiered pool once the pool
+    /// has expired. Anyone can call it.
+    pub fn release_stake(ctx: Context<ReleaseStake>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let depositor_record = &mut ctx.accounts.depositor_record;
+        let stake_record = &mut ctx.accounts.stake_record;
+
+        require!(pool.now(&Clock::get()?) >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(depositor_record.stake_locked, PresaleError::StakeNotLocked);
+
+        depositor_record.stake_locked = false;
+        stake_record.active_positions -= 1;
+
+        emit!(StakeReleased {
+            pool: pool.key(),
+            staker: stake_record.staker,
+            active_positions: stake_record.active_positions,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Creates or replaces the pool's display metadata. Unlike the sale
+    /// parameters it can change at any time.
+    pub fn set_pool_metadata(
+        ctx: Context<SetPoolMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+        links: Vec<String>,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(
+            !name.is_empty()
+                && name.len() <= PoolMetadata::MAX_NAME_LEN
+                && symbol.len() <= PoolMetadata::MAX_SYMBOL_LEN
+                && uri.len() <= PoolMetadata::MAX_URI_LEN
+                && links.len() <= PoolMetadata::MAX_LINKS
+                && links.iter().all(|link| link.len() <= PoolMetadata::MAX_LINK_LEN),
+            PresaleError::InvalidMetadata
+        );
+
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.pool = pool.key();
+        metadata.name = name;
+        metadata.symbol = symbol;
+        metadata.uri = uri;
+        metadata.links = links;
+
+        emit!(PoolMetadataSet {
+            pool: pool.key(),
+            name: metadata.name.clone(),
+            symbol: metadata.symbol.clone(),
+            uri: metadata.uri.clone(),
+            links: metadata.links.clone(),
+            header: EventHeader::now()?,
         });
 
         Ok(())
     }
 
-    pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64) -> Result<()> {
+    /// `payer` funds the deposit; the record, allocation and refund rights
+    /// belong to `beneficiary`, which is the payer itself for a plain deposit.
+    pub fn deposit_sol(ctx: Context<DepositSol>, amount: u64, whitelist_proof: Vec<[u8; 32]>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
+        let beneficiary = ctx.accounts.beneficiary.key();
         let clock = Clock::get()?;
+        let now = pool.now(&clock);
+        let phase = pool.phase(now);
 
         // Check if pool is still active
-        require!(clock.unix_timestamp < pool.expiry_timestamp, PresaleError::PoolExpired);
+        require!(now < pool.expiry_timestamp, PresaleError::PoolExpired);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+
+        // Anti-bot rules: direct calls only, whitelist-only opening, per-wallet slot gap
+        if pool.reject_cpi {
+            require!(
+                get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+                PresaleError::CpiDepositRejected
+            );
+        }
+        if pool.whitelist_active(now) {
+            require!(
+                verify_merkle_proof(
+                    &whitelist_proof,
+                    pool.whitelist_root,
+                    whitelist_leaf(&beneficiary)
+                ),
+                PresaleError::NotWhitelisted
+            );
+        }
+        // Records of the first layout take their index once the pool is migrated
+        require!(!Pool::predates_indexes(&pool.to_account_info()), PresaleError::AccountNeedsMigration);
+        let pool_key = pool.key();
+        let mut depositor_record = DepositorRecord::open(
+            &ctx.accounts.depositor_record,
+            pool,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            &[b"depositor", pool_key.as_ref(), beneficiary.as_ref(), &[ctx.bumps.depositor_record]],
+        )?;
+        if pool.min_slot_gap > 0 {
+            // The gap runs per beneficiary, so others depositing for it could hold it off
+            require!(ctx.accounts.beneficiary.is_signer, PresaleError::BeneficiarySignatureRequired);
+            if depositor_record.amount > 0 {
+                require!(
+                    clock.slot >= depositor_record.last_deposit_slot.saturating_add(pool.min_slot_gap),
+                    PresaleError::DepositTooSoon
+                );
+            }
+        }
         
         // Check deposit amount limits
         require!(amount >= pool.min_deposit, PresaleError::DepositTooLow);
         require!(amount <= pool.max_deposit, PresaleError::DepositTooHigh);
+        require!(
+            pool.hard_cap == 0 || pool.total_raised + amount <= pool.hard_cap,
+            PresaleError::HardCapExceeded
+        );
+        // USD limits value the deposit at the oracle's current price
+        let priced = if pool.has_usd_limits() {
+            let price = read_usd_price(pool, ctx.accounts.price_oracle.as_deref(), clock.unix_timestamp)?;
+            let usd_value = price.usd_value(amount).ok_or(PresaleError::InvalidPriceOracle)?;
+            require!(usd_value >= pool.usd_min_deposit, PresaleError::DepositTooLow);
+            require!(
+                pool.usd_max_deposit == 0 || usd_value <= pool.usd_max_deposit,
+                PresaleError::DepositTooHigh
+            );
+            require!(
+                pool.usd_hard_cap == 0 || pool.total_raised_usd + usd_value <= pool.usd_hard_cap,
+                PresaleError::HardCapExceeded
+            );
+            Some((price, usd_value))
+        } else {
+            None
+        };
+        if pool.is_lottery() {
+            require!(depositor_record.amount == 0, PresaleError::TicketAlreadyRegistered);
+        }
+        verify_gate(
+            pool,
+            &beneficiary,
+            ctx.accounts.gate_token_account.as_deref(),
+            ctx.accounts.gate_metadata.as_deref(),
+        )?;
+        // Tiered pools cap the beneficiary's total by its stake; no stake, no allocation.
+        // The deposit locks that stake, which only the staker may agree to
+        if pool.is_tiered() {
+            require!(ctx.accounts.beneficiary.is_signer, PresaleError::BeneficiarySignatureRequired);
+            let staked = ctx.accounts.stake_record.as_ref().map_or(0, |record| record.amount);
+            require!(
+                depositor_record.amount + amount <= pool.tier_allocation(staked),
+                PresaleError::TierAllocationExceeded
+            );
+        }
 
-        // Transfer SOL from depositor to pool vault
+        // Gated pools need the authority's signed allocation in this transaction
+        if pool.attestation_authority != Pubkey::default() {
+            let max_allocation = verify_attestation(
+                &ctx.accounts.instructions,
+                &pool.key(),
+                &beneficiary,
+                &pool.attestation_authority,
+                clock.unix_timestamp,
+            )?;
+            require!(
+                depositor_record.amount + amount <= max_allocation,
+                PresaleError::AllocationExceeded
+            );
+        }
+
+        // Transfer SOL from the payer to pool vault
         let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             anchor_lang::system_program::Transfer {
-                from: ctx.accounts.depositor.to_account_info(),
+                from: ctx.accounts.payer.to_account_info(),
                 to: ctx.accounts.pool_vault.to_account_info(),
             },
         );
         anchor_lang::system_program::transfer(cpi_context, amount)?;
 
         // Update or create depositor record
-        let depositor_record = &mut ctx.accounts.depositor_record;
-        if depositor_record.amount == 0 {
-            // New depositor
+        // Records emptied by a transfer keep their index and claim bitmap bit
+        if depositor_record.pool == Pubkey::default() {
+            // New depositor, indexed in arrival order for the claim bitmap
+            depositor_record.version = DepositorRecord::VERSION;
+            depositor_record.index = pool.depositor_count;
             pool.depositor_count += 1;
-            depositor_record.depositor = ctx.accounts.depositor.key();
+            depositor_record.depositor = beneficiary;
             depositor_record.pool = pool.key();
         }
         
         depositor_record.amount += amount;
         depositor_record.timestamp = clock.unix_timestamp;
+        depositor_record.last_deposit_slot = clock.slot;
+
+        // Early deposits weigh more in the token split; the SOL stays at face value
+        let bonus_bps = pool.bonus_bps_at(now);
+        if pool.has_bonus() {
+            let weighted = amount + (amount as u128 * bonus_bps as u128 / 10_000) as u64;
+            depositor_record.weighted_amount += weighted;
+            pool.total_weighted += weighted;
+        }
+
+        // The stake stays locked until the pool expires and `release_stake` runs
+        if let Some(stake_record) = ctx.accounts.stake_record.as_mut() {
+            if pool.is_tiered() && !depositor_record.stake_locked {
+                depositor_record.stake_locked = true;
+                stake_record.active_positions += 1;
+            }
+        }
         
         pool.total_raised += amount;
+        if let Some((_, usd_value)) = priced {
+            pool.total_raised_usd += usd_value;
+        }
+        depositor_record.save(&ctx.accounts.depositor_record)?;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.bump = ctx.bumps.stats;
+        stats.total_raised += amount;
+        let participant = &mut ctx.accounts.participant;
+        if participant.wallet == Pubkey::default() {
+            participant.wallet = beneficiary;
+            stats.unique_depositors += 1;
+        }
+
+        // Every ticket is folded into the participants' side of the draw seed
+        if pool.is_lottery() {
+            pool.lottery_entropy = hashv(&[
+                &pool.lottery_entropy,
+                beneficiary.as_ref(),
+                &clock.slot.to_le_bytes(),
+            ])
+            .to_bytes();
+        }
 
         emit!(DepositMade {
             pool: pool.key(),
-            depositor: ctx.accounts.depositor.key(),
+            depositor: beneficiary,
             amount,
             total_deposit: depositor_record.amount,
             pool_total: pool.total_raised,
+            payer: ctx.accounts.payer.key(),
+            header: EventHeader::now()?,
+        });
+        if pool.has_bonus() {
+            emit!(BonusApplied {
+                pool: pool.key(),
+                depositor: beneficiary,
+                bonus_bps,
+                weighted_amount: depositor_record.weighted_amount,
+                total_weighted: pool.total_weighted,
+                header: EventHeader::now()?,
+            });
+        }
+        if let Some((price, usd_value)) = priced {
+            emit!(DepositPriced {
+                pool: pool.key(),
+                depositor: beneficiary,
+                amount,
+                usd_value,
+                price: price.price,
+                exponent: price.exponent,
+                publish_time: price.publish_time,
+                total_raised_usd: pool.total_raised_usd,
+                header: EventHeader::now()?,
+            });
+        }
+        emit_phase_change(pool, phase, now)?;
+
+        Ok(())
+    }
+
+    /// Moves `amount` of the owner's position to `recipient`, who must meet
+    /// the pool's whitelist and attestation rules as if depositing it.
+    /// Positions are frozen once allocations are finalized for token claims.
+    pub fn transfer_position(
+        ctx: Context<TransferPosition>,
+        amount: u64,
+        whitelist_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let recipient = ctx.accounts.recipient.key();
+        let clock = Clock::get()?;
+        let now = pool.now(&clock);
+
+        require!(!pool.is_finalized, PresaleError::PositionsFrozen);
+        require!(!pool.is_lottery(), PresaleError::TicketNotTransferable);
+        require!(!pool.is_tiered(), PresaleError::TieredPositionNotTransferable);
+        // Votes are weighted by records, so governed and streaming positions settle at expiry
+        require!(
+            !(pool.is_governed() || pool.is_streaming()) || now < pool.expiry_timestamp,
+            PresaleError::GovernedPositionFrozen
+        );
+        require!(recipient != ctx.accounts.owner.key(), PresaleError::InvalidTransfer);
+
+        require!(!Pool::predates_indexes(&pool.to_account_info()), PresaleError::AccountNeedsMigration);
+        let pool_key = pool.key();
+        let mut to_record = DepositorRecord::open(
+            &ctx.accounts.to_record,
+            pool,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            &[b"depositor", pool_key.as_ref(), recipient.as_ref(), &[ctx.bumps.to_record]],
+        )?;
+        let from_record = &ctx.accounts.from_record;
+        require!(!from_record.is_refunded && !to_record.is_refunded, PresaleError::AlreadyRefunded);
+        require!(amount > 0 && amount <= from_record.amount, PresaleError::InvalidTransfer);
+
+        // Partial transfers leave both sides with at least a minimum deposit
+        let remaining = from_record.amount - amount;
+        require!(
+            remaining == 0 || (amount >= pool.min_deposit && remaining >= pool.min_deposit),
+            PresaleError::DepositTooLow
+        );
+
+        if pool.whitelist_active(now) {
+            require!(
+                verify_merkle_proof(&whitelist_proof, pool.whitelist_root, whitelist_leaf(&recipient)),
+                PresaleError::NotWhitelisted
+            );
+        }
+        verify_gate(
+            pool,
+            &recipient,
+            ctx.accounts.gate_token_account.as_deref(),
+            ctx.accounts.gate_metadata.as_deref(),
+        )?;
+        if pool.attestation_authority != Pubkey::default() {
+            let max_allocation = verify_attestation(
+                &ctx.accounts.instructions,
+                &pool.key(),
+                &recipient,
+                &pool.attestation_authority,
+                clock.unix_timestamp,
+            )?;
+            require!(to_record.amount + amount <= max_allocation, PresaleError::AllocationExceeded);
+        }
+
+        if to_record.pool == Pubkey::default() {
+            // New depositor, indexed like a first deposit
+            to_record.version = DepositorRecord::VERSION;
+            to_record.index = pool.depositor_count;
+            pool.depositor_count += 1;
+            to_record.depositor = recipient;
+            to_record.pool = pool.key();
+        }
+        to_record.amount += amount;
+        to_record.timestamp = clock.unix_timestamp;
+
+        // Recipients count as depositors, as if they had deposited the position
+        let stats = &mut ctx.accounts.stats;
+        stats.bump = ctx.bumps.stats;
+        let participant = &mut ctx.accounts.participant;
+        if participant.wallet == Pubkey::default() {
+            participant.wallet = recipient;
+            stats.unique_depositors += 1;
+        }
+
+        // The bonus travels with the lamports it was earned on
+        let from_record = &mut ctx.accounts.from_record;
+        let moved_weight = if remaining == 0 {
+            from_record.weighted_amount
+        } else {
+            (from_record.weighted_amount as u128 * amount as u128 / from_record.amount as u128) as u64
+        };
+        from_record.weighted_amount -= moved_weight;
+        to_record.weighted_amount += moved_weight;
+        from_record.amount = remaining;
+        to_record.save(&ctx.accounts.to_record)?;
+
+        emit!(PositionTransferred {
+            pool: pool.key(),
+            from: from_record.depositor,
+            to: recipient,
+            amount,
+            from_total: from_record.amount,
+            to_total: to_record.amount,
+            header: EventHeader::now()?,
         });
 
         Ok(())
@@ -90,7 +918,7 @@ pub mod presale_program {
         let clock = Clock::get()?;
 
         // Check if pool has expired
-        require!(clock.unix_timestamp >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(pool.now(&clock) >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
         
         // Check if funds haven't been claimed yet
         require!(!pool.is_claimed, PresaleError::AlreadyClaimed);
@@ -98,7 +926,22 @@ pub mod presale_program {
         // Check if caller is the pool owner
         require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
 
-        let amount_to_claim = ctx.accounts.pool_vault.lamports();
+        // Failed raises stay in the vault for refunds
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+
+        // Governed escrow only leaves the vault through approved proposals
+        require!(!pool.is_governed(), PresaleError::GovernedPool);
+
+        // Streaming pools pay out over time through withdraw_stream
+        require!(!pool.is_streaming(), PresaleError::StreamingPool);
+
+        // Lottery pools only pay out winning tickets; losing ones stay refundable
+        let amount_to_claim = if pool.is_lottery() {
+            require!(pool.lottery_drawn, PresaleError::LotteryNotDrawn);
+            pool.lottery_winner_count() as u64 * pool.max_deposit
+        } else {
+            ctx.accounts.pool_vault.lamports()
+        };
         
         // Transfer SOL from pool vault to owner
         **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= amount_to_claim;
@@ -106,133 +949,3286 @@ pub mod presale_program {
 
         pool.is_claimed = true;
 
+        let stats = &mut ctx.accounts.stats;
+        stats.total_claimed += amount_to_claim;
+        stats.settle_pool(pool)?;
+
         emit!(FundsClaimed {
             pool: pool.key(),
             owner: pool.owner,
             amount: amount_to_claim,
+            header: EventHeader::now()?,
         });
 
         Ok(())
     }
 
-    pub fn get_pool_info(ctx: Context<GetPoolInfo>) -> Result<PoolInfo> {
-        let pool = &ctx.accounts.pool;
-        
-        Ok(PoolInfo {
-            owner: pool.owner,
-            expiry_timestamp: pool.expiry_timestamp,
-            total_raised: pool.total_raised,
-            min_deposit: pool.min_deposit,
-            max_deposit: pool.max_deposit,
-            is_claimed: pool.is_claimed,
-            depositor_count: pool.depositor_count,
-        })
+    pub fn finalize_pool(
+        ctx: Context<FinalizePool>,
+        merkle_root: [u8; 32],
+        total_tokens: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let clock = Clock::get()?;
+        let now = pool.now(&clock);
+        let phase = pool.phase(now);
+
+        require!(now >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(!pool.is_finalized, PresaleError::AlreadyFinalized);
+        require!(!pool.is_curve(), PresaleError::CurvePool);
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        require!(pool.depositor_count > 0, PresaleError::NoDepositors);
+        require!(pool.unindexed_records == 0, PresaleError::RecordsNeedMigration);
+        require!(total_tokens > 0, PresaleError::InvalidTokenAmount);
+        require!(!pool.is_lottery() || pool.lottery_drawn, PresaleError::LotteryNotDrawn);
+
+        // Fund the token vault with the full allocation covered by the root
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.owner_token_account.to_account_info(),
+                to: ctx.accounts.pool_token_vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token::transfer(cpi_context, total_tokens)?;
+
+        let claim_bitmap = &mut ctx.accounts.claim_bitmap;
+        claim_bitmap.pool = pool.key();
+        claim_bitmap.bits = vec![0; ClaimBitmap::byte_len(pool.depositor_count)];
+
+        pool.token_mint = ctx.accounts.token_mint.key();
+        pool.total_tokens = total_tokens;
+        pool.merkle_root = merkle_root;
+        pool.is_finalized = true;
+
+        emit!(PoolFinalized {
+            pool: pool.key(),
+            token_mint: pool.token_mint,
+            merkle_root,
+            total_tokens,
+            header: EventHeader::now()?,
+        });
+        emit_phase_change(pool, phase, now)?;
+
+        Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct CreatePool<'info> {
-    #[account(
-        init,
+    pub fn claim_tokens(ctx: Context<ClaimTokens>, amount: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let depositor_record = &mut ctx.accounts.depositor_record;
+        let claim_bitmap = &mut ctx.accounts.claim_bitmap;
+
+        require!(pool.is_finalized, PresaleError::PoolNotFinalized);
+
+        // Check the allocation hasn't been claimed yet
+        let index = depositor_record.index;
+        require!(!claim_bitmap.is_claimed(index), PresaleError::AllocationAlreadyClaimed);
+
+        // Check the (index, depositor, amount) leaf against the published root
+        let leaf = merkle_leaf(index, &ctx.accounts.depositor.key(), amount);
+        require!(
+            verify_merkle_proof(&proof, pool.merkle_root, leaf),
+            PresaleError::InvalidMerkleProof
+        );
+
+        // Transfer tokens from the token vault, signed by the pool PDA
+        let seeds: &[&[u8]] = &[b"pool", pool.owner.as_ref(), &[pool.bump]];
+        let signer_seeds = &[seeds];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.pool_token_vault.to_account_info(),
+                to: ctx.accounts.depositor_token_account.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(cpi_context, amount)?;
+
+        claim_bitmap.set_claimed(index);
+        depositor_record.tokens_claimed = amount;
+
+        emit!(TokensClaimed {
+            pool: pool.key(),
+            depositor: ctx.accounts.depositor.key(),
+            index,
+            amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Pushes tokens out instead of waiting for claims: pays every
+    /// `(depositor_record, depositor_token_account)` pair in
+    /// `remaining_accounts` its entitlement and marks its index claimed.
+    /// Records already claimed or distributed, refunded, or holding a losing
+    /// ticket are skipped, so a crank can resend a batch after a failure.
+    pub fn distribute_batch<'info>(ctx: Context<'_, '_, 'info, 'info, DistributeBatch<'info>>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let claim_bitmap = &mut ctx.accounts.claim_bitmap;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.is_finalized, PresaleError::PoolNotFinalized);
+        require!(!pool.is_lottery() || ctx.accounts.lottery_draw.is_some(), PresaleError::LotteryNotDrawn);
+        require!(ctx.remaining_accounts.len() % 2 == 0, PresaleError::InvalidDistributionAccounts);
+
+        let seeds: &[&[u8]] = &[b"pool", pool.owner.as_ref(), &[pool.bump]];
+        let signer_seeds = &[seeds];
+        let (mut distributed, mut skipped) = (0u32, 0u32);
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let mut depositor_record = Account::<DepositorRecord>::try_from(&pair[0])?;
+            let token_account = Account::<TokenAccount>::try_from(&pair[1])?;
+            require!(
+                depositor_record.pool == pool.key()
+                    && token_account.mint == pool.token_mint
+                    && token_account.owner == depositor_record.depositor,
+                PresaleError::InvalidDistributionAccounts
+            );
+
+            let index = depositor_record.index;
+            let lost_lottery = ctx.accounts.lottery_draw.as_ref().is_some_and(|draw| !draw.is_winner(index));
+            let amount = pool.entitlement(depositor_record.allocation_weight());
+            if claim_bitmap.is_claimed(index) || depositor_record.is_refunded || lost_lottery || amount == 0 {
+                skipped += 1;
+                continue;
+            }
+
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.pool_token_vault.to_account_info(),
+                    to: pair[1].clone(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::transfer(cpi_context, amount)?;
+
+            claim_bitmap.set_claimed(index);
+            depositor_record.tokens_claimed = amount;
+            depositor_record.exit(&crate::ID)?;
+            distributed += 1;
+
+            emit!(TokensDistributed {
+                pool: pool.key(),
+                depositor: depositor_record.depositor,
+                index,
+                amount,
+                header: EventHeader::now()?,
+            });
+        }
+
+        emit!(BatchDistributed {
+            pool: pool.key(),
+            distributed,
+            skipped,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let depositor_record = &mut ctx.accounts.depositor_record;
+        let clock = Clock::get()?;
+
+        // Refunds only open once a pool has expired below its soft cap
+        require!(
+            pool.phase(pool.now(&clock)) == PoolPhase::Failed,
+            PresaleError::RefundNotAvailable
+        );
+        require!(!depositor_record.is_refunded, PresaleError::AlreadyRefunded);
+
+        let amount = depositor_record.amount;
+        require!(amount > 0, PresaleError::NothingToRefund);
+
+        // The first refund marks a failed pool as no longer active
+        ctx.accounts.stats.settle_pool(pool)?;
+
+        // Transfer SOL from pool vault back to depositor
+        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.depositor.try_borrow_mut_lamports()? += amount;
+
+        depositor_record.is_refunded = true;
+        pool.total_refunded += amount;
+
+        emit!(DepositRefunded {
+            pool: pool.key(),
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Marks a failed pool as no longer active without waiting for a refund,
+    /// e.g. one nobody deposited into. Anyone can call it.
+    pub fn settle_failed_pool(ctx: Context<SettleFailedPool>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.phase(pool.now(&Clock::get()?)) == PoolPhase::Failed, PresaleError::RefundNotAvailable);
+        require!(!pool.is_settled(), PresaleError::PoolAlreadySettled);
+        // Older layouts have no room to record the settlement
+        require!(pool.version >= 10, PresaleError::AccountNeedsMigration);
+
+        ctx.accounts.stats.settle_pool(pool)?;
+
+        Ok(())
+    }
+
+    /// Reveals the owner's lottery secret and stores
+    /// sha256("lottery" || secret || entropy), where `entropy` accumulated
+    /// every ticket registration after the secret was committed. The draw
+    /// completes it with the hash of a slot `LOTTERY_SEED_DELAY` later, which
+    /// neither the owner nor the participants know yet.
+    pub fn reveal_lottery(ctx: Context<RevealLottery>, secret: [u8; 32]) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let clock = Clock::get()?;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.is_lottery(), PresaleError::NotLotteryPool);
+        require!(pool.now(&clock) >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(
+            pool.now(&clock) < pool.lottery_reveal_deadline(),
+            PresaleError::LotteryRevealClosed
+        );
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        require!(pool.depositor_count > 0, PresaleError::NoDepositors);
+        require!(
+            hashv(&[&secret]).to_bytes() == pool.lottery_commitment,
+            PresaleError::InvalidLotteryReveal
+        );
+
+        pool.lottery_seed = hashv(&[b"lottery".as_ref(), &secret, &pool.lottery_entropy]).to_bytes();
+        pool.lottery_seed_slot = clock.slot + LOTTERY_SEED_DELAY;
+
+        // Sample whichever of winners and losers is the smaller set, so
+        // rejection sampling never needs more than two picks per ticket on average
+        let winners = pool.lottery_winner_count();
+        let draw = &mut ctx.accounts.lottery_draw;
+        draw.pool = pool.key();
+        draw.selects_losers = winners * 2 > pool.depositor_count;
+        draw.to_select = if draw.selects_losers { pool.depositor_count - winners } else { winners };
+        draw.selected = 0;
+        draw.nonce = 0;
+        draw.bits = vec![0; ClaimBitmap::byte_len(pool.depositor_count)];
+
+        emit!(LotteryRevealed {
+            pool: pool.key(),
+            secret,
+            entropy: pool.lottery_entropy,
+            seed: pool.lottery_seed,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Advances the draw by up to `max_draws` picks. Anyone can call it until
+    /// the winner set is complete; each pick is `sha256(seed || nonce)` modulo
+    /// the ticket count, so the result can be replayed from on-chain data.
+    /// The first call completes the seed as sha256(revealed seed || slot hash)
+    /// with the hash of the first slot from `lottery_seed_slot` on. If that
+    /// hash has left the SlotHashes sysvar, it waits for a new slot instead.
+    pub fn draw_winners(ctx: Context<DrawWinners>, max_draws: u32) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let draw = &mut ctx.accounts.lottery_draw;
+
+        require!(!pool.lottery_drawn, PresaleError::LotteryAlreadyDrawn);
+        require!(pool.unindexed_records == 0, PresaleError::RecordsNeedMigration);
+
+        if !pool.lottery_seeded() {
+            let slot_hashes = ctx.accounts.slot_hashes.try_borrow_data()?;
+            match find_slot_hash(&slot_hashes, pool.lottery_seed_slot) {
+                SlotHashLookup::Found(slot, slot_hash) => {
+                    pool.lottery_seed = hashv(&[&pool.lottery_seed, &slot_hash]).to_bytes();
+                    pool.lottery_slot_hash = slot_hash;
+
+                    emit!(LotterySeeded {
+                        pool: pool.key(),
+                        slot,
+                        slot_hash,
+                        seed: pool.lottery_seed,
+                        header: EventHeader::now()?,
+                    });
+                }
+                SlotHashLookup::Pending => return err!(PresaleError::LotterySeedPending),
+                SlotHashLookup::Expired => {
+                    let expired_slot = pool.lottery_seed_slot;
+                    pool.lottery_seed_slot = Clock::get()?.slot + LOTTERY_SEED_DELAY;
+
+                    emit!(LotterySeedRescheduled {
+                        pool: pool.key(),
+                        expired_slot,
+                        seed_slot: pool.lottery_seed_slot,
+                        header: EventHeader::now()?,
+                    });
+                    return Ok(());
+                }
+            }
+        }
+
+        for _ in 0..max_draws {
+            if draw.is_complete() {
+                break;
+            }
+            let index = lottery_pick(&pool.lottery_seed, draw.nonce, pool.depositor_count);
+            draw.nonce += 1;
+            if !draw.is_selected(index) {
+                draw.set_selected(index);
+                draw.selected += 1;
+            }
+        }
+
+        if draw.is_complete() {
+            pool.lottery_drawn = true;
+
+            emit!(LotteryDrawn {
+                pool: pool.key(),
+                seed: pool.lottery_seed,
+                winners: pool.lottery_winner_count(),
+                picks: draw.nonce,
+                header: EventHeader::now()?,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn refund_losing_ticket(ctx: Context<RefundLosingTicket>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let depositor_record = &mut ctx.accounts.depositor_record;
+
+        require!(pool.lottery_drawn, PresaleError::LotteryNotDrawn);
+        require!(
+            !ctx.accounts.lottery_draw.is_winner(depositor_record.index),
+            PresaleError::WinningTicket
+        );
+        require!(!depositor_record.is_refunded, PresaleError::AlreadyRefunded);
+
+        let amount = depositor_record.amount;
+
+        // Transfer SOL from pool vault back to depositor
+        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.depositor.try_borrow_mut_lamports()? += amount;
+
+        depositor_record.is_refunded = true;
+        pool.total_refunded += amount;
+
+        emit!(DepositRefunded {
+            pool: pool.key(),
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Opens a vote on releasing `amount` of a governed pool's escrow to the
+    /// owner. One proposal runs at a time, until `execute_proposal`.
+    pub fn open_release_proposal(ctx: Context<OpenReleaseProposal>, amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let now = pool.now(&Clock::get()?);
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.is_governed(), PresaleError::NotGovernedPool);
+        require!(now >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        require!(!pool.release_rejected, PresaleError::ReleaseRejected);
+        require!(now < pool.governance_deadline(), PresaleError::GovernanceWindowClosed);
+        require!(!pool.proposal_open, PresaleError::ProposalAlreadyOpen);
+        require!(amount > 0 && amount <= pool.escrow_remaining(), PresaleError::InvalidReleaseAmount);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.pool = pool.key();
+        proposal.id = pool.proposal_count;
+        proposal.amount = amount;
+        proposal.voting_end = now.saturating_add(pool.voting_period);
+        proposal.bump = ctx.bumps.proposal;
+
+        pool.proposal_count += 1;
+        pool.proposal_open = true;
+
+        emit!(ReleaseProposed {
+            pool: pool.key(),
+            proposal: proposal.key(),
+            id: proposal.id,
+            amount,
+            voting_end: proposal.voting_end,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Votes for or against a release with the weight of the voter's deposit.
+    pub fn cast_vote(ctx: Context<CastVote>, approve: bool) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let proposal = &mut ctx.accounts.proposal;
+        let depositor_record = &ctx.accounts.depositor_record;
+
+        require!(pool.now(&Clock::get()?) < proposal.voting_end, PresaleError::VotingClosed);
+        require!(!depositor_record.is_refunded, PresaleError::AlreadyRefunded);
+        require!(depositor_record.amount > 0, PresaleError::NoVotingWeight);
+
+        let weight = depositor_record.amount;
+        if approve {
+            proposal.votes_for += weight;
+        } else {
+            proposal.votes_against += weight;
+        }
+
+        let vote = &mut ctx.accounts.vote;
+        vote.proposal = proposal.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.weight = weight;
+        vote.approve = approve;
+
+        emit!(VoteCast {
+            pool: pool.key(),
+            proposal: proposal.key(),
+            voter: vote.voter,
+            approve,
+            weight,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Settles a proposal once voting has ended. Anyone can call it. An
+    /// approved release pays the owner; a rejected one opens pro-rata
+    /// refunds of the remaining escrow through `refund_escrow`.
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(pool.now(&Clock::get()?) >= proposal.voting_end, PresaleError::VotingNotEnded);
+        require!(!proposal.executed, PresaleError::ProposalAlreadyExecuted);
+
+        proposal.executed = true;
+        proposal.approved = proposal.passed(pool);
+        pool.proposal_open = false;
+
+        let stats = &mut ctx.accounts.stats;
+        if proposal.approved {
+            **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= proposal.amount;
+            **ctx.accounts.owner.try_borrow_mut_lamports()? += proposal.amount;
+
+            pool.total_released += proposal.amount;
+            stats.total_claimed += proposal.amount;
+            if pool.escrow_remaining() == 0 {
+                stats.settle_pool(pool)?;
+            }
+        } else {
+            pool.release_rejected = true;
+            stats.settle_pool(pool)?;
+        }
+
+        emit!(ProposalExecuted {
+            pool: pool.key(),
+            proposal: proposal.key(),
+            id: proposal.id,
+            approved: proposal.approved,
+            amount: proposal.amount,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the depositor's share of the escrow left after depositors
+    /// rejected a release or halted the stream, or after the owner let the
+    /// governance window pass.
+    pub fn refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let depositor_record = &mut ctx.accounts.depositor_record;
+        let now = pool.now(&Clock::get()?);
+
+        require!(pool.release_rejected || pool.governance_lapsed(now), PresaleError::RefundNotAvailable);
+        require!(!depositor_record.is_refunded, PresaleError::AlreadyRefunded);
+
+        // The first refund after the window ends the pool's releases, like a rejection
+        if !pool.release_rejected {
+            ctx.accounts.stats.settle_pool(pool)?;
+            pool.release_rejected = true;
+        }
+
+        // Earlier releases are shared by every depositor in proportion to their deposit
+        let amount = (depositor_record.amount as u128 * pool.escrow_remaining() as u128
+            / pool.total_raised as u128) as u64;
+
+        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.depositor.try_borrow_mut_lamports()? += amount;
+
+        depositor_record.is_refunded = true;
+        pool.total_refunded += amount;
+
+        emit!(DepositRefunded {
+            pool: pool.key(),
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the owner of a streaming pool everything accrued since the last
+    /// withdrawal.
+    pub fn withdraw_stream(ctx: Context<ClaimFunds>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let now = pool.now(&Clock::get()?);
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.is_streaming(), PresaleError::NotStreamingPool);
+        require!(now >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        require!(!pool.release_rejected, PresaleError::StreamHalted);
+
+        let amount = pool.stream_withdrawable(now);
+        require!(amount > 0, PresaleError::NothingToWithdraw);
+
+        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.owner.try_borrow_mut_lamports()? += amount;
+
+        pool.total_released += amount;
+
+        let stats = &mut ctx.accounts.stats;
+        stats.total_claimed += amount;
+        if pool.escrow_remaining() == 0 {
+            stats.settle_pool(pool)?;
+        }
+
+        emit!(StreamWithdrawn {
+            pool: pool.key(),
+            owner: pool.owner,
+            amount,
+            total_released: pool.total_released,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Votes to halt a streaming pool's payout with the weight of the voter's
+    /// deposit. The vote that brings the total to the pool's `halt_bps` of
+    /// the raise halts the stream: the owner is paid what has accrued so far
+    /// and the rest is refunded pro rata through `refund_escrow`.
+    pub fn vote_halt(ctx: Context<VoteHalt>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let depositor_record = &ctx.accounts.depositor_record;
+        let now = pool.now(&Clock::get()?);
+
+        require!(pool.is_streaming(), PresaleError::NotStreamingPool);
+        require!(now >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(pool.soft_cap_reached(), PresaleError::SoftCapNotReached);
+        require!(!pool.release_rejected, PresaleError::StreamHalted);
+        require!(pool.stream_accrued(now) < pool.total_raised, PresaleError::StreamEnded);
+        require!(!depositor_record.is_refunded, PresaleError::AlreadyRefunded);
+        require!(depositor_record.amount > 0, PresaleError::NoVotingWeight);
+
+        let weight = depositor_record.amount;
+        pool.halt_votes += weight;
+
+        let vote = &mut ctx.accounts.vote;
+        vote.proposal = pool.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.weight = weight;
+        vote.approve = true;
+
+        emit!(HaltVoteCast {
+            pool: pool.key(),
+            voter: vote.voter,
+            weight,
+            halt_votes: pool.halt_votes,
+            header: EventHeader::now()?,
+        });
+
+        if pool.halt_votes as u128 * 10_000 >= pool.halt_bps as u128 * pool.total_raised as u128 {
+            let paid_to_owner = pool.stream_withdrawable(now);
+            **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= paid_to_owner;
+            **ctx.accounts.owner.try_borrow_mut_lamports()? += paid_to_owner;
+
+            pool.total_released += paid_to_owner;
+            pool.release_rejected = true;
+
+            let stats = &mut ctx.accounts.stats;
+            stats.total_claimed += paid_to_owner;
+            stats.settle_pool(pool)?;
+
+            emit!(StreamHalted {
+                pool: pool.key(),
+                paid_to_owner,
+                refundable: pool.escrow_remaining(),
+                halt_votes: pool.halt_votes,
+                header: EventHeader::now()?,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Turns the pool into a continuous sale along a bonding curve. The owner
+    /// funds the token vault with `supply` tokens, which `buy_tokens` then
+    /// sells at a price rising from `start_price` to `end_price` lamports per
+    /// whole token, linearly or exponentially. Curve pools take no deposits,
+    /// lotteries, governance or soft cap.
+    pub fn configure_curve(
+        ctx: Context<ConfigureCurve>,
+        kind: CurveKind,
+        start_price: u64,
+        end_price: u64,
+        supply: u64,
+        sell_back: bool,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let decimals = ctx.accounts.token_mint.decimals;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.depositor_count == 0, PresaleError::PoolAlreadyActive);
+        // Exponential curves need a growth factor above one to take its logarithm
+        require!(
+            start_price > 0
+                && supply > 0
+                && decimals <= CURVE_MAX_DECIMALS
+                && match kind {
+                    CurveKind::None => false,
+                    CurveKind::Linear => end_price >= start_price,
+                    CurveKind::Exponential => end_price > start_price,
+                }
+                && pool.soft_cap == 0
+                && !pool.is_lottery()
+                && !pool.is_governed()
+                && !pool.is_streaming()
+                && !pool.has_bonus()
+                && !pool.has_usd_limits(),
+            PresaleError::InvalidCurveConfig
+        );
+
+        pool.curve_kind = kind;
+        pool.curve_start_price = start_price;
+        pool.curve_end_price = end_price;
+        pool.curve_supply = supply;
+        pool.curve_decimals = decimals;
+        pool.curve_sell_back = sell_back;
+        pool.token_mint = ctx.accounts.token_mint.key();
+
+        // Every partial cost is below the full one, so this bounds all later math
+        require!(pool.curve_cost(supply, Rounding::Up).is_some(), PresaleError::InvalidCurveConfig);
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.owner_token_account.to_account_info(),
+                to: ctx.accounts.pool_token_vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token::transfer(cpi_context, supply)?;
+
+        emit!(CurveConfigured {
+            pool: pool.key(),
+            token_mint: pool.token_mint,
+            kind,
+            start_price,
+            end_price,
+            supply,
+            sell_back,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Buys `amount` tokens off the curve for at most `max_cost` lamports.
+    /// The cost must fall within the pool's deposit limits.
+    pub fn buy_tokens(ctx: Context<BuyTokens>, amount: u64, max_cost: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        let now = pool.now(&Clock::get()?);
+        let phase = pool.phase(now);
+
+        require!(pool.is_curve(), PresaleError::NotCurvePool);
+        require!(now < pool.expiry_timestamp, PresaleError::PoolExpired);
+        require!(amount > 0, PresaleError::InvalidTokenAmount);
+        require!(amount <= pool.curve_supply - pool.curve_sold, PresaleError::CurveSoldOut);
+
+        let cost = pool.curve_buy_cost(amount).ok_or(PresaleError::CurveMathOverflow)?;
+        require!(cost <= max_cost, PresaleError::SlippageExceeded);
+        require!(cost >= pool.min_deposit, PresaleError::DepositTooLow);
+        require!(cost <= pool.max_deposit, PresaleError::DepositTooHigh);
+        require!(
+            pool.hard_cap == 0 || pool.curve_reserve() + cost <= pool.hard_cap,
+            PresaleError::HardCapExceeded
+        );
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.pool_vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, cost)?;
+
+        // The token vault is owned by the pool PDA
+        let seeds: &[&[u8]] = &[b"pool", pool.owner.as_ref(), &[pool.bump]];
+        let signer_seeds = &[seeds];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.pool_token_vault.to_account_info(),
+                to: ctx.accounts.buyer_token_account.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(cpi_context, amount)?;
+
+        pool.curve_sold += amount;
+        pool.total_raised += cost;
+        ctx.accounts.stats.total_raised += cost;
+
+        emit!(TokensBought {
+            pool: pool.key(),
+            buyer: ctx.accounts.buyer.key(),
+            amount,
+            cost,
+            tokens_sold: pool.curve_sold,
+            header: EventHeader::now()?,
+        });
+        emit_phase_change(pool, phase, now)?;
+
+        Ok(())
+    }
+
+    /// Sells `amount` tokens back to the curve for at least `min_proceeds`
+    /// lamports, on pools that allow it and until expiry.
+    pub fn sell_tokens(ctx: Context<SellTokens>, amount: u64, min_proceeds: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        require!(pool.is_curve(), PresaleError::NotCurvePool);
+        require!(pool.curve_sell_back, PresaleError::SellBackDisabled);
+        require!(pool.now(&Clock::get()?) < pool.expiry_timestamp, PresaleError::PoolExpired);
+        require!(amount > 0 && amount <= pool.curve_sold, PresaleError::InvalidTokenAmount);
+
+        let proceeds = pool.curve_sell_proceeds(amount).ok_or(PresaleError::CurveMathOverflow)?;
+        require!(proceeds >= min_proceeds, PresaleError::SlippageExceeded);
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.seller_token_account.to_account_info(),
+                to: ctx.accounts.pool_token_vault.to_account_info(),
+                authority: ctx.accounts.seller.to_account_info(),
+            },
+        );
+        token::transfer(cpi_context, amount)?;
+
+        **ctx.accounts.pool_vault.try_borrow_mut_lamports()? -= proceeds;
+        **ctx.accounts.seller.try_borrow_mut_lamports()? += proceeds;
+
+        pool.curve_sold -= amount;
+        pool.total_refunded += proceeds;
+
+        emit!(TokensSold {
+            pool: pool.key(),
+            seller: ctx.accounts.seller.key(),
+            amount,
+            proceeds,
+            tokens_sold: pool.curve_sold,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the tokens left in an expired curve pool's vault to the owner.
+    /// The raised SOL is claimed with `claim_funds` as usual.
+    pub fn withdraw_unsold_tokens(ctx: Context<WithdrawUnsoldTokens>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let amount = ctx.accounts.pool_token_vault.amount;
+
+        require!(pool.owner == ctx.accounts.owner.key(), PresaleError::Unauthorized);
+        require!(pool.is_curve(), PresaleError::NotCurvePool);
+        require!(pool.now(&Clock::get()?) >= pool.expiry_timestamp, PresaleError::PoolNotExpired);
+        require!(amount > 0, PresaleError::InvalidTokenAmount);
+
+        let seeds: &[&[u8]] = &[b"pool", pool.owner.as_ref(), &[pool.bump]];
+        let signer_seeds = &[seeds];
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.pool_token_vault.to_account_info(),
+                to: ctx.accounts.owner_token_account.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(cpi_context, amount)?;
+
+        emit!(UnsoldTokensWithdrawn {
+            pool: pool.key(),
+            amount,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the stats account after an upgrade from a program version
+    /// without it. `create_pool` and `deposit_sol` create it as they count,
+    /// but claims, refunds and settlements of existing pools need it first.
+    /// Anyone can pay for it.
+    pub fn init_stats(ctx: Context<InitStats>) -> Result<()> {
+        ctx.accounts.stats.bump = ctx.bumps.stats;
+
+        emit!(StatsInitialized {
+            stats: ctx.accounts.stats.key(),
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Grows a pool created by an older program version to the current
+    /// layout. Anyone can pay for it; new fields start out zeroed. Pools of
+    /// the first layout then count their records as unindexed.
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let info = ctx.accounts.pool.to_account_info();
+        let pool = &mut ctx.accounts.pool;
+        let from_version = pool.version;
+        require!(from_version < Pool::VERSION, PresaleError::AlreadyMigrated);
+
+        // Grown by hand, as the original length tells the first layout apart
+        if Pool::predates_indexes(&info) {
+            pool.unindexed_records = pool.depositor_count;
+        }
+        fund_rent(&info, &ctx.accounts.payer, &ctx.accounts.system_program, Pool::SPACE)?;
+        info.resize(Pool::SPACE)?;
+        pool.counted = pool.is_counted();
+        pool.version = Pool::VERSION;
+
+        emit!(AccountMigrated {
+            pool: pool.key(),
+            account: pool.key(),
+            from_version,
+            to_version: Pool::VERSION,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    /// Grows a depositor record created by an older program version to the
+    /// current layout. Records from before indexes take the pool's next
+    /// index, so their pool must be migrated first.
+    pub fn migrate_depositor_record(ctx: Context<MigrateDepositorRecord>) -> Result<()> {
+        let info = ctx.accounts.depositor_record.to_account_info();
+        let depositor_record = &mut ctx.accounts.depositor_record;
+        let from_version = depositor_record.version;
+        require!(from_version < DepositorRecord::VERSION, PresaleError::AlreadyMigrated);
+
+        if info.data_len() <= DepositorRecord::UNINDEXED_SPACE {
+            depositor_record.take_index(&mut ctx.accounts.pool)?;
+        }
+        fund_rent(&info, &ctx.accounts.payer, &ctx.accounts.system_program, DepositorRecord::SPACE)?;
+        info.resize(DepositorRecord::SPACE)?;
+        depositor_record.version = DepositorRecord::VERSION;
+
+        emit!(AccountMigrated {
+            pool: depositor_record.pool,
+            account: depositor_record.key(),
+            from_version,
+            to_version: DepositorRecord::VERSION,
+            header: EventHeader::now()?,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_pool_status(ctx: Context<GetPoolStatus>) -> Result<PoolStatus> {
+        let pool = &ctx.accounts.pool;
+        let clock = Clock::get()?;
+        let now = pool.now(&clock);
+        let phase = pool.phase(now);
+        let (expiry_timestamp, expiry_slot) = pool.expiry_in_both_bases(&clock);
+
+        Ok(PoolStatus {
+            phase,
+            seconds_remaining: expiry_timestamp.saturating_sub(clock.unix_timestamp).max(0),
+            total_raised: pool.total_raised,
+            soft_cap: pool.soft_cap,
+            hard_cap: pool.hard_cap,
+            soft_cap_bps: ratio_bps(pool.total_raised, pool.soft_cap),
+            hard_cap_bps: ratio_bps(pool.total_raised, pool.hard_cap),
+            depositor_count: pool.depositor_count,
+            owner_can_claim: matches!(phase, PoolPhase::Succeeded | PoolPhase::Finalized)
+                && !pool.is_claimed
+                && !pool.is_governed()
+                && !pool.is_streaming()
+                && (!pool.is_lottery() || pool.lottery_drawn),
+            refunds_open: phase == PoolPhase::Failed,
+            whitelist_only: pool.whitelist_active(now),
+            slots_remaining: expiry_slot.saturating_sub(clock.slot),
+        })
+    }
+
+    /// Checks a pool's bookkeeping against its vault, for monitors: the vault
+    /// must cover the refunds and owner payouts still owed, the claimed flag
+    /// must match how the vault was paid out, and the counters must agree.
+    /// Anyone can call it; a failed check also emits `AuditFailed`. Finalized
+    /// pools need their claim bitmap.
+    pub fn audit_pool(ctx: Context<AuditPool>) -> Result<AuditReport> {
+        let pool = &ctx.accounts.pool;
+        let now = pool.now(&Clock::get()?);
+        let vault_lamports = ctx.accounts.pool_vault.lamports();
+        let liabilities = pool.vault_liabilities();
+
+        // Anyone can send lamports to the vault, so a claimed vault need only still cover
+        // what it owes: nothing for pro-rata pools, the losing tickets for lotteries
+        let claim_consistent = !pool.is_claimed
+            || (pool.soft_cap_reached()
+                && !pool.is_governed()
+                && !pool.is_streaming()
+                && (!pool.is_lottery() || pool.lottery_drawn)
+                && vault_lamports >= liabilities);
+
+        let bitmap_matches = ctx
+            .accounts
+            .claim_bitmap
+            .as_ref()
+            .is_some_and(|bitmap| bitmap.bits.len() == ClaimBitmap::byte_len(pool.depositor_count));
+        // Curve pools cap their reserve, which sell-backs shrink
+        let capped = if pool.is_curve() {
+            pool.total_raised.saturating_sub(pool.total_refunded)
+        } else {
+            pool.total_raised
+        };
+        let counters_consistent = pool
+            .total_refunded
+            .checked_add(pool.total_released)
+            .is_some_and(|paid_out| paid_out <= pool.total_raised)
+            && (pool.hard_cap == 0 || capped <= pool.hard_cap)
+            && pool.curve_sold <= pool.curve_supply
+            && (!pool.proposal_open || pool.is_governed())
+            && !(pool.is_governed() && pool.is_streaming())
+            && (!pool.is_streaming() || pool.total_released <= pool.stream_accrued(now))
+            && (pool.halt_votes == 0 || pool.is_streaming())
+            && (pool.total_raised_usd == 0 || pool.has_usd_limits())
+            && (pool.usd_hard_cap == 0 || pool.total_raised_usd <= pool.usd_hard_cap)
+            && (!pool.lottery_drawn || pool.lottery_seed != [0; 32])
+            && (!pool.has_bonus() || pool.total_weighted >= pool.total_raised)
+            && (!pool.is_finalized || bitmap_matches);
+
+        let report = AuditReport {
+            vault_lamports,
+            liabilities,
+            vault_solvent: vault_lamports >= liabilities,
+            claim_consistent,
+            counters_consistent,
+        };
+        if !report.passed() {
+            emit!(AuditFailed {
+                pool: pool.key(),
+                vault_lamports,
+                liabilities,
+                vault_solvent: report.vault_solvent,
+                claim_consistent,
+                counters_consistent,
+                header: EventHeader::now()?,
+            });
+        }
+
+        Ok(report)
+    }
+
+    pub fn get_depositor_info(ctx: Context<GetDepositorInfo>) -> Result<DepositorInfo> {
+        let pool = &ctx.accounts.pool;
+        let depositor_record = &ctx.accounts.depositor_record;
+        let clock = Clock::get()?;
+        let phase = pool.phase(pool.now(&clock));
+
+        // Losing lottery tickets carry no allocation and become refundable
+        let lost_lottery = match &ctx.accounts.lottery_draw {
+            Some(draw) if pool.lottery_drawn => !draw.is_winner(depositor_record.index),
+            _ => false,
+        };
+        let entitlement = if lost_lottery { 0 } else { pool.entitlement(depositor_record.allocation_weight()) };
+
+        Ok(DepositorInfo {
+            depositor: depositor_record.depositor,
+            amount: depositor_record.amount,
+            share_bps: ratio_bps(depositor_record.amount, pool.total_raised),
+            entitlement,
+            tokens_claimed: depositor_record.tokens_claimed,
+            is_refunded: depositor_record.is_refunded,
+            can_claim_tokens: phase == PoolPhase::Finalized
+                && entitlement > 0
+                && depositor_record.tokens_claimed == 0,
+            can_refund: (phase == PoolPhase::Failed
+                || lost_lottery
+                || pool.release_rejected
+                || pool.governance_lapsed(pool.now(&clock)))
+                && !depositor_record.is_refunded,
+            won_lottery: pool.lottery_drawn && !lost_lottery,
+            weighted_amount: depositor_record.allocation_weight(),
+        })
+    }
+
+    pub fn get_launchpad_stats(ctx: Context<GetLaunchpadStats>) -> Result<LaunchpadStats> {
+        Ok((*ctx.accounts.stats).clone())
+    }
+
+    pub fn get_pool_info(ctx: Context<GetPoolInfo>) -> Result<PoolInfo> {
+        let pool = &ctx.accounts.pool;
+        let (expiry_timestamp, expiry_slot) = pool.expiry_in_both_bases(&Clock::get()?);
+        
+        Ok(PoolInfo {
+            owner: pool.owner,
+            expiry_timestamp,
+            total_raised: pool.total_raised,
+            min_deposit: pool.min_deposit,
+            max_deposit: pool.max_deposit,
+            is_claimed: pool.is_claimed,
+            depositor_count: pool.depositor_count,
+            soft_cap: pool.soft_cap,
+            hard_cap: pool.hard_cap,
+            attestation_authority: pool.attestation_authority,
+            time_basis: pool.time_basis,
+            expiry_slot,
+        })
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Pool::SPACE,
+        seeds = [b"pool", owner.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 0,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LaunchpadStats::SPACE,
+        seeds = [b"stats"],
+        bump
+    )]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+    
+    /// CHECK: The beneficiary's `DepositorRecord`; `DepositorRecord::open`
+    /// creates it, or grows one an older version wrote
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), beneficiary.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: UncheckedAccount<'info>,
+
+    /// CHECK: Any wallet; it owns the record and signs claims and refunds,
+    /// and deposits into tiered pools, which lock its stake, and pools with
+    /// a slot gap, which it would otherwise delay
+    pub beneficiary: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+
+    #[account(address = instructions_sysvar::ID)]
+    /// CHECK: Instructions sysvar, read for ed25519 attestations
+    pub instructions: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = LaunchpadStats::SPACE,
+        seeds = [b"stats"],
+        bump
+    )]
+    pub stats: Account<'info, LaunchpadStats>,
+
+    /// Marks the beneficiary as counted in `unique_depositors`
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Participant::SPACE,
+        seeds = [b"participant", beneficiary.key().as_ref()],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// The beneficiary's stake, for pools with tiers
+    #[account(
+        mut,
+        seeds = [b"stake", pool.tier_mint.as_ref(), beneficiary.key().as_ref()],
+        bump = stake_record.bump
+    )]
+    pub stake_record: Option<Account<'info, StakeRecord>>,
+
+    /// A token account of the beneficiary's, for token-holder and NFT gates
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Token Metadata account of the gate token account's mint, for
+    /// NFT gates; `verify_gate` checks its address and owner
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The pool's SOL/USD price account, for pools with USD limits;
+    /// `read_usd_price` checks its address, owner and layout
+    pub price_oracle: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub from_record: Account<'info, DepositorRecord>,
+
+    /// CHECK: The recipient's `DepositorRecord`; `DepositorRecord::open`
+    /// creates it, or grows one an older version wrote
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), recipient.key().as_ref()],
+        bump
+    )]
+    pub to_record: UncheckedAccount<'info>,
+
+    /// CHECK: Any wallet; it owns the transferred position
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(address = instructions_sysvar::ID)]
+    /// CHECK: Instructions sysvar, read for ed25519 attestations
+    pub instructions: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LaunchpadStats::SPACE,
+        seeds = [b"stats"],
+        bump
+    )]
+    pub stats: Account<'info, LaunchpadStats>,
+
+    /// Marks the recipient as counted in `unique_depositors`
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Participant::SPACE,
+        seeds = [b"participant", recipient.key().as_ref()],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// A token account of the recipient's, for token-holder and NFT gates
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Token Metadata account of the gate token account's mint, for
+    /// NFT gates; `verify_gate` checks its address and owner
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigurePool<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureTiers<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    pub stake_mint: Account<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureUsdLimits<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: SOL/USD price account, read according to the oracle kind; left
+    /// out when removing the USD limits
+    pub price_oracle: Option<UncheckedAccount<'info>>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    pub stake_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = staker,
+        space = StakeRecord::SPACE,
+        seeds = [b"stake", stake_mint.key().as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub stake_record: Account<'info, StakeRecord>,
+
+    #[account(
+        init_if_needed,
+        payer = staker,
+        seeds = [b"stake_vault", stake_mint.key().as_ref(), staker.key().as_ref()],
+        bump,
+        token::mint = stake_mint,
+        token::authority = stake_record
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = stake_mint,
+        token::authority = staker
+    )]
+    pub staker_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub staker: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(
+        mut,
+        seeds = [b"stake", stake_record.mint.as_ref(), staker.key().as_ref()],
+        bump = stake_record.bump
+    )]
+    pub stake_record: Account<'info, StakeRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_vault", stake_record.mint.as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = stake_record.mint,
+        token::authority = staker
+    )]
+    pub staker_token_account: Account<'info, TokenAccount>,
+
+    pub staker: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseStake<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), depositor_record.depositor.as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", pool.tier_mint.as_ref(), depositor_record.depositor.as_ref()],
+        bump = stake_record.bump
+    )]
+    pub stake_record: Account<'info, StakeRecord>,
+}
+
+#[derive(Accounts)]
+pub struct SetPoolMetadata<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PoolMetadata::SPACE,
+        seeds = [b"metadata", pool.key().as_ref()],
+        bump
+    )]
+    pub metadata: Account<'info, PoolMetadata>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFunds<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizePool<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
         payer = owner,
-        space = Pool::SPACE,
-        seeds = [b"pool", owner.key().as_ref()],
+        space = ClaimBitmap::space(pool.depositor_count),
+        seeds = [b"claims", pool.key().as_ref()],
+        bump
+    )]
+    pub claim_bitmap: Account<'info, ClaimBitmap>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = pool
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimTokens<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"claims", pool.key().as_ref()],
+        bump
+    )]
+    pub claim_bitmap: Account<'info, ClaimBitmap>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(
+        mut,
+        token::mint = pool.token_mint,
+        token::authority = depositor
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+
+    pub depositor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeBatch<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"claims", pool.key().as_ref()],
+        bump
+    )]
+    pub claim_bitmap: Account<'info, ClaimBitmap>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    /// Required for lottery pools, whose losing tickets get nothing
+    #[account(
+        seeds = [b"lottery", pool.key().as_ref()],
+        bump
+    )]
+    pub lottery_draw: Option<Account<'info, LotteryDraw>>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct SettleFailedPool<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct RevealLottery<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = LotteryDraw::space(pool.depositor_count),
+        seeds = [b"lottery", pool.key().as_ref()],
+        bump
+    )]
+    pub lottery_draw: Account<'info, LotteryDraw>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DrawWinners<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery", pool.key().as_ref()],
+        bump
+    )]
+    pub lottery_draw: Account<'info, LotteryDraw>,
+
+    /// CHECK: The SlotHashes sysvar, parsed by `find_slot_hash`; too large to deserialize
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundLosingTicket<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        seeds = [b"lottery", pool.key().as_ref()],
+        bump
+    )]
+    pub lottery_draw: Account<'info, LotteryDraw>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenReleaseProposal<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = ReleaseProposal::SPACE,
+        seeds = [b"proposal", pool.key().as_ref(), &pool.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, ReleaseProposal>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CastVote<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [b"proposal", pool.key().as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, ReleaseProposal>,
+
+    #[account(
+        seeds = [b"depositor", pool.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    /// Exists once per voter and proposal, so nobody votes twice
+    #[account(
+        init,
+        payer = voter,
+        space = Vote::SPACE,
+        seeds = [b"vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote: Account<'info, Vote>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = pool,
+        seeds = [b"proposal", pool.key().as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, ReleaseProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    /// Receives approved releases
+    #[account(mut, address = pool.owner)]
+    pub owner: SystemAccount<'info>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct RefundEscrow<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"depositor", pool.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct VoteHalt<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"depositor", pool.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    /// Exists once per voter and pool, so nobody votes twice
+    #[account(
+        init,
+        payer = voter,
+        space = Vote::SPACE,
+        seeds = [b"halt_vote", pool.key().as_ref(), voter.key().as_ref()],
         bump
     )]
+    pub vote: Account<'info, Vote>,
+
+    /// Receives the accrued stream when the vote halts it
+    #[account(mut, address = pool.owner)]
+    pub owner: SystemAccount<'info>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureCurve<'info> {
+    #[account(mut)]
     pub pool: Account<'info, Pool>,
-    
+
+    pub token_mint: Account<'info, Mint>,
+
     #[account(
         init,
         payer = owner,
-        space = 0,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = pool
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTokens<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
         seeds = [b"vault", pool.key().as_ref()],
         bump
     )]
     /// CHECK: This is a PDA used as a vault to hold SOL
     pub pool_vault: AccountInfo<'info>,
-    
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    /// Receives the tokens; any account of the pool's mint
+    #[account(mut, token::mint = pool.token_mint)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    #[account(mut, seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+#[derive(Accounts)]
+pub struct SellTokens<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as a vault to hold SOL
+    pub pool_vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = pool.token_mint,
+        token::authority = seller
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
     #[account(mut)]
+    pub seller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnsoldTokens<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = pool.token_mint,
+        token::authority = owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
     pub owner: Signer<'info>,
-    
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = LaunchpadStats::SPACE,
+        seeds = [b"stats"],
+        bump
+    )]
+    pub stats: Account<'info, LaunchpadStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateDepositorRecord<'info> {
+    #[account(mut, has_one = pool)]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    /// Hands out indexes to records from before them
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct DepositSol<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    /// CHECK: This is a PDA used as a vault to hold SOL
-    pub pool_vault: AccountInfo<'info>,
-    
-    #[account(
-        init_if_needed,
-        payer = depositor,
-        space = DepositorRecord::SPACE,
-        seeds = [b"depositor", pool.key().as_ref(), depositor.key().as_ref()],
-        bump
-    )]
-    pub depositor_record: Account<'info, DepositorRecord>,
-    
-    #[account(mut)]
-    pub depositor: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+#[derive(Accounts)]
+pub struct GetPoolStatus<'info> {
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct AuditPool<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        seeds = [b"vault", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Only the vault's lamports are read
+    pub pool_vault: AccountInfo<'info>,
+
+    /// Only for finalized pools
+    #[account(
+        seeds = [b"claims", pool.key().as_ref()],
+        bump
+    )]
+    pub claim_bitmap: Option<Account<'info, ClaimBitmap>>,
+}
+
+#[derive(Accounts)]
+pub struct GetDepositorInfo<'info> {
+    pub pool: Account<'info, Pool>,
+
+    #[account(has_one = pool)]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    /// Only for drawn lottery pools
+    #[account(
+        seeds = [b"lottery", pool.key().as_ref()],
+        bump
+    )]
+    pub lottery_draw: Option<Account<'info, LotteryDraw>>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolInfo<'info> {
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct GetLaunchpadStats<'info> {
+    #[account(seeds = [b"stats"], bump = stats.bump)]
+    pub stats: Account<'info, LaunchpadStats>,
+}
+
+/// Implements the account traits `#[account]` would derive for an
+/// append-only layout with a `version` field. Accounts written by an older
+/// program version are shorter; they read as if the missing fields were zero
+/// and can be written back as long as those fields stay zero. Storing
+/// anything in them needs a migration that grows the account first.
+macro_rules! versioned_account {
+    ($name:ident, $discriminator:expr) => {
+        impl Discriminator for $name {
+            /// sha256("account:<name>")[..8], as `#[account]` derives it
+            const DISCRIMINATOR: &'static [u8] = &$discriminator;
+        }
+
+        impl Owner for $name {
+            fn owner() -> Pubkey {
+                crate::ID
+            }
+        }
+
+        impl AccountSerialize for $name {
+            fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+                let mut data = Self::DISCRIMINATOR.to_vec();
+                AnchorSerialize::serialize(self, &mut data)
+                    .map_err(|_| error!(ErrorCode::AccountDidNotSerialize))?;
+
+                // Fill what the account has room for; the rest must be unset fields
+                let mut written = 0;
+                while written < data.len() {
+                    match writer.write(&data[written..]) {
+                        Ok(0) => break,
+                        Ok(n) => written += n,
+                        Err(_) => return err!(ErrorCode::AccountDidNotSerialize),
+                    }
+                }
+                require!(data[written..].iter().all(|&b| b == 0), PresaleError::AccountNeedsMigration);
+                Ok(())
+            }
+        }
+
+        impl AccountDeserialize for $name {
+            fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+                if buf.len() < Self::DISCRIMINATOR.len() {
+                    return err!(ErrorCode::AccountDiscriminatorNotFound);
+                }
+                if &buf[..Self::DISCRIMINATOR.len()] != Self::DISCRIMINATOR {
+                    return Err(error!(ErrorCode::AccountDiscriminatorMismatch).with_account_name(stringify!($name)));
+                }
+                Self::try_deserialize_unchecked(buf)
+            }
+
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+                let mut data = buf[Self::DISCRIMINATOR.len()..].to_vec();
+                data.resize(data.len().max(Self::SPACE - Self::DISCRIMINATOR.len()), 0);
+                let account: Self = AnchorDeserialize::deserialize(&mut data.as_slice())
+                    .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+                require!(account.version <= Self::VERSION, PresaleError::UnsupportedAccountVersion);
+                Ok(account)
+            }
+        }
+    };
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Pool {
+    pub owner: Pubkey,
+    /// Unix timestamp, or slot for slot-based pools
+    pub expiry_timestamp: i64,
+    pub total_raised: u64,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub is_claimed: bool,
+    pub depositor_count: u32,
+    pub bump: u8,
+    pub token_mint: Pubkey,
+    pub total_tokens: u64,
+    pub merkle_root: [u8; 32],
+    pub is_finalized: bool,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub total_refunded: u64,
+    pub attestation_authority: Pubkey,
+    pub whitelist_root: [u8; 32],
+    /// In the pool's time basis, like `expiry_timestamp`
+    pub whitelist_end: i64,
+    pub min_slot_gap: u64,
+    pub reject_cpi: bool,
+    pub lottery_winners: u32,
+    pub lottery_commitment: [u8; 32],
+    pub lottery_entropy: [u8; 32],
+    pub lottery_seed: [u8; 32],
+    pub lottery_drawn: bool,
+    /// Layout version; zero for pools created before versioning
+    pub version: u8,
+    pub time_basis: TimeBasis,
+    /// Staked token sizing allocations (default = no tiers)
+    pub tier_mint: Pubkey,
+    pub tier_count: u8,
+    /// Ascending stake thresholds, the first `tier_count` entries used
+    pub tier_min_stakes: [u64; 4],
+    /// Maximum total deposit of a wallet in the matching tier
+    pub tier_max_allocations: [u64; 4],
+    /// Share of the raise that must vote for a proposal to count (0 = no governance)
+    pub quorum_bps: u16,
+    /// Share of the cast votes that must approve a release
+    pub approval_bps: u16,
+    /// Length of each vote in the pool's time basis; zero leaves claim_funds to the owner
+    pub voting_period: i64,
+    pub proposal_count: u32,
+    pub proposal_open: bool,
+    /// Escrow paid to the owner through approved proposals or stream withdrawals
+    pub total_released: u64,
+    /// Set when depositors vote a release down or halt the stream; the rest of the escrow is refunded
+    pub release_rejected: bool,
+    /// Bonding-curve sale of `token_mint` (None = deposit pool)
+    pub curve_kind: CurveKind,
+    /// Lamports per whole token at the start and the end of the curve
+    pub curve_start_price: u64,
+    pub curve_end_price: u64,
+    /// Tokens for sale along the curve, in base units
+    pub curve_supply: u64,
+    /// Tokens currently held by buyers, the curve's position
+    pub curve_sold: u64,
+    pub curve_decimals: u8,
+    pub curve_sell_back: bool,
+    /// Holding required of depositors (None = ungated)
+    pub gate_kind: GateKind,
+    /// SPL mint for token-holder gates, collection mint for NFT gates
+    pub gate_mint: Pubkey,
+    pub gate_min_amount: u64,
+    /// Early-bird bonus steps (0 = no bonus)
+    pub bonus_count: u8,
+    /// Ascending step ends in the pool's time basis, the first `bonus_count` entries used
+    pub bonus_ends: [i64; 4],
+    /// Extra allocation weight of deposits before the matching end
+    pub bonus_bps: [u16; 4],
+    /// Sum of the depositors' weighted amounts, the token split's denominator in bonus pools
+    pub total_weighted: u64,
+    /// Length of the raise's payout from expiry in the pool's time basis (0 = claim_funds)
+    pub stream_duration: i64,
+    /// Share of the raise whose votes halt the stream
+    pub halt_bps: u16,
+    /// Deposited lamports behind halt votes
+    pub halt_votes: u64,
+    /// Format of `price_oracle` (None = lamport limits only)
+    pub oracle_kind: OracleKind,
+    /// SOL/USD price account valuing deposits against the USD limits
+    pub price_oracle: Pubkey,
+    /// Oldest price deposits accept, in seconds
+    pub max_price_age: i64,
+    /// USD limits in micro-USD; zero maximums and caps are unlimited
+    pub usd_min_deposit: u64,
+    pub usd_max_deposit: u64,
+    pub usd_soft_cap: u64,
+    pub usd_hard_cap: u64,
+    /// Deposits valued at the price of their deposit, in micro-USD
+    pub total_raised_usd: u64,
+    /// Set once the pool stopped counting towards `active_pools`
+    pub settled: bool,
+    /// Slot whose hash completes the revealed lottery seed (0 = not revealed)
+    pub lottery_seed_slot: u64,
+    /// Hash of the slot mixed into `lottery_seed`, once the draw started
+    pub lottery_slot_hash: [u8; 32],
+    /// Depositor records from before indexes that `migrate_depositor_record`
+    /// has yet to index
+    pub unindexed_records: u32,
+    /// Counted in `LaunchpadStats::active_pools` when created
+    pub counted: bool,
+}
+
+versioned_account!(Pool, [241, 154, 109, 4, 17, 177, 109, 188]);
+
+impl Pool {
+    pub const VERSION: u8 = 12;
+    pub const MAX_TIERS: usize = 4;
+    pub const MAX_BONUS_STEPS: usize = 4;
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1 + 4 + 1 + 32 + 8 + 32 + 1 + 8 + 8 + 8 + 32 + 32 + 8 + 8 + 1
+        + 4 + 32 + 32 + 32 + 1 + 1 + 1 + 32 + 1 + 8 * Self::MAX_TIERS + 8 * Self::MAX_TIERS
+        + 2 + 2 + 8 + 4 + 1 + 8 + 1
+        + 1 + 8 + 8 + 8 + 8 + 1 + 1
+        + 1 + 32 + 8
+        + 1 + 8 * Self::MAX_BONUS_STEPS + 2 * Self::MAX_BONUS_STEPS + 8
+        + 8 + 2 + 8
+        + 1 + 32 + 8 + 8 + 8 + 8 + 8 + 8
+        + 1 + 8 + 32
+        + 4 + 1;
+    /// Length of the first layout, whose depositor records had no index
+    pub const UNINDEXED_SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1 + 4 + 1;
+
+    /// Whether the pool at `info` still has the first layout. Its records
+    /// all read as index 0 until the pool and then each of them is migrated.
+    pub fn predates_indexes(info: &AccountInfo) -> bool {
+        info.data_len() <= Self::UNINDEXED_SPACE
+    }
+
+    /// Current time in the pool's time basis, comparable with its deadlines.
+    pub fn now(&self, clock: &Clock) -> i64 {
+        match self.time_basis {
+            TimeBasis::UnixTimestamp => clock.unix_timestamp,
+            TimeBasis::Slot => clock.slot as i64,
+        }
+    }
+
+    /// A duration in seconds, converted to the pool's time basis.
+    pub fn in_time_basis(&self, seconds: i64) -> i64 {
+        match self.time_basis {
+            TimeBasis::UnixTimestamp => seconds,
+            TimeBasis::Slot => seconds.saturating_mul(1000) / ESTIMATED_SLOT_MS,
+        }
+    }
+
+    /// Expiry as a unix timestamp and as a slot. The one outside the pool's
+    /// time basis is estimated from the current clock.
+    pub fn expiry_in_both_bases(&self, clock: &Clock) -> (i64, u64) {
+        match self.time_basis {
+            TimeBasis::UnixTimestamp => {
+                let seconds = self.expiry_timestamp.saturating_sub(clock.unix_timestamp);
+                let slots = seconds.saturating_mul(1000) / ESTIMATED_SLOT_MS;
+                (self.expiry_timestamp, (clock.slot as i64).saturating_add(slots).max(0) as u64)
+            }
+            TimeBasis::Slot => {
+                let slots = self.expiry_timestamp.saturating_sub(clock.slot as i64);
+                let seconds = slots.saturating_mul(ESTIMATED_SLOT_MS) / 1000;
+                (clock.unix_timestamp.saturating_add(seconds), self.expiry_timestamp as u64)
+            }
+        }
+    }
+
+    pub fn is_lottery(&self) -> bool {
+        self.lottery_winners > 0
+    }
+
+    pub fn is_tiered(&self) -> bool {
+        self.tier_count > 0
+    }
+
+    pub fn is_governed(&self) -> bool {
+        self.voting_period > 0
+    }
+
+    pub fn is_gated(&self) -> bool {
+        self.gate_kind != GateKind::None
+    }
+
+    pub fn is_curve(&self) -> bool {
+        self.curve_kind != CurveKind::None
+    }
+
+    pub fn has_bonus(&self) -> bool {
+        self.bonus_count > 0
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.stream_duration > 0
+    }
+
+    pub fn has_usd_limits(&self) -> bool {
+        self.oracle_kind != OracleKind::None
+    }
+
+    /// Part of the raise streamed to the owner by `now`, withdrawn or not:
+    /// nothing before expiry, all of it `stream_duration` later.
+    pub fn stream_accrued(&self, now: i64) -> u64 {
+        if !self.is_streaming() {
+            return 0;
+        }
+        let elapsed = (now - self.expiry_timestamp).clamp(0, self.stream_duration);
+        (self.total_raised as u128 * elapsed as u128 / self.stream_duration as u128) as u64
+    }
+
+    /// Accrued stream the owner has not withdrawn yet; nothing once halted.
+    pub fn stream_withdrawable(&self, now: i64) -> u64 {
+        if self.release_rejected {
+            return 0;
+        }
+        self.stream_accrued(now).saturating_sub(self.total_released)
+    }
+
+    /// Bonus of a deposit made at `now`: the first step not yet ended, or
+    /// nothing once they all have.
+    pub fn bonus_bps_at(&self, now: i64) -> u16 {
+        let steps = self.bonus_count as usize;
+        self.bonus_ends[..steps]
+            .iter()
+            .zip(&self.bonus_bps[..steps])
+            .find(|(&end, _)| now < end)
+            .map_or(0, |(_, &bps)| bps)
+    }
+
+    /// Lamports backing the tokens buyers hold: purchases minus sell-backs.
+    pub fn curve_reserve(&self) -> u64 {
+        self.total_raised - self.total_refunded
+    }
+
+    /// Cost of the first `sold` tokens of the curve in lamports.
+    pub fn curve_cost(&self, sold: u64, rounding: Rounding) -> Option<u64> {
+        curve_cost(
+            self.curve_kind,
+            self.curve_start_price,
+            self.curve_end_price,
+            self.curve_supply,
+            self.curve_decimals,
+            sold,
+            rounding,
+        )
+    }
+
+    /// Lamports for the next `amount` tokens. Rounding both ends against the
+    /// buyer keeps the reserve at or above the rounded-up cost of all sold tokens.
+    pub fn curve_buy_cost(&self, amount: u64) -> Option<u64> {
+        let sold = self.curve_sold;
+        self.curve_cost(sold + amount, Rounding::Up)?
+            .checked_sub(self.curve_cost(sold, Rounding::Down)?)
+    }
+
+    /// Lamports for selling the last `amount` sold tokens back, rounded
+    /// against the seller.
+    pub fn curve_sell_proceeds(&self, amount: u64) -> Option<u64> {
+        let sold = self.curve_sold;
+        Some(
+            self.curve_cost(sold, Rounding::Down)?
+                .saturating_sub(self.curve_cost(sold - amount, Rounding::Up)?),
+        )
+    }
+
+    /// Lamports the vault still owes: unpaid refunds and sell-backs, and the
+    /// owner's unclaimed raise. Saturates so a corrupt pool still reports.
+    pub fn vault_liabilities(&self) -> u64 {
+        if self.is_claimed && !self.is_lottery() {
+            return 0;
+        }
+        let claimed = if self.is_claimed { self.lottery_winner_count() as u64 * self.max_deposit } else { 0 };
+        self.total_raised
+            .saturating_sub(self.total_refunded)
+            .saturating_sub(self.total_released)
+            .saturating_sub(claimed)
+    }
+
+    /// Deposits not yet released to the owner. Refunds after a rejected
+    /// release are shares of this amount, so it does not shrink with them.
+    pub fn escrow_remaining(&self) -> u64 {
+        self.total_raised - self.total_released
+    }
+
+    /// Last moment the owner of a governed pool can open a release proposal.
+    pub fn governance_deadline(&self) -> i64 {
+        self.expiry_timestamp.saturating_add(self.in_time_basis(GOVERNANCE_WINDOW))
+    }
+
+    /// A governed raise whose window passed with escrow left and no vote
+    /// running; depositors can refund it without a rejected proposal.
+    pub fn governance_lapsed(&self, now: i64) -> bool {
+        self.is_governed()
+            && self.soft_cap_reached()
+            && !self.proposal_open
+            && self.escrow_remaining() > 0
+            && now >= self.governance_deadline()
+    }
+
+    /// Whether creating the pool counted it in `active_pools`. Pools from
+    /// before version 12 have no `counted` flag; the stats account arrived
+    /// with version 2.
+    pub fn is_counted(&self) -> bool {
+        self.counted || (2..12).contains(&self.version)
+    }
+
+    /// Whether the pool left `active_pools`. Pools from before version 10
+    /// have no `settled` flag and count as settled in any state that settles.
+    pub fn is_settled(&self) -> bool {
+        self.settled
+            || (self.version < 10
+                && (self.is_claimed
+                    || self.total_refunded > 0
+                    || self.release_rejected
+                    || (self.total_released > 0 && self.escrow_remaining() == 0)))
+    }
+
+    /// Maximum total deposit for a wallet with `staked` tokens: the highest
+    /// tier it reaches, or nothing below the first tier.
+    pub fn tier_allocation(&self, staked: u64) -> u64 {
+        let tiers = self.tier_count as usize;
+        self.tier_min_stakes[..tiers]
+            .iter()
+            .zip(&self.tier_max_allocations[..tiers])
+            .rev()
+            .find(|(&min_stake, _)| staked >= min_stake)
+            .map_or(0, |(_, &max_allocation)| max_allocation)
+    }
+
+    /// Winning tickets: the configured count, or every ticket when undersubscribed.
+    pub fn lottery_winner_count(&self) -> u32 {
+        self.lottery_winners.min(self.depositor_count)
+    }
+
+    pub fn lottery_reveal_deadline(&self) -> i64 {
+        self.expiry_timestamp.saturating_add(self.in_time_basis(LOTTERY_REVEAL_WINDOW))
+    }
+
+    /// Whether the slot hash has completed the seed. Lotteries revealed
+    /// before slot hashes were mixed in have no seed slot and keep their seed.
+    pub fn lottery_seeded(&self) -> bool {
+        self.lottery_slot_hash != [0; 32] || (self.lottery_seed_slot == 0 && self.lottery_seed != [0; 32])
+    }
+
+    /// A lottery whose secret was not revealed in time can never be drawn.
+    pub fn lottery_abandoned(&self, now: i64) -> bool {
+        self.is_lottery() && self.lottery_seed == [0; 32] && now >= self.lottery_reveal_deadline()
+    }
+
+    /// Whether deposits are still limited to the whitelist.
+    pub fn whitelist_active(&self, now: i64) -> bool {
+        self.whitelist_root != [0; 32] && now < self.whitelist_end
+    }
+
+    /// Both caps must be met when the pool also has USD limits.
+    pub fn soft_cap_reached(&self) -> bool {
+        self.total_raised >= self.soft_cap && self.total_raised_usd >= self.usd_soft_cap
+    }
+
+    /// Either cap fills the pool.
+    pub fn hard_cap_reached(&self) -> bool {
+        (self.hard_cap > 0 && self.total_raised >= self.hard_cap)
+            || (self.usd_hard_cap > 0 && self.total_raised_usd >= self.usd_hard_cap)
+    }
+
+    pub fn phase(&self, now: i64) -> PoolPhase {
+        if self.is_finalized {
+            PoolPhase::Finalized
+        } else if now < self.expiry_timestamp {
+            if self.hard_cap_reached() {
+                PoolPhase::Filled
+            } else {
+                PoolPhase::Open
+            }
+        } else if self.soft_cap_reached() && !self.lottery_abandoned(now) {
+            PoolPhase::Succeeded
+        } else {
+            PoolPhase::Failed
+        }
+    }
+
+    /// Pro-rata share of the finalized token supply for a record's
+    /// `allocation_weight`, matching presale-merkle. Lottery pools split it
+    /// over the winning tickets only, bonus pools over the weighted total.
+    pub fn entitlement(&self, weight: u64) -> u64 {
+        let allocated_raise = if self.is_lottery() {
+            self.lottery_winner_count() as u64 * self.max_deposit
+        } else if self.has_bonus() {
+            self.total_weighted
+        } else {
+            self.total_raised
+        };
+        if !self.is_finalized || allocated_raise == 0 {
+            return 0;
+        }
+        (weight as u128 * self.total_tokens as u128 / allocated_raise as u128) as u64
+    }
+}
+
+/// Unit of a pool's deadlines, chosen at creation.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBasis {
+    /// `Clock::unix_timestamp`; pools from before time bases existed use it
+    UnixTimestamp,
+    /// `Clock::slot`
+    Slot,
 }
 
-#[derive(Accounts)]
-pub struct ClaimFunds<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    /// CHECK: This is a PDA used as a vault to hold SOL
-    pub pool_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
+/// Shape of a curve pool's price between its start and end price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CurveKind {
+    /// Not a curve pool; the value older pools read as
+    None,
+    /// Price grows by the same amount for every token sold
+    Linear,
+    /// Price grows by the same factor for every token sold
+    Exponential,
+}
+
+/// What a gated pool requires its depositors to hold.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GateKind {
+    /// No holding requirement; the value older pools read as
+    None,
+    /// At least `gate_min_amount` of the SPL mint `gate_mint`
+    TokenHolder,
+    /// An NFT whose metadata names `gate_mint` as its verified collection
+    NftCollection,
+}
+
+/// Layout of the price account a pool with USD limits reads.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OracleKind {
+    /// No USD limits; the value older pools read as
+    None,
+    /// A `MOCK_PRICE_MAGIC` account, for local validators and tests
+    Mock,
+    /// A fully verified Pyth `PriceUpdateV2` account of the SOL/USD feed
+    Pyth,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PoolPhase {
+    /// Accepting deposits
+    Open,
+    /// Hard cap reached before expiry
+    Filled,
+    /// Expired with the soft cap met, awaiting finalization
+    Succeeded,
+    /// Expired below the soft cap, deposits are refundable
+    Failed,
+    /// Allocations published, tokens claimable
+    Finalized,
+}
+
+/// `part / whole` in basis points, capped at 100%; zero when `whole` is zero.
+fn ratio_bps(part: u64, whole: u64) -> u16 {
+    if whole == 0 {
+        return 0;
+    }
+    (part as u128 * 10_000 / whole as u128).min(10_000) as u16
+}
+
+/// Common tail of every event: the event layout version and when it was
+/// emitted, so a replay of the event stream alone orders and dates it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct EventHeader {
+    pub version: u8,
+    pub timestamp: i64,
+    pub slot: u64,
+}
+
+impl EventHeader {
+    pub fn now() -> Result<Self> {
+        let clock = Clock::get()?;
+        Ok(Self {
+            version: EVENT_VERSION,
+            timestamp: clock.unix_timestamp,
+            slot: clock.slot,
+        })
+    }
+}
+
+/// Emits `PhaseChanged` when the handler moved the pool out of `from`.
+fn emit_phase_change(pool: &Account<Pool>, from: PoolPhase, now: i64) -> Result<()> {
+    let to = pool.phase(now);
+    if to != from {
+        emit!(PhaseChanged {
+            pool: pool.key(),
+            from,
+            to,
+            header: EventHeader::now()?,
+        });
+    }
+    Ok(())
+}
+
+/// Tops `info` up from `payer` to the rent of `space` bytes.
+fn fund_rent<'info>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let top_up = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if top_up > 0 {
+        let cpi_context = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer { from: payer.clone(), to: info.clone() },
+        );
+        anchor_lang::system_program::transfer(cpi_context, top_up)?;
+    }
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DepositorRecord {
+    pub depositor: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub index: u32,
+    pub tokens_claimed: u64,
+    pub is_refunded: bool,
+    pub last_deposit_slot: u64,
+    /// Layout version; zero for records created before versioning
+    pub version: u8,
+    /// Counted in the staker's `active_positions` until `release_stake`
+    pub stake_locked: bool,
+    /// Deposits plus their early-bird bonus; zero outside bonus pools
+    pub weighted_amount: u64,
+}
+
+versioned_account!(DepositorRecord, [120, 180, 8, 71, 198, 212, 93, 187]);
+
+impl DepositorRecord {
+    pub const VERSION: u8 = 3;
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 8 + 4 + 8 + 1 + 8 + 1 + 1 + 8;
+    /// Length of the first layout, which had no index
+    pub const UNINDEXED_SPACE: usize = 8 + 32 + 32 + 8 + 8;
+
+    /// Reads the record at `info`, creating it first, or growing and
+    /// migrating one written by an older version; `init_if_needed` would
+    /// reject those with `ConstraintSpace`. `payer` covers the rent and
+    /// `signer_seeds` are the record's own. Records from before indexes
+    /// take the next index of `pool`, which must not predate them itself.
+    pub fn open<'info>(
+        info: &AccountInfo<'info>,
+        pool: &mut Pool,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        signer_seeds: &[&[u8]],
+    ) -> Result<Self> {
+        fund_rent(info, payer, system_program, Self::SPACE)?;
+
+        if *info.owner == anchor_lang::system_program::ID {
+            // Funded above, so allocating and assigning covers prefunded addresses too
+            let signer = &[signer_seeds];
+            let cpi_context = CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Allocate { account_to_allocate: info.clone() },
+                signer,
+            );
+            anchor_lang::system_program::allocate(cpi_context, Self::SPACE as u64)?;
+            let cpi_context = CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Assign { account_to_assign: info.clone() },
+                signer,
+            );
+            anchor_lang::system_program::assign(cpi_context, &crate::ID)?;
+            info.try_borrow_mut_data()?[..8].copy_from_slice(Self::DISCRIMINATOR);
+            return Self::try_deserialize(&mut &info.try_borrow_data()?[..]);
+        }
+
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        let mut record = Self::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        if info.data_len() < Self::SPACE {
+            if info.data_len() <= Self::UNINDEXED_SPACE {
+                record.take_index(pool)?;
+            }
+            info.resize(Self::SPACE)?;
+            let from_version = record.version;
+            record.version = Self::VERSION;
+            emit!(AccountMigrated {
+                pool: record.pool,
+                account: info.key(),
+                from_version,
+                to_version: Self::VERSION,
+                header: EventHeader::now()?,
+            });
+        }
+        Ok(record)
+    }
+
+    /// Gives a record from before indexes the next index of its pool, one of
+    /// the `unindexed_records` that `migrate_pool` counted.
+    pub fn take_index(&mut self, pool: &mut Pool) -> Result<()> {
+        pool.unindexed_records =
+            pool.unindexed_records.checked_sub(1).ok_or(PresaleError::AccountNeedsMigration)?;
+        self.index = pool.depositor_count;
+        pool.depositor_count += 1;
+        Ok(())
+    }
+
+    /// Writes a record `open` returned back to its account.
+    pub fn save(&self, info: &AccountInfo) -> Result<()> {
+        self.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+    }
+
+    /// Weight of the position in the token split: the bonus-weighted amount
+    /// in bonus pools, the deposit itself otherwise.
+    pub fn allocation_weight(&self) -> u64 {
+        if self.weighted_amount > 0 {
+            self.weighted_amount
+        } else {
+            self.amount
+        }
+    }
+}
+
+/// A vote on releasing part of a governed pool's escrow to its owner.
+#[account]
+pub struct ReleaseProposal {
+    pub pool: Pubkey,
+    pub id: u32,
+    pub amount: u64,
+    /// In the pool's time basis
+    pub voting_end: i64,
+    /// Deposited lamports behind each side
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub executed: bool,
+    pub approved: bool,
+    pub bump: u8,
+}
+
+impl ReleaseProposal {
+    pub const SPACE: usize = 8 + 32 + 4 + 8 + 8 + 8 + 8 + 1 + 1 + 1;
+
+    /// Turnout must reach the quorum share of the raise, and at least the
+    /// approval share of the cast weight must be in favour.
+    pub fn passed(&self, pool: &Pool) -> bool {
+        let cast = self.votes_for as u128 + self.votes_against as u128;
+        self.votes_for > 0
+            && cast * 10_000 >= pool.quorum_bps as u128 * pool.total_raised as u128
+            && self.votes_for as u128 * 10_000 >= pool.approval_bps as u128 * cast
+    }
+}
+
+#[account]
+pub struct Vote {
+    /// The release proposal, or the pool for stream halt votes
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub approve: bool,
+}
+
+impl Vote {
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+/// A wallet's stake of one mint, held in its own `[b"stake_vault", mint,
+/// staker]` token account.
+#[account]
+pub struct StakeRecord {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    /// Positions in tiered pools that still lock the stake
+    pub active_positions: u32,
+    pub bump: u8,
+}
+
+impl StakeRecord {
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 4 + 1;
+}
+
+#[account]
+pub struct ClaimBitmap {
+    pub pool: Pubkey,
+    pub bits: Vec<u8>,
+}
+
+impl ClaimBitmap {
+    pub fn byte_len(depositor_count: u32) -> usize {
+        (depositor_count as usize).div_ceil(8)
+    }
+
+    pub fn space(depositor_count: u32) -> usize {
+        8 + 32 + 4 + Self::byte_len(depositor_count)
+    }
+
+    pub fn is_claimed(&self, index: u32) -> bool {
+        self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set_claimed(&mut self, index: u32) {
+        self.bits[(index / 8) as usize] |= 1 << (index % 8);
+    }
+}
+
+#[account]
+pub struct PoolMetadata {
+    pub pool: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    /// Off-chain JSON or image describing the project
+    pub uri: String,
+    /// Website, socials and similar project links
+    pub links: Vec<String>,
+}
+
+impl PoolMetadata {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_SYMBOL_LEN: usize = 10;
+    pub const MAX_URI_LEN: usize = 200;
+    pub const MAX_LINKS: usize = 4;
+    pub const MAX_LINK_LEN: usize = 100;
+
+    pub const SPACE: usize = 8
+        + 32
+        + 4 + Self::MAX_NAME_LEN
+        + 4 + Self::MAX_SYMBOL_LEN
+        + 4 + Self::MAX_URI_LEN
+        + 4 + Self::MAX_LINKS * (4 + Self::MAX_LINK_LEN);
+}
+
+/// Program-wide totals, kept in a single PDA. It only counts activity from
+/// the program version that introduced it onwards.
+#[account]
+pub struct LaunchpadStats {
+    pub total_pools: u64,
+    /// Pools neither claimed by their owner nor refunding
+    pub active_pools: u64,
+    /// Every deposit, including ones later refunded
+    pub total_raised: u64,
+    /// SOL owners claimed from successful pools
+    pub total_claimed: u64,
+    /// Wallets that deposited into or received a position in any pool, each counted once
+    pub unique_depositors: u64,
+    pub bump: u8,
+}
+
+impl LaunchpadStats {
+    pub const SPACE: usize = 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    /// A pool stops being active once claimed or refunding, and only once.
+    /// Pools created before the stats account existed were never counted as
+    /// active.
+    pub fn settle_pool(&mut self, pool: &mut Account<Pool>) -> Result<()> {
+        if pool.is_settled() {
+            return Ok(());
+        }
+        if pool.is_counted() {
+            self.active_pools = self.active_pools.saturating_sub(1);
+        }
+        // Older layouts end before the flag; `is_settled` reads their state instead
+        if pool.version >= 10 {
+            pool.settled = true;
+        }
+
+        emit!(PoolSettled {
+            pool: pool.key(),
+            active_pools: self.active_pools,
+            header: EventHeader::now()?,
+        });
+        Ok(())
+    }
+}
+
+/// Exists once per wallet that ever deposited or received a position, so it
+/// is counted once.
+#[account]
+pub struct Participant {
+    pub wallet: Pubkey,
+}
+
+impl Participant {
+    pub const SPACE: usize = 8 + 32;
+}
+
+#[account]
+pub struct LotteryDraw {
+    pub pool: Pubkey,
+    /// Picks mark losers instead of winners when losers are the smaller set
+    pub selects_losers: bool,
+    pub to_select: u32,
+    pub selected: u32,
+    /// Picks made so far, including repeats
+    pub nonce: u64,
+    pub bits: Vec<u8>,
+}
+
+impl LotteryDraw {
+    pub fn space(depositor_count: u32) -> usize {
+        8 + 32 + 1 + 4 + 4 + 8 + 4 + ClaimBitmap::byte_len(depositor_count)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.selected == self.to_select
+    }
+
+    pub fn is_selected(&self, index: u32) -> bool {
+        self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set_selected(&mut self, index: u32) {
+        self.bits[(index / 8) as usize] |= 1 << (index % 8);
+    }
+
+    pub fn is_winner(&self, index: u32) -> bool {
+        self.is_selected(index) != self.selects_losers
+    }
+}
+
+/// Where a slot's hash stands in the SlotHashes sysvar.
+pub enum SlotHashLookup {
+    /// The first slot at or after the requested one, and its hash
+    Found(u64, [u8; 32]),
+    /// No slot that late has been recorded yet
+    Pending,
+    /// The first slot at or after the requested one may have been dropped
+    Expired,
+}
+
+/// Looks up the hash of the first slot at or after `slot` in the SlotHashes
+/// sysvar's data: a u64 entry count, then `(slot, hash)` entries, newest
+/// first. Only a recorded slot before `slot` proves none was dropped between.
+pub fn find_slot_hash(data: &[u8], slot: u64) -> SlotHashLookup {
+    let count = data.get(..8).map_or(0, |count| u64::from_le_bytes(count.try_into().unwrap()));
+    let entries = data.get(8..).unwrap_or_default().chunks_exact(40).take(count as usize).map(|entry| {
+        (u64::from_le_bytes(entry[..8].try_into().unwrap()), <[u8; 32]>::try_from(&entry[8..]).unwrap())
+    });
+
+    let mut found = None;
+    for (entry_slot, hash) in entries {
+        if entry_slot < slot {
+            return found.map_or(SlotHashLookup::Pending, |(slot, hash)| SlotHashLookup::Found(slot, hash));
+        }
+        found = Some((entry_slot, hash));
+    }
+    match found {
+        Some((oldest, hash)) if oldest == slot => SlotHashLookup::Found(oldest, hash),
+        Some(_) => SlotHashLookup::Expired,
+        None => SlotHashLookup::Pending,
+    }
+}
+
+/// Ticket index of lottery pick `nonce`: sha256(seed || nonce) modulo the ticket count.
+pub fn lottery_pick(seed: &[u8; 32], nonce: u64, ticket_count: u32) -> u32 {
+    let hash = hashv(&[seed, &nonce.to_le_bytes()]).to_bytes();
+    (u64::from_le_bytes(hash[..8].try_into().unwrap()) % ticket_count as u64) as u32
+}
+
+/// Leaf of the allocation tree: sha256(0x00 || index || depositor || amount).
+pub fn merkle_leaf(index: u32, depositor: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[&[0u8], &index.to_le_bytes(), depositor.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// Leaf of the opening-window whitelist: sha256(0x02 || depositor).
+pub fn whitelist_leaf(depositor: &Pubkey) -> [u8; 32] {
+    hashv(&[&[2u8], depositor.as_ref()]).to_bytes()
+}
+
+/// Walks a proof with sorted-pair hashing: sha256(0x01 || min || max).
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        hashv(&[&[1u8], &left, &right]).to_bytes()
+    });
+    computed == root
+}
+
+/// Largest mint precision a curve accepts, keeping `10^decimals` and the
+/// curve's intermediate products within 128 bits.
+pub const CURVE_MAX_DECIMALS: u8 = 18;
+
+/// 1.0 in the curve math's unsigned 64.64 fixed point.
+const Q64: u128 = 1 << 64;
+
+/// ln(2) in 64.64 fixed point, rounded down.
+const LN2_Q64: u128 = 12_786_308_645_202_655_659;
+
+/// Direction a curve computation rounds every intermediate step.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+impl Rounding {
+    fn reverse(self) -> Self {
+        match self {
+            Rounding::Down => Rounding::Up,
+            Rounding::Up => Rounding::Down,
+        }
+    }
+}
+
+/// Lamports the first `sold` base units of a curve cost: the integral of its
+/// price from zero to `sold`. Each step rounds in `rounding`'s direction, so
+/// the result only moves one way and stays monotonic in `sold`. `None` when
+/// the parameters overflow the fixed-point math.
+pub fn curve_cost(
+    kind: CurveKind,
+    start_price: u64,
+    end_price: u64,
+    supply: u64,
+    decimals: u8,
+    sold: u64,
+    rounding: Rounding,
+) -> Option<u64> {
+    let unit = 10u128.pow(decimals as u32);
+    let (start, sold_128, supply_128) = (start_price as u128, sold as u128, supply as u128);
+    let cost = match kind {
+        CurveKind::None => return None,
+        // start * x / unit + (end - start) * x^2 / (2 * supply * unit)
+        CurveKind::Linear => {
+            let flat = mul_div(start, sold_128, unit, rounding)?;
+            let slope = (end_price - start_price) as u128 * sold_128;
+            flat.checked_add(mul_div(slope, sold_128, 2 * supply_128 * unit, rounding)?)?
+        }
+        // price(x) = start * 2^(g * x / supply) with g = log2(end / start), so
+        // cost = start * supply * (2^(g * x / supply) - 1) / (g * ln 2 * unit)
+        CurveKind::Exponential => {
+            let growth = log2_q64(end_price, start_price);
+            let exponent = mul_div(growth, sold_128, supply_128, rounding)?;
+            let factor = exp2_q64(exponent)? - Q64;
+            let divisor = mul_div(growth, LN2_Q64, Q64, rounding.reverse())?;
+            if divisor == 0 {
+                return None;
+            }
+            let scaled = mul_div(start * supply_128, factor, divisor, rounding)?;
+            mul_div(scaled, 1, unit, rounding)?
+        }
+    };
+    u64::try_from(cost).ok()
+}
+
+/// `a * b / divisor` with a 256-bit intermediate product. `None` when the
+/// quotient does not fit 128 bits or `divisor` is zero.
+pub fn mul_div(a: u128, b: u128, divisor: u128, rounding: Rounding) -> Option<u128> {
+    const LOW: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & LOW);
+    let (b_high, b_low) = (b >> 64, b & LOW);
+    let low_low = a_low * b_low;
+    let (low_high, high_low) = (a_low * b_high, a_high * b_low);
+    let middle = (low_low >> 64) + (low_high & LOW) + (high_low & LOW);
+    let low = (low_low & LOW) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    if high >= divisor {
+        return None;
+    }
+
+    // Long division of (high, low) by the divisor, one bit of `low` at a time
+    let (mut remainder, mut quotient) = (high, 0u128);
+    for bit in (0..128).rev() {
+        let overflow = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if overflow == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    if rounding == Rounding::Up && remainder > 0 {
+        quotient = quotient.checked_add(1)?;
+    }
+    Some(quotient)
+}
+
+/// log2(numerator / denominator) in 64.64 fixed point, rounded down, for
+/// `numerator >= denominator > 0`.
+pub fn log2_q64(numerator: u64, denominator: u64) -> u128 {
+    let (numerator, denominator) = (numerator as u128, denominator as u128);
+    let mut integer = 0;
+    while denominator << (integer + 1) <= numerator {
+        integer += 1;
+    }
+
+    // Square the remaining factor in [1, 2), held with 63 fraction bits;
+    // each time it reaches 2 the next fraction bit of the logarithm is one
+    let mut factor = (numerator << 63) / (denominator << integer);
+    let mut result = (integer as u128) << 64;
+    for bit in (0..64).rev() {
+        factor = (factor * factor) >> 63;
+        if factor >= 1 << 64 {
+            factor >>= 1;
+            result |= 1 << bit;
+        }
+    }
+    result
+}
+
+/// 2^exponent for a 64.64 fixed-point exponent, rounded down. `None` from
+/// 2^63 on, which no realistic curve reaches.
+pub fn exp2_q64(exponent: u128) -> Option<u128> {
+    let integer = exponent >> 64;
+    if integer >= 63 {
+        return None;
+    }
+
+    // e^(fraction * ln 2) by its Taylor series, with 63 fraction bits
+    let z = (((exponent & (Q64 - 1)) >> 1) * (LN2_Q64 >> 1)) >> 63;
+    let (mut sum, mut term) = (1u128 << 63, 1u128 << 63);
+    for k in 1..=24 {
+        term = ((term * z) >> 63) / k;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    Some(sum << (integer + 1))
+}
+
+/// Length of the message an attestation authority signs for a depositor:
+/// pool || depositor || expiry (i64 LE) || max allocation in lamports (u64 LE).
+pub const ATTESTATION_LEN: usize = 32 + 32 + 8 + 8;
+
+/// Looks for an ed25519 verification earlier in the transaction, signed by
+/// `authority` over an attestation for this pool and depositor, and returns
+/// the attested maximum allocation. The ed25519 program has already checked
+/// the signature by the time this runs.
+fn verify_attestation(
+    instructions: &AccountInfo,
+    pool: &Pubkey,
+    depositor: &Pubkey,
+    authority: &Pubkey,
+    now: i64,
+) -> Result<u64> {
+    let current = instructions_sysvar::load_current_index_checked(instructions)?;
+
+    for index in 0..current as usize {
+        let ix = instructions_sysvar::load_instruction_at_checked(index, instructions)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+        let Some((signer, message)) = ed25519_signed_message(&ix.data) else {
+            continue;
+        };
+        if signer != authority.as_ref()
+            || message.len() != ATTESTATION_LEN
+            || &message[..32] != pool.as_ref()
+            || &message[32..64] != depositor.as_ref()
+        {
+            continue;
+        }
+
+        let expiry = i64::from_le_bytes(message[64..72].try_into().unwrap());
+        require!(now <= expiry, PresaleError::AttestationExpired);
+        return Ok(u64::from_le_bytes(message[72..80].try_into().unwrap()));
+    }
+
+    err!(PresaleError::MissingAttestation)
+}
+
+/// Public key and message of a single-signature ed25519 program instruction
+/// that carries all of its data inline.
+fn ed25519_signed_message(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    // num_signatures, padding, then one 14-byte Ed25519SignatureOffsets
+    if data.len() < 16 || data[0] != 1 {
+        return None;
+    }
+    let read = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+
+    // Instruction indexes of u16::MAX point at this instruction's own data
+    if [read(4), read(8), read(14)].iter().any(|&ix| ix != u16::MAX) {
+        return None;
+    }
+    let (pubkey_offset, message_offset, message_size) = (read(6) as usize, read(10) as usize, read(12) as usize);
+
+    Some((
+        data.get(pubkey_offset..pubkey_offset + 32)?,
+        data.get(message_offset..message_offset + message_size)?,
+    ))
+}
+
+/// Token Metadata program, owner of the NFT metadata that collection gates read.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Checks that `holder` meets the pool's gate with the token account and, for
+/// collection gates, the metadata account of that account's mint.
+fn verify_gate(
+    pool: &Pool,
+    holder: &Pubkey,
+    token_account: Option<&TokenAccount>,
+    metadata: Option<&AccountInfo>,
+) -> Result<()> {
+    if !pool.is_gated() {
+        return Ok(());
+    }
+    let token_account = token_account.ok_or(PresaleError::MissingGateAccount)?;
+    require!(
+        token_account.owner == *holder && token_account.amount >= pool.gate_min_amount,
+        PresaleError::GateNotMet
+    );
+
+    match pool.gate_kind {
+        GateKind::None => {}
+        GateKind::TokenHolder => require!(token_account.mint == pool.gate_mint, PresaleError::GateNotMet),
+        GateKind::NftCollection => {
+            let metadata = metadata.ok_or(PresaleError::MissingGateAccount)?;
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), token_account.mint.as_ref()],
+                &TOKEN_METADATA_PROGRAM_ID,
+            );
+            require!(
+                *metadata.key == expected && *metadata.owner == TOKEN_METADATA_PROGRAM_ID,
+                PresaleError::InvalidGateMetadata
+            );
+            let collection = metadata_collection(&metadata.try_borrow_data()?);
+            require!(collection == Some(pool.gate_mint), PresaleError::GateNotMet);
+        }
+    }
+    Ok(())
+}
+
+/// Verified collection named by a Token Metadata `Metadata` account, read by
+/// walking its Borsh layout up to the `collection` field. `None` for
+/// unverified or missing collections and for data that does not parse.
+fn metadata_collection(data: &[u8]) -> Option<Pubkey> {
+    // key (MetadataV1 = 4), update authority, mint
+    if *data.first()? != 4 {
+        return None;
+    }
+    let mut rest = data.get(1 + 32 + 32..)?;
+    // name, symbol and uri, each a u32 length and its bytes
+    for _ in 0..3 {
+        let len = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        rest = rest.get(4 + len..)?;
+    }
+    // seller fee bps, then optional creators of 34 bytes each
+    rest = rest.get(2..)?;
+    rest = match *rest.first()? {
+        0 => rest.get(1..)?,
+        _ => {
+            let count = u32::from_le_bytes(rest.get(1..5)?.try_into().ok()?) as usize;
+            rest.get(5 + count * 34..)?
+        }
+    };
+    // primary sale happened, is mutable, then optional edition nonce and token standard
+    rest = rest.get(2..)?;
+    for _ in 0..2 {
+        rest = rest.get(if *rest.first()? == 0 { 1 } else { 2 }..)?;
+    }
+    // collection: Option<{ verified: bool, key: Pubkey }>
+    match rest.get(..34)? {
+        [1, 1, key @ ..] => Some(Pubkey::new_from_array(key.try_into().ok()?)),
+        _ => None,
+    }
 }
 
-#[derive(Accounts)]
-pub struct GetPoolInfo<'info> {
-    pub pool: Account<'info, Pool>,
+/// Pyth receiver program, owner of `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// sha256("account:PriceUpdateV2")[..8]
+pub const PYTH_PRICE_UPDATE_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+/// Pyth's Crypto.SOL/USD feed id.
+pub const PYTH_SOL_USD_FEED_ID: [u8; 32] = [
+    239, 13, 139, 111, 218, 44, 235, 164, 29, 161, 93, 64, 149, 209, 218, 57, 42, 13, 47, 142, 208, 198, 199, 188,
+    15, 76, 250, 200, 194, 128, 181, 109,
+];
+
+/// First bytes of an `OracleKind::Mock` price account, followed by the price
+/// (i64 LE), its exponent (i32 LE) and its publish time (i64 LE, unix
+/// seconds). Any program may own one, so the pool's oracle kind tells
+/// depositors whether its limits rest on a real feed.
+pub const MOCK_PRICE_MAGIC: [u8; 8] = *b"mockprc1";
+pub const MOCK_PRICE_LEN: usize = 8 + 8 + 4 + 8;
+
+/// Seconds a price's publish time may run ahead of the cluster clock, whose
+/// `unix_timestamp` can trail the publishers' clocks slightly.
+pub const MAX_PRICE_CLOCK_SKEW: i64 = 10;
+
+/// A SOL/USD price of `price × 10^exponent` dollars per SOL.
+#[derive(Clone, Copy)]
+pub struct UsdPrice {
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
 }
 
-#[account]
-pub struct Pool {
-    pub owner: Pubkey,
-    pub expiry_timestamp: i64,
-    pub total_raised: u64,
-    pub min_deposit: u64,
-    pub max_deposit: u64,
-    pub is_claimed: bool,
-    pub depositor_count: u32,
-    pub bump: u8,
+impl UsdPrice {
+    /// Value of `lamports` in micro-USD, rounded down; `None` on overflow.
+    pub fn usd_value(&self, lamports: u64) -> Option<u64> {
+        // Lamports carry 9 decimals and micro-USD 6, so the scale is 10^(exponent - 3)
+        let price = u128::try_from(self.price).ok()?;
+        let shift = self.exponent.checked_sub(3)?;
+        let scale = 10u128.checked_pow(shift.unsigned_abs())?;
+        let value = if shift >= 0 {
+            (lamports as u128).checked_mul(price)?.checked_mul(scale)?
+        } else {
+            mul_div(lamports as u128, price, scale, Rounding::Down)?
+        };
+        u64::try_from(value).ok()
+    }
 }
 
-impl Pool {
-    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1 + 4 + 1;
+/// Reads the pool's SOL/USD price from `oracle`, which must be the pool's
+/// configured account, parse as its oracle kind, and be positive and no
+/// older than `max_price_age` at `now` (unix seconds). Prices dated more
+/// than `MAX_PRICE_CLOCK_SKEW` into the future are rejected as well.
+fn read_usd_price(pool: &Pool, oracle: Option<&AccountInfo>, now: i64) -> Result<UsdPrice> {
+    let oracle = oracle.ok_or(PresaleError::MissingPriceOracle)?;
+    require!(*oracle.key == pool.price_oracle, PresaleError::InvalidPriceOracle);
+
+    let data = oracle.try_borrow_data()?;
+    let price = match pool.oracle_kind {
+        OracleKind::None => None,
+        OracleKind::Mock => mock_price(&data),
+        OracleKind::Pyth => {
+            require!(*oracle.owner == PYTH_RECEIVER_PROGRAM_ID, PresaleError::InvalidPriceOracle);
+            pyth_price(&data)
+        }
+    }
+    .ok_or(PresaleError::InvalidPriceOracle)?;
+
+    require!(price.price > 0, PresaleError::InvalidPriceOracle);
+    let age = now.checked_sub(price.publish_time).ok_or(PresaleError::StalePrice)?;
+    require!((-MAX_PRICE_CLOCK_SKEW..=pool.max_price_age).contains(&age), PresaleError::StalePrice);
+    Ok(price)
 }
 
-#[account]
-pub struct DepositorRecord {
-    pub depositor: Pubkey,
-    pub pool: Pubkey,
-    pub amount: u64,
-    pub timestamp: i64,
+fn mock_price(data: &[u8]) -> Option<UsdPrice> {
+    if data.len() < MOCK_PRICE_LEN || data[..8] != MOCK_PRICE_MAGIC {
+        return None;
+    }
+    Some(UsdPrice {
+        price: i64::from_le_bytes(data[8..16].try_into().ok()?),
+        exponent: i32::from_le_bytes(data[16..20].try_into().ok()?),
+        publish_time: i64::from_le_bytes(data[20..28].try_into().ok()?),
+    })
 }
 
-impl DepositorRecord {
-    pub const SPACE: usize = 8 + 32 + 32 + 8 + 8;
+/// Price of a `PriceUpdateV2` account, read by walking its Borsh layout.
+/// `None` for other accounts, partially verified updates and other feeds.
+fn pyth_price(data: &[u8]) -> Option<UsdPrice> {
+    if *data.get(..8)? != PYTH_PRICE_UPDATE_DISCRIMINATOR {
+        return None;
+    }
+    // write authority, then the verification level: Partial { num_signatures } = 0, Full = 1
+    let rest = data.get(8 + 32..)?;
+    if *rest.first()? != 1 {
+        return None;
+    }
+    // feed id, price, confidence, exponent, publish time
+    let message = rest.get(1..1 + 32 + 8 + 8 + 4 + 8)?;
+    if message[..32] != PYTH_SOL_USD_FEED_ID {
+        return None;
+    }
+    Some(UsdPrice {
+        price: i64::from_le_bytes(message[32..40].try_into().ok()?),
+        exponent: i32::from_le_bytes(message[48..52].try_into().ok()?),
+        publish_time: i64::from_le_bytes(message[52..60].try_into().ok()?),
+    })
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -244,6 +4240,62 @@ pub struct PoolInfo {
     pub max_deposit: u64,    
     pub is_claimed: bool,
     pub depositor_count: u32,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub attestation_authority: Pubkey,
+    pub time_basis: TimeBasis,
+    /// Expiry as a slot; `expiry_timestamp` is the same deadline in unix
+    /// time. Whichever is not the pool's time basis is an estimate.
+    pub expiry_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AuditReport {
+    pub vault_lamports: u64,
+    /// Refunds, sell-backs and owner payouts the vault still has to cover
+    pub liabilities: u64,
+    pub vault_solvent: bool,
+    /// A claimed pool was claimable, and its vault still covers what it owes
+    pub claim_consistent: bool,
+    /// Payouts within the raise, caps and curve within bounds, flags in agreement
+    pub counters_consistent: bool,
+}
+
+impl AuditReport {
+    pub fn passed(&self) -> bool {
+        self.vault_solvent && self.claim_consistent && self.counters_consistent
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PoolStatus {
+    pub phase: PoolPhase,
+    pub seconds_remaining: i64,
+    pub total_raised: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub soft_cap_bps: u16,
+    pub hard_cap_bps: u16,
+    pub depositor_count: u32,
+    pub owner_can_claim: bool,
+    pub refunds_open: bool,
+    pub whitelist_only: bool,
+    pub slots_remaining: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DepositorInfo {
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub share_bps: u16,
+    pub entitlement: u64,
+    pub tokens_claimed: u64,
+    pub is_refunded: bool,
+    pub can_claim_tokens: bool,
+    pub can_refund: bool,
+    pub won_lottery: bool,
+    /// Deposit including the early-bird bonus; equals `amount` without one
+    pub weighted_amount: u64,
 }
 
 #[event]
@@ -253,6 +4305,20 @@ pub struct PoolCreated {
     pub expiry_timestamp: i64,
     pub min_deposit: u64,
     pub max_deposit: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub time_basis: TimeBasis,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct PoolMetadataSet {
+    pub pool: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub links: Vec<String>,
+    pub header: EventHeader,
 }
 
 #[event]
@@ -262,6 +4328,19 @@ pub struct DepositMade {
     pub amount: u64,
     pub total_deposit: u64,
     pub pool_total: u64,
+    pub payer: Pubkey,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub pool: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub from_total: u64,
+    pub to_total: u64,
+    pub header: EventHeader,
 }
 
 #[event]
@@ -269,11 +4348,379 @@ pub struct FundsClaimed {
     pub pool: Pubkey,
     pub owner: Pubkey,
     pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct DepositRefunded {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct AttestationAuthoritySet {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct AntiBotConfigured {
+    pub pool: Pubkey,
+    pub whitelist_root: [u8; 32],
+    pub whitelist_end: i64,
+    pub min_slot_gap: u64,
+    pub reject_cpi: bool,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct GateConfigured {
+    pub pool: Pubkey,
+    pub kind: GateKind,
+    pub mint: Pubkey,
+    pub min_amount: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct UsdLimitsConfigured {
+    pub pool: Pubkey,
+    pub kind: OracleKind,
+    pub price_oracle: Pubkey,
+    pub max_price_age: i64,
+    /// In micro-USD
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct LotteryConfigured {
+    pub pool: Pubkey,
+    pub winner_count: u32,
+    pub commitment: [u8; 32],
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct GovernanceConfigured {
+    pub pool: Pubkey,
+    pub quorum_bps: u16,
+    pub approval_bps: u16,
+    pub voting_period: i64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct ReleaseProposed {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u32,
+    pub amount: u64,
+    pub voting_end: i64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct VoteCast {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub approve: bool,
+    pub weight: u64,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u32,
+    pub approved: bool,
+    pub amount: u64,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct StreamConfigured {
+    pub pool: Pubkey,
+    pub duration: i64,
+    pub halt_bps: u16,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct StreamWithdrawn {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub total_released: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct HaltVoteCast {
+    pub pool: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub halt_votes: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct StreamHalted {
+    pub pool: Pubkey,
+    pub paid_to_owner: u64,
+    /// Escrow left for `refund_escrow`
+    pub refundable: u64,
+    pub halt_votes: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct CurveConfigured {
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub kind: CurveKind,
+    pub start_price: u64,
+    pub end_price: u64,
+    pub supply: u64,
+    pub sell_back: bool,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct TokensBought {
+    pub pool: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub cost: u64,
+    /// Curve position after the trade
+    pub tokens_sold: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct TokensSold {
+    pub pool: Pubkey,
+    pub seller: Pubkey,
+    pub amount: u64,
+    pub proceeds: u64,
+    pub tokens_sold: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct UnsoldTokensWithdrawn {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct TiersConfigured {
+    pub pool: Pubkey,
+    pub stake_mint: Pubkey,
+    pub min_stakes: Vec<u64>,
+    pub max_allocations: Vec<u64>,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct BonusConfigured {
+    pub pool: Pubkey,
+    pub ends: Vec<i64>,
+    pub bonus_bps: Vec<u16>,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct BonusApplied {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    /// Bonus of this deposit, zero after the last step
+    pub bonus_bps: u16,
+    /// Depositor's weighted total after the deposit
+    pub weighted_amount: u64,
+    pub total_weighted: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct DepositPriced {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    /// In micro-USD, at `price × 10^exponent` dollars per SOL
+    pub usd_value: u64,
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub total_raised_usd: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct Staked {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct Unstaked {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct StakeReleased {
+    pub pool: Pubkey,
+    pub staker: Pubkey,
+    pub active_positions: u32,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct LotteryRevealed {
+    pub pool: Pubkey,
+    pub secret: [u8; 32],
+    pub entropy: [u8; 32],
+    pub seed: [u8; 32],
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct LotterySeeded {
+    pub pool: Pubkey,
+    pub slot: u64,
+    pub slot_hash: [u8; 32],
+    pub seed: [u8; 32],
+    pub header: EventHeader,
+}
+
+/// The seed slot's hash left `SlotHashes` before `draw_winners` read it,
+/// so the seed waits for a later slot instead.
+#[event]
+pub struct LotterySeedRescheduled {
+    pub pool: Pubkey,
+    pub expired_slot: u64,
+    pub seed_slot: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct LotteryDrawn {
+    pub pool: Pubkey,
+    pub seed: [u8; 32],
+    pub winners: u32,
+    pub picks: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub pool: Pubkey,
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub header: EventHeader,
+}
+
+/// A transaction moved the pool to another phase: a deposit or purchase
+/// filling the hard cap, or finalization. Phases that change with time alone
+/// (expiry, an unrevealed lottery) follow from the deadlines in `PoolCreated`.
+#[event]
+pub struct PhaseChanged {
+    pub pool: Pubkey,
+    pub from: PoolPhase,
+    pub to: PoolPhase,
+    pub header: EventHeader,
+}
+
+/// The pool left `LaunchpadStats::active_pools`: claimed, refunding,
+/// rejected by its depositors or settled without deposits. `active_pools`
+/// is the count afterwards.
+#[event]
+pub struct PoolSettled {
+    pub pool: Pubkey,
+    pub active_pools: u64,
+    pub header: EventHeader,
+}
+
+/// `init_stats` created the stats account after an upgrade.
+#[event]
+pub struct StatsInitialized {
+    pub stats: Pubkey,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct PoolFinalized {
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_tokens: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct TokensClaimed {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+/// One record paid by `distribute_batch`; its index is now claimed.
+#[event]
+pub struct TokensDistributed {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[event]
+pub struct BatchDistributed {
+    pub pool: Pubkey,
+    pub distributed: u32,
+    /// Records already paid, refunded or holding losing tickets
+    pub skipped: u32,
+    pub header: EventHeader,
+}
+
+/// `audit_pool` found the pool's bookkeeping inconsistent with its vault.
+#[event]
+pub struct AuditFailed {
+    pub pool: Pubkey,
+    pub vault_lamports: u64,
+    pub liabilities: u64,
+    pub vault_solvent: bool,
+    pub claim_consistent: bool,
+    pub counters_consistent: bool,
+    pub header: EventHeader,
 }
 
 #[error_code]
 pub enum PresaleError {
-    #[msg("Invalid expiry time - must be in the future")]
+    #[msg("Invalid expiry time - must be in the future and at most a year away")]
     InvalidExpiryTime,
     #[msg("Invalid deposit amount")]
     InvalidDepositAmount,
@@ -289,4 +4736,283 @@ pub enum PresaleError {
     AlreadyClaimed,
     #[msg("Unauthorized - only pool owner can perform this action")]
     Unauthorized,
+    #[msg("Pool allocations have already been finalized")]
+    AlreadyFinalized,
+    #[msg("Pool allocations have not been finalized yet")]
+    PoolNotFinalized,
+    #[msg("Pool has no depositors")]
+    NoDepositors,
+    #[msg("Invalid token amount")]
+    InvalidTokenAmount,
+    #[msg("Invalid Merkle proof for this allocation")]
+    InvalidMerkleProof,
+    #[msg("Allocation has already been claimed")]
+    AllocationAlreadyClaimed,
+    #[msg("Invalid caps - hard cap must cover the soft cap and minimum deposit")]
+    InvalidCaps,
+    #[msg("Deposit would exceed the pool's hard cap")]
+    HardCapExceeded,
+    #[msg("Pool did not reach its soft cap")]
+    SoftCapNotReached,
+    #[msg("Refunds are only available for expired pools below their soft cap")]
+    RefundNotAvailable,
+    #[msg("Deposit has already been refunded")]
+    AlreadyRefunded,
+    #[msg("Pool settings can only change before the first deposit")]
+    PoolAlreadyActive,
+    #[msg("Deposit requires an ed25519 attestation from the pool's attestation authority")]
+    MissingAttestation,
+    #[msg("Attestation has expired")]
+    AttestationExpired,
+    #[msg("Deposit exceeds the attested maximum allocation")]
+    AllocationExceeded,
+    #[msg("Whitelist window must end before the pool expires")]
+    InvalidWhitelistWindow,
+    #[msg("Only whitelisted wallets can deposit during the opening window")]
+    NotWhitelisted,
+    #[msg("Wallet must wait more slots before depositing again")]
+    DepositTooSoon,
+    #[msg("Deposits through cross-program invocation are not allowed")]
+    CpiDepositRejected,
+    #[msg("Lottery pools need at least one winner and a fixed ticket price (min deposit equal to max deposit)")]
+    InvalidLotteryConfig,
+    #[msg("Pool does not use lottery allocation")]
+    NotLotteryPool,
+    #[msg("Wallet already holds a ticket in this lottery")]
+    TicketAlreadyRegistered,
+    #[msg("Revealed secret does not match the lottery commitment")]
+    InvalidLotteryReveal,
+    #[msg("Lottery reveal window has closed")]
+    LotteryRevealClosed,
+    #[msg("Lottery winners have not been drawn yet")]
+    LotteryNotDrawn,
+    #[msg("Lottery winners have already been drawn")]
+    LotteryAlreadyDrawn,
+    #[msg("Winning tickets cannot be refunded")]
+    WinningTicket,
+    #[msg("Positions cannot be transferred once allocations are finalized")]
+    PositionsFrozen,
+    #[msg("Lottery tickets cannot be transferred")]
+    TicketNotTransferable,
+    #[msg("Transfer must move part of the sender's position to another wallet")]
+    InvalidTransfer,
+    #[msg("Metadata needs a name, and its fields must fit their maximum lengths")]
+    InvalidMetadata,
+    #[msg("Account must be migrated to the current layout first")]
+    AccountNeedsMigration,
+    #[msg("Account was written by a newer program version")]
+    UnsupportedAccountVersion,
+    #[msg("Account already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Tiers need up to four ascending stake thresholds, each allowing at least the minimum deposit")]
+    InvalidTiers,
+    #[msg("Deposit exceeds the maximum allocation of the wallet's stake tier")]
+    TierAllocationExceeded,
+    #[msg("Stake amount must be non-zero and within the staked balance")]
+    InvalidStakeAmount,
+    #[msg("Stake is locked while the wallet has positions in active tiered pools")]
+    StakeLocked,
+    #[msg("Position does not lock any stake")]
+    StakeNotLocked,
+    #[msg("Positions in tiered pools cannot be transferred")]
+    TieredPositionNotTransferable,
+    #[msg("Governance needs a positive voting period, quorum and approval of at most 100%, and a non-lottery pool")]
+    InvalidGovernanceConfig,
+    #[msg("Pool does not use depositor governance")]
+    NotGovernedPool,
+    #[msg("Funds of governed pools are released by depositor vote")]
+    GovernedPool,
+    #[msg("Positions in governed or streaming pools cannot be transferred after expiry")]
+    GovernedPositionFrozen,
+    #[msg("Another release proposal is still open")]
+    ProposalAlreadyOpen,
+    #[msg("Release must be non-zero and within the remaining escrow")]
+    InvalidReleaseAmount,
+    #[msg("Depositors rejected a release; the escrow is being refunded")]
+    ReleaseRejected,
+    #[msg("Voting on this proposal has closed")]
+    VotingClosed,
+    #[msg("Voting on this proposal has not ended yet")]
+    VotingNotEnded,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Only depositors with an unrefunded deposit can vote")]
+    NoVotingWeight,
+    #[msg("Curves need a start price, a rising end price, a supply, at most 18 decimals, and no soft cap, lottery or governance")]
+    InvalidCurveConfig,
+    #[msg("Pool does not sell along a bonding curve")]
+    NotCurvePool,
+    #[msg("Curve pools trade through buy_tokens and sell_tokens")]
+    CurvePool,
+    #[msg("Not enough tokens left on the curve")]
+    CurveSoldOut,
+    #[msg("Curve price moved past the given limit")]
+    SlippageExceeded,
+    #[msg("This curve does not buy tokens back")]
+    SellBackDisabled,
+    #[msg("Curve price computation overflowed")]
+    CurveMathOverflow,
+    #[msg("Token gates need a mint and a minimum balance; NFT gates need a collection")]
+    InvalidGateConfig,
+    #[msg("Gated pools need the depositor's gate token account")]
+    MissingGateAccount,
+    #[msg("Depositor does not hold the pool's gate token or collection NFT")]
+    GateNotMet,
+    #[msg("Gate metadata account does not belong to the token's mint")]
+    InvalidGateMetadata,
+    #[msg("Distribution accounts must be record and token account pairs of the pool's depositors")]
+    InvalidDistributionAccounts,
+    #[msg("Bonus steps need ascending ends up to expiry and decaying bonuses of at most 100%, outside lottery and curve pools")]
+    InvalidBonusConfig,
+    #[msg("Streams need a positive duration and a halt threshold of at most 100%, outside lottery and governed pools")]
+    InvalidStreamConfig,
+    #[msg("Pool does not stream its raise")]
+    NotStreamingPool,
+    #[msg("Funds of streaming pools are paid out through withdraw_stream")]
+    StreamingPool,
+    #[msg("Depositors halted the stream; the escrow is being refunded")]
+    StreamHalted,
+    #[msg("Nothing has accrued since the last withdrawal")]
+    NothingToWithdraw,
+    #[msg("The stream has fully accrued and can no longer be halted")]
+    StreamEnded,
+    #[msg("USD limits need a positive price age, a maximum above the minimum and a hard cap above the soft cap, outside lotteries")]
+    InvalidUsdConfig,
+    #[msg("Pools with USD limits need their price oracle account")]
+    MissingPriceOracle,
+    #[msg("Price oracle is not the pool's, or holds no positive SOL/USD price of its kind")]
+    InvalidPriceOracle,
+    #[msg("Oracle price is older than the pool's maximum price age, or dated in the future")]
+    StalePrice,
+    #[msg("The beneficiary must sign deposits into this pool")]
+    BeneficiarySignatureRequired,
+    #[msg("Minimum slot gap is longer than a day")]
+    InvalidSlotGap,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Pool is already settled")]
+    PoolAlreadySettled,
+    #[msg("The governance window has closed; the escrow is being refunded")]
+    GovernanceWindowClosed,
+    #[msg("The slot completing the lottery seed has not passed yet")]
+    LotterySeedPending,
+    #[msg("Depositor records of the pool still need migrate_depositor_record")]
+    RecordsNeedMigration,
+}
+
+/// Helpers for programs that deposit into and claim from pools through CPI,
+/// on top of the `cpi` module Anchor generates with the same feature.
+///
+/// The depositor may be a PDA of the calling program, owned by it and holding
+/// its own data: `signer_seeds` sign for it, its record and allocation are
+/// keyed by its address, and refunds credit its lamports. The payer of a
+/// deposit moves lamports through the system program, so it must be a
+/// system-owned account, e.g. a data-less PDA of the caller or the user's
+/// wallet. Pools with `reject_cpi` set refuse deposits made this way.
+#[cfg(feature = "cpi")]
+pub mod composer {
+    use super::*;
+
+    pub fn pool_address(owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"pool", owner.as_ref()], &crate::ID)
+    }
+
+    pub fn vault_address(pool: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"vault", pool.as_ref()], &crate::ID)
+    }
+
+    pub fn depositor_record_address(pool: &Pubkey, depositor: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"depositor", pool.as_ref(), depositor.as_ref()], &crate::ID)
+    }
+
+    pub fn stats_address() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"stats"], &crate::ID)
+    }
+
+    pub fn participant_address(wallet: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"participant", wallet.as_ref()], &crate::ID)
+    }
+
+    pub fn claim_bitmap_address(pool: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"claims", pool.as_ref()], &crate::ID)
+    }
+
+    pub fn token_vault_address(pool: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"token_vault", pool.as_ref()], &crate::ID)
+    }
+
+    pub fn stake_record_address(mint: &Pubkey, staker: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"stake", mint.as_ref(), staker.as_ref()], &crate::ID)
+    }
+
+    /// Accounts of a deposit into a pool without stake tiers or holder gates.
+    pub struct Deposit<'info> {
+        pub pool: AccountInfo<'info>,
+        pub pool_vault: AccountInfo<'info>,
+        pub depositor_record: AccountInfo<'info>,
+        /// Owns the record; a PDA of the caller needs no signature here,
+        /// except in pools with a slot gap, where it must be the payer too
+        pub beneficiary: AccountInfo<'info>,
+        /// System-owned and signing, through `signer_seeds` if it is a PDA
+        pub payer: AccountInfo<'info>,
+        pub system_program: AccountInfo<'info>,
+        pub instructions: AccountInfo<'info>,
+        pub stats: AccountInfo<'info>,
+        pub participant: AccountInfo<'info>,
+        /// The pool's SOL/USD price account, for pools with USD limits
+        pub price_oracle: Option<AccountInfo<'info>>,
+    }
+
+    /// Deposits `amount` lamports from `payer` for `beneficiary`. The
+    /// whitelist proof is only read during a pool's whitelist window.
+    pub fn deposit<'info>(
+        program: AccountInfo<'info>,
+        accounts: Deposit<'info>,
+        amount: u64,
+        whitelist_proof: Vec<[u8; 32]>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let accounts = crate::cpi::accounts::DepositSol {
+            pool: accounts.pool,
+            pool_vault: accounts.pool_vault,
+            depositor_record: accounts.depositor_record,
+            beneficiary: accounts.beneficiary,
+            payer: accounts.payer,
+            system_program: accounts.system_program,
+            instructions: accounts.instructions,
+            stats: accounts.stats,
+            participant: accounts.participant,
+            stake_record: None,
+            gate_token_account: None,
+            gate_metadata: None,
+            price_oracle: accounts.price_oracle,
+        };
+        crate::cpi::deposit_sol(CpiContext::new_with_signer(program, accounts, signer_seeds), amount, whitelist_proof)
+    }
+
+    /// Claims the depositor's Merkle allocation into a token account it owns.
+    pub fn claim_tokens<'info>(
+        program: AccountInfo<'info>,
+        accounts: crate::cpi::accounts::ClaimTokens<'info>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        crate::cpi::claim_tokens(CpiContext::new_with_signer(program, accounts, signer_seeds), amount, proof)
+    }
+
+    /// Refunds a deposit into a failed pool; the lamports go to the depositor.
+    pub fn refund<'info>(
+        program: AccountInfo<'info>,
+        accounts: crate::cpi::accounts::Refund<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        crate::cpi::refund(CpiContext::new_with_signer(program, accounts, signer_seeds))
+    }
+
+    /// Reads the pool's phase and fill ratios, e.g. to pick a pool to enter.
+    pub fn pool_status<'info>(program: AccountInfo<'info>, pool: AccountInfo<'info>) -> Result<PoolStatus> {
+        let accounts = crate::cpi::accounts::GetPoolStatus { pool };
+        Ok(crate::cpi::get_pool_status(CpiContext::new(program, accounts))?.get())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+//! The per-wallet slot gap of `configure_anti_bot`.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    InstructionData, ToAccountMetas,
+};
+use presale_program::{PresaleError, MAX_SLOT_GAP};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+const GAP: u64 = 10;
+
+fn configure_slot_gap(runtime: &mut Runtime, pool: &Pubkey, owner: &Pubkey, min_slot_gap: u64) -> ProgramResult {
+    let configure = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::ConfigurePool { pool: *pool, owner: *owner }.to_account_metas(None),
+        data: presale_program::instruction::ConfigureAntiBot {
+            whitelist_root: [0; 32],
+            whitelist_end: 0,
+            min_slot_gap,
+            reject_cpi: false,
+        }
+        .data(),
+    };
+    runtime.process(&[configure])
+}
+
+/// A pool with a `GAP`-slot gap and a funded depositor.
+fn setup() -> (Runtime, Pubkey, Pubkey) {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    configure_slot_gap(&mut runtime, &pool, &owner, GAP).expect("configure_anti_bot");
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    (runtime, pool, depositor)
+}
+
+#[test]
+fn wallets_wait_the_gap_between_deposits() {
+    let (mut runtime, pool, depositor) = setup();
+    let deposit = deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL);
+    runtime.process(std::slice::from_ref(&deposit)).expect("deposit");
+    assert_eq!(runtime.process(std::slice::from_ref(&deposit)), Err(program_error(PresaleError::DepositTooSoon)));
+
+    let clock = runtime.clock();
+    runtime.warp(clock.slot + GAP, clock.unix_timestamp + 4);
+    runtime.process(&[deposit]).expect("deposit after the gap");
+}
+
+#[test]
+fn others_cannot_restart_a_wallets_wait() {
+    let (mut runtime, pool, depositor) = setup();
+    let payer = Pubkey::new_unique();
+    runtime.airdrop(&payer, 10 * LAMPORTS_PER_SOL);
+
+    let deposit = deposit_instruction(&pool, &depositor, &payer, LAMPORTS_PER_SOL);
+    assert_eq!(runtime.process(&[deposit]), Err(program_error(PresaleError::BeneficiarySignatureRequired)));
+}
+
+#[test]
+fn gaps_are_at_most_a_day() {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    assert_eq!(MAX_SLOT_GAP, 216_000);
+
+    configure_slot_gap(&mut runtime, &pool, &owner, MAX_SLOT_GAP).expect("a day");
+    assert_eq!(
+        configure_slot_gap(&mut runtime, &pool, &owner, u64::MAX),
+        Err(program_error(PresaleError::InvalidSlotGap))
+    );
+}
@@ -0,0 +1,121 @@
+//! Deposits into pools gated by an attestation authority, which admits each
+//! depositor with an ed25519-signed allocation earlier in the transaction.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{ed25519_program, entrypoint::ProgramResult, instruction::Instruction},
+    InstructionData, ToAccountMetas,
+};
+use presale_program::{PresaleError, ATTESTATION_LEN};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+/// Stands in for the ed25519 precompile. The runtime checks signatures
+/// before any program runs; the presale program only parses what was signed.
+fn ed25519_precompile(_: &Pubkey, _: &[AccountInfo], _: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+/// pool || depositor || expiry || max allocation, as the authority signs it.
+fn attestation(pool: &Pubkey, depositor: &Pubkey, expiry: i64, max_allocation: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ATTESTATION_LEN);
+    message.extend_from_slice(pool.as_ref());
+    message.extend_from_slice(depositor.as_ref());
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message.extend_from_slice(&max_allocation.to_le_bytes());
+    message
+}
+
+/// An ed25519 program instruction by `authority` over `message`, with key,
+/// signature and message inline as the client lays them out.
+fn verify_instruction(authority: &Pubkey, message: &[u8]) -> Instruction {
+    let mut data = vec![1u8, 0];
+    // Signature, public key and message offsets, each in this instruction
+    for field in [48, u16::MAX, 16, u16::MAX, 112, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(authority.as_ref());
+    data.extend_from_slice(&[0; 64]);
+    data.extend_from_slice(message);
+    Instruction { program_id: ed25519_program::ID, accounts: vec![], data }
+}
+
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    authority: Pubkey,
+    depositor: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        runtime.add_program(ed25519_program::ID, ed25519_precompile);
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let authority = Pubkey::new_unique();
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigurePool { pool, owner }.to_account_metas(None),
+            data: presale_program::instruction::SetAttestationAuthority { authority }.data(),
+        };
+        runtime.process(&[configure]).expect("set_attestation_authority");
+        let depositor = Pubkey::new_unique();
+        runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+        Fixture { runtime, pool, authority, depositor }
+    }
+
+    /// An attestation of the pool's authority for the depositor, good for a minute.
+    fn attestation(&self, max_allocation: u64) -> Vec<u8> {
+        let expiry = self.runtime.clock().unix_timestamp + 60;
+        attestation(&self.pool, &self.depositor, expiry, max_allocation)
+    }
+
+    fn deposit(&mut self, verify: Option<Instruction>, amount: u64) -> std::result::Result<(), ProgramError> {
+        let deposit = deposit_instruction(&self.pool, &self.depositor, &self.depositor, amount);
+        self.runtime.advance(1);
+        self.runtime.process(&verify.into_iter().chain([deposit]).collect::<Vec<_>>())
+    }
+}
+
+#[test]
+fn attested_depositors_deposit_up_to_their_allocation() {
+    let mut fixture = Fixture::new();
+    let verify = verify_instruction(&fixture.authority, &fixture.attestation(2 * LAMPORTS_PER_SOL));
+
+    fixture.deposit(Some(verify.clone()), LAMPORTS_PER_SOL).expect("deposit");
+    // The allocation caps the depositor's total, not each deposit
+    assert_eq!(
+        fixture.deposit(Some(verify.clone()), 2 * LAMPORTS_PER_SOL),
+        Err(program_error(PresaleError::AllocationExceeded))
+    );
+    fixture.deposit(Some(verify), LAMPORTS_PER_SOL).expect("deposit");
+}
+
+#[test]
+fn deposits_without_a_matching_attestation_are_rejected() {
+    let mut fixture = Fixture::new();
+    let missing = Err(program_error(PresaleError::MissingAttestation));
+
+    assert_eq!(fixture.deposit(None, LAMPORTS_PER_SOL), missing);
+    let other_signer = verify_instruction(&Pubkey::new_unique(), &fixture.attestation(LAMPORTS_PER_SOL));
+    assert_eq!(fixture.deposit(Some(other_signer), LAMPORTS_PER_SOL), missing);
+    let now = fixture.runtime.clock().unix_timestamp;
+    let other_depositor = attestation(&fixture.pool, &Pubkey::new_unique(), now + 60, LAMPORTS_PER_SOL);
+    let other_depositor = verify_instruction(&fixture.authority, &other_depositor);
+    assert_eq!(fixture.deposit(Some(other_depositor), LAMPORTS_PER_SOL), missing);
+    let short = fixture.attestation(LAMPORTS_PER_SOL)[..ATTESTATION_LEN - 1].to_vec();
+    assert_eq!(fixture.deposit(Some(verify_instruction(&fixture.authority, &short)), LAMPORTS_PER_SOL), missing);
+
+    // Keys or messages read from another instruction are not inline
+    let mut elsewhere = verify_instruction(&fixture.authority, &fixture.attestation(LAMPORTS_PER_SOL));
+    elsewhere.data[8..10].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(fixture.deposit(Some(elsewhere), LAMPORTS_PER_SOL), missing);
+
+    let expired = attestation(&fixture.pool, &fixture.depositor, now, LAMPORTS_PER_SOL);
+    assert_eq!(
+        fixture.deposit(Some(verify_instruction(&fixture.authority, &expired)), LAMPORTS_PER_SOL),
+        Err(program_error(PresaleError::AttestationExpired))
+    );
+}
@@ -0,0 +1,55 @@
+//! `audit_pool` against the vault's real balance.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{composer, AuditReport};
+use runtime::{deposit_instruction, Runtime, LAMPORTS_PER_SOL};
+
+fn audit(runtime: &mut Runtime, pool: &Pubkey) -> AuditReport {
+    let audit = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::AuditPool {
+            pool: *pool,
+            pool_vault: composer::vault_address(pool).0,
+            claim_bitmap: None,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::AuditPool {}.data(),
+    };
+    runtime.process(&[audit]).expect("audit_pool");
+    let (_, data) = runtime.return_data().expect("report");
+    AuditReport::try_from_slice(&data).unwrap()
+}
+
+#[test]
+fn donations_to_a_claimed_vault_pass_the_audit() {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    runtime.advance(3601);
+
+    let claim = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::ClaimFunds {
+            pool,
+            pool_vault: composer::vault_address(&pool).0,
+            owner,
+            stats: composer::stats_address().0,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::ClaimFunds {}.data(),
+    };
+    runtime.process(&[claim]).expect("claim_funds");
+    assert!(audit(&mut runtime, &pool).passed());
+
+    // Anyone can send lamports to the vault once it has been paid out
+    runtime.airdrop(&composer::vault_address(&pool).0, 1);
+    let report = audit(&mut runtime, &pool);
+    assert_eq!(report.liabilities, 0);
+    assert!(report.claim_consistent);
+    assert!(report.passed());
+}
@@ -0,0 +1,58 @@
+//! Deposits a payer funds on behalf of a separate beneficiary, who owns the
+//! resulting position.
+
+mod runtime;
+
+use anchor_lang::{
+    error::ErrorCode, prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, DepositMade, DepositorRecord, Participant};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+fn refund_instruction(pool: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::Refund {
+            pool: *pool,
+            pool_vault: composer::vault_address(pool).0,
+            depositor_record: composer::depositor_record_address(pool, depositor).0,
+            depositor: *depositor,
+            stats: composer::stats_address().0,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::Refund {}.data(),
+    }
+}
+
+#[test]
+fn beneficiaries_own_what_others_pay_for() {
+    let mut runtime = Runtime::new();
+    // A soft cap above the deposit, so the pool fails and refunds open
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 5 * LAMPORTS_PER_SOL);
+    let (payer, beneficiary) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&payer, 10 * LAMPORTS_PER_SOL);
+
+    // The beneficiary does not sign a deposit into a pool without tiers or a slot gap
+    runtime.take_events();
+    runtime.process(&[deposit_instruction(&pool, &beneficiary, &payer, LAMPORTS_PER_SOL)]).expect("deposit");
+    assert_eq!(runtime.lamports(&beneficiary), 0);
+
+    let record: DepositorRecord = runtime.load(&composer::depositor_record_address(&pool, &beneficiary).0);
+    assert_eq!((record.depositor, record.amount), (beneficiary, LAMPORTS_PER_SOL));
+    assert!(runtime.account(&composer::depositor_record_address(&pool, &payer).0).is_none());
+    let participant: Participant = runtime.load(&composer::participant_address(&beneficiary).0);
+    assert_eq!(participant.wallet, beneficiary);
+    let events = runtime.take_events();
+    let event = events.iter().find(|event| event.starts_with(DepositMade::DISCRIMINATOR)).expect("DepositMade");
+    let event = DepositMade::try_from_slice(&event[8..]).unwrap();
+    assert_eq!((event.depositor, event.payer), (beneficiary, payer));
+
+    // Refunds go to the beneficiary, not to whoever paid
+    runtime.advance(3601);
+    assert_eq!(
+        runtime.process(&[refund_instruction(&pool, &payer)]),
+        Err(program_error(ErrorCode::AccountNotInitialized))
+    );
+    runtime.process(&[refund_instruction(&pool, &beneficiary)]).expect("refund");
+    assert_eq!(runtime.lamports(&beneficiary), LAMPORTS_PER_SOL);
+}
@@ -0,0 +1,96 @@
+//! Early-bird bonuses, which weigh early deposits more in the token split.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{composer, DepositorRecord, Pool, PresaleError};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+fn configure_bonus(pool: &Pubkey, owner: &Pubkey, ends: Vec<i64>, bonus_bps: Vec<u16>) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::ConfigurePool { pool: *pool, owner: *owner }.to_account_metas(None),
+        data: presale_program::instruction::ConfigureBonus { ends, bonus_bps }.data(),
+    }
+}
+
+#[test]
+fn earlier_deposits_earn_a_larger_share() {
+    let mut runtime = Runtime::new();
+    runtime.add_token_program();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    let start = runtime.clock().unix_timestamp;
+    // 20% for the first ten minutes, then 10% until half an hour in
+    let configure = configure_bonus(&pool, &owner, vec![start + 600, start + 1800], vec![2_000, 1_000]);
+    runtime.process(&[configure]).expect("configure_bonus");
+
+    let depositors = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    for (depositor, wait) in depositors.iter().zip([0, 900, 1100]) {
+        runtime.advance(wait);
+        runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+        runtime.process(&[deposit_instruction(&pool, depositor, depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    }
+
+    let records: Vec<DepositorRecord> = depositors
+        .iter()
+        .map(|depositor| runtime.load(&composer::depositor_record_address(&pool, depositor).0))
+        .collect();
+    let weights: Vec<u64> = records.iter().map(|record| record.weighted_amount).collect();
+    assert_eq!(weights, [12, 11, 10].map(|tenths| tenths * LAMPORTS_PER_SOL / 10));
+    let state: Pool = runtime.load(&pool);
+    // SOL stays at face value; only the split is weighted
+    assert_eq!((state.total_raised, state.total_weighted), (3 * LAMPORTS_PER_SOL, 33 * LAMPORTS_PER_SOL / 10));
+
+    runtime.advance(3600);
+    let mint = runtime.create_mint(&owner);
+    let owner_tokens = runtime.create_token_account(&mint, &owner, 3_300);
+    let finalize = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::FinalizePool {
+            pool,
+            claim_bitmap: composer::claim_bitmap_address(&pool).0,
+            token_mint: mint,
+            pool_token_vault: composer::token_vault_address(&pool).0,
+            owner_token_account: owner_tokens,
+            owner,
+            token_program: anchor_spl::token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::FinalizePool { merkle_root: [0; 32], total_tokens: 3_300 }.data(),
+    };
+    runtime.process(&[finalize]).expect("finalize_pool");
+    let state: Pool = runtime.load(&pool);
+    let entitlements: Vec<u64> = records.iter().map(|record| state.entitlement(record.allocation_weight())).collect();
+    assert_eq!(entitlements, [1_200, 1_100, 1_000]);
+}
+
+#[test]
+fn bonuses_decay_within_the_deposit_window() {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    let start = runtime.clock().unix_timestamp;
+    let invalid = Err(program_error(PresaleError::InvalidBonusConfig));
+
+    // Rising bonuses, steps past the expiry, unsorted ends and mismatched lists
+    let rising = configure_bonus(&pool, &owner, vec![start + 600, start + 1800], vec![1_000, 2_000]);
+    assert_eq!(runtime.process(&[rising]), invalid);
+    assert_eq!(runtime.process(&[configure_bonus(&pool, &owner, vec![start + 3601], vec![1_000])]), invalid);
+    let unsorted = configure_bonus(&pool, &owner, vec![start + 1800, start + 600], vec![2_000, 1_000]);
+    assert_eq!(runtime.process(&[unsorted]), invalid);
+    assert_eq!(runtime.process(&[configure_bonus(&pool, &owner, vec![start + 600], vec![])]), invalid);
+    assert_eq!(runtime.process(&[configure_bonus(&pool, &owner, vec![start + 600], vec![10_001])]), invalid);
+
+    runtime.process(&[configure_bonus(&pool, &owner, vec![start + 3600], vec![10_000])]).expect("configure_bonus");
+
+    // Deposits fix the split, so the schedule cannot change after the first
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    assert_eq!(
+        runtime.process(&[configure_bonus(&pool, &owner, vec![], vec![])]),
+        Err(program_error(PresaleError::PoolAlreadyActive))
+    );
+}
@@ -0,0 +1,206 @@
+//! A vault strategy program depositing into, reading and refunding a pool
+//! through the `composer` helpers, with PDAs of its own as depositor and payer.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, program::set_return_data},
+    system_program, AnchorSerialize, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, DepositorRecord, Pool, PoolPhase, PoolStatus, PresaleError};
+use runtime::{program_error, Runtime, LAMPORTS_PER_SOL};
+
+const STRATEGY_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+const DEPOSIT: u8 = 0;
+const STATUS: u8 = 1;
+const REFUND: u8 = 2;
+
+/// Holds the position; refunds credit it.
+fn strategy_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"strategy"], &STRATEGY_ID)
+}
+
+/// Data-less and system-owned, so it can pay for deposits.
+fn funding_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"funding"], &STRATEGY_ID)
+}
+
+/// The strategy's entrypoint. Accounts: presale program, pool, vault,
+/// depositor record, strategy PDA, funding PDA, system program, instructions
+/// sysvar, stats, participant.
+fn strategy<'a>(_program_id: &Pubkey, accounts: &'a [AccountInfo<'a>], data: &[u8]) -> ProgramResult {
+    let [program, pool, pool_vault, depositor_record, strategy, funding, system_program, instructions, stats, participant] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let strategy_bump = [strategy_address().1];
+    let funding_bump = [funding_address().1];
+    let strategy_seeds: &[&[u8]] = &[b"strategy", &strategy_bump];
+    let funding_seeds: &[&[u8]] = &[b"funding", &funding_bump];
+
+    match data[0] {
+        DEPOSIT => composer::deposit(
+            program.clone(),
+            composer::Deposit {
+                pool: pool.clone(),
+                pool_vault: pool_vault.clone(),
+                depositor_record: depositor_record.clone(),
+                beneficiary: strategy.clone(),
+                payer: funding.clone(),
+                system_program: system_program.clone(),
+                instructions: instructions.clone(),
+                stats: stats.clone(),
+                participant: participant.clone(),
+                price_oracle: None,
+            },
+            u64::from_le_bytes(data[1..9].try_into().unwrap()),
+            Vec::new(),
+            &[funding_seeds],
+        )?,
+        STATUS => {
+            let status = composer::pool_status(program.clone(), pool.clone())?;
+            set_return_data(&status.try_to_vec()?);
+        }
+        REFUND => composer::refund(
+            program.clone(),
+            presale_program::cpi::accounts::Refund {
+                pool: pool.clone(),
+                pool_vault: pool_vault.clone(),
+                depositor_record: depositor_record.clone(),
+                depositor: strategy.clone(),
+                stats: stats.clone(),
+            },
+            &[strategy_seeds],
+        )?,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+struct Fixture {
+    runtime: Runtime,
+    owner: Pubkey,
+    pool: Pubkey,
+}
+
+impl Fixture {
+    /// A pool taking 0.1 to 5 SOL per deposit for an hour, with a 10 SOL soft cap.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        runtime.add_program(STRATEGY_ID, strategy);
+        runtime.airdrop(&funding_address().0, 10 * LAMPORTS_PER_SOL);
+
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 10 * LAMPORTS_PER_SOL);
+        Fixture { runtime, owner, pool }
+    }
+
+    fn strategy_instruction(&self, data: Vec<u8>) -> Instruction {
+        let strategy = strategy_address().0;
+        let writable = |pubkey| AccountMeta::new(pubkey, false);
+        let funding = funding_address().0;
+        Instruction {
+            program_id: STRATEGY_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(presale_program::ID, false),
+                writable(self.pool),
+                writable(composer::vault_address(&self.pool).0),
+                writable(composer::depositor_record_address(&self.pool, &strategy).0),
+                writable(strategy),
+                writable(funding),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(anchor_lang::solana_program::sysvar::instructions::ID, false),
+                writable(composer::stats_address().0),
+                writable(composer::participant_address(&strategy).0),
+            ],
+            data,
+        }
+    }
+
+    fn deposit(&mut self, amount: u64) -> ProgramResult {
+        let mut data = vec![DEPOSIT];
+        data.extend_from_slice(&amount.to_le_bytes());
+        let instruction = self.strategy_instruction(data);
+        self.runtime.process(&[instruction])
+    }
+
+    fn status(&mut self) -> PoolStatus {
+        let instruction = self.strategy_instruction(vec![STATUS]);
+        self.runtime.process(&[instruction]).expect("status");
+        let (program, data) = self.runtime.return_data().expect("return data");
+        assert_eq!(program, STRATEGY_ID);
+        PoolStatus::try_from_slice(&data).unwrap()
+    }
+}
+
+#[test]
+fn strategy_deposits_reads_status_and_refunds() {
+    let mut fixture = Fixture::new();
+    let strategy = strategy_address().0;
+    let vault = composer::vault_address(&fixture.pool).0;
+    let vault_before = fixture.runtime.lamports(&vault);
+
+    fixture.deposit(2 * LAMPORTS_PER_SOL).expect("deposit through composer");
+
+    let record: DepositorRecord =
+        fixture.runtime.load(&composer::depositor_record_address(&fixture.pool, &strategy).0);
+    assert_eq!(record.depositor, strategy);
+    assert_eq!(record.pool, fixture.pool);
+    assert_eq!(record.amount, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(fixture.runtime.lamports(&vault), vault_before + 2 * LAMPORTS_PER_SOL);
+    let pool: Pool = fixture.runtime.load(&fixture.pool);
+    assert_eq!(pool.total_raised, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(pool.depositor_count, 1);
+
+    let status = fixture.status();
+    assert!(status.phase == PoolPhase::Open);
+    assert_eq!(status.total_raised, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(status.depositor_count, 1);
+
+    // Expiring below the soft cap fails the pool; the strategy PDA signs its refund
+    fixture.runtime.advance(3601);
+    assert!(fixture.status().phase == PoolPhase::Failed);
+    let strategy_before = fixture.runtime.lamports(&strategy);
+    let refund = fixture.strategy_instruction(vec![REFUND]);
+    fixture.runtime.process(&[refund]).expect("refund through composer");
+    assert_eq!(fixture.runtime.lamports(&strategy), strategy_before + 2 * LAMPORTS_PER_SOL);
+    let record: DepositorRecord =
+        fixture.runtime.load(&composer::depositor_record_address(&fixture.pool, &strategy).0);
+    assert!(record.is_refunded);
+}
+
+#[test]
+fn pools_rejecting_cpi_refuse_composer_deposits() {
+    let mut fixture = Fixture::new();
+    let configure = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::ConfigurePool { pool: fixture.pool, owner: fixture.owner }
+            .to_account_metas(None),
+        data: presale_program::instruction::ConfigureAntiBot {
+            whitelist_root: [0; 32],
+            whitelist_end: 0,
+            min_slot_gap: 0,
+            reject_cpi: true,
+        }
+        .data(),
+    };
+    fixture.runtime.process(&[configure]).expect("configure_anti_bot");
+
+    assert_eq!(fixture.deposit(LAMPORTS_PER_SOL), Err(program_error(PresaleError::CpiDepositRejected)));
+}
+
+#[test]
+fn composer_addresses_match_the_program_seeds() {
+    let owner = Pubkey::new_unique();
+    let (pool, bump) = composer::pool_address(&owner);
+    assert_eq!(Pubkey::create_program_address(&[b"pool", owner.as_ref(), &[bump]], &presale_program::ID), Ok(pool));
+    let depositor = Pubkey::new_unique();
+    assert_eq!(
+        composer::depositor_record_address(&pool, &depositor).0,
+        Pubkey::find_program_address(&[b"depositor", pool.as_ref(), depositor.as_ref()], &presale_program::ID).0
+    );
+    assert_ne!(composer::vault_address(&pool).0, composer::token_vault_address(&pool).0);
+}
@@ -0,0 +1,209 @@
+//! Bonding-curve math against float references, its rounding guarantees, and
+//! the vectors `presale-client`'s copy of it must reproduce.
+
+use presale_program::{curve_cost, exp2_q64, log2_q64, mul_div, CurveKind, Rounding};
+
+const Q64: u128 = 1 << 64;
+
+/// Shared with `presale-client/src/curve.rs`, which asserts the same values.
+const VECTORS: &str = include_str!("curve_vectors.txt");
+
+struct Curve {
+    kind: CurveKind,
+    start: u64,
+    end: u64,
+    supply: u64,
+    decimals: u8,
+}
+
+impl Curve {
+    fn cost(&self, sold: u64, rounding: Rounding) -> Option<u64> {
+        curve_cost(self.kind, self.start, self.end, self.supply, self.decimals, sold, rounding)
+    }
+
+    /// `Pool::curve_buy_cost` at position `sold`.
+    fn buy_cost(&self, sold: u64, amount: u64) -> u64 {
+        self.cost(sold + amount, Rounding::Up).unwrap() - self.cost(sold, Rounding::Down).unwrap()
+    }
+
+    /// `Pool::curve_sell_proceeds` at position `sold`.
+    fn sell_proceeds(&self, sold: u64, amount: u64) -> u64 {
+        self.cost(sold, Rounding::Down).unwrap().saturating_sub(self.cost(sold - amount, Rounding::Up).unwrap())
+    }
+
+    /// The integral the fixed-point math approximates.
+    fn reference(&self, sold: u64) -> f64 {
+        let (start, end, supply, x) = (self.start as f64, self.end as f64, self.supply as f64, sold as f64);
+        let unit = 10f64.powi(self.decimals as i32);
+        match self.kind {
+            CurveKind::None => unreachable!(),
+            CurveKind::Linear => (start * x + (end - start) * x * x / (2.0 * supply)) / unit,
+            CurveKind::Exponential => {
+                let ratio = end / start;
+                start * supply * (ratio.powf(x / supply) - 1.0) / (ratio.ln() * unit)
+            }
+        }
+    }
+
+    /// Positions from empty to sold out.
+    fn positions(&self) -> [u64; 7] {
+        let supply = self.supply;
+        [0, 1, supply / 7, supply / 3, supply / 2, supply - 1, supply]
+    }
+}
+
+/// The curves the vectors cover: typical, flat, 18- and 0-decimal mints, and
+/// a tiny supply where every step rounds.
+fn curves() -> Vec<Curve> {
+    let curve = |kind, start, end, supply, decimals| Curve { kind, start, end, supply, decimals };
+    vec![
+        curve(CurveKind::Linear, 1_000_000, 3_000_000, 1_000_000_000_000_000, 9),
+        curve(CurveKind::Linear, 5_000_000, 5_000_000, 100_000_000_000, 6),
+        curve(CurveKind::Linear, 1_000_000_000, 2_000_000_000, 10_000_000_000_000_000_000, 18),
+        curve(CurveKind::Linear, 1_000, 100_000, 1_000, 0),
+        curve(CurveKind::Exponential, 1_000_000, 100_000_000, 1_000_000_000_000_000, 9),
+        curve(CurveKind::Exponential, 1_000_000_000, 2_000_000_000, 10_000_000_000_000_000_000, 18),
+        curve(CurveKind::Exponential, 1, 3, 7, 0),
+    ]
+}
+
+fn kind_name(kind: CurveKind) -> &'static str {
+    match kind {
+        CurveKind::None => "none",
+        CurveKind::Linear => "linear",
+        CurveKind::Exponential => "exponential",
+    }
+}
+
+#[test]
+fn costs_match_float_references() {
+    for curve in curves() {
+        for sold in curve.positions() {
+            let reference = curve.reference(sold);
+            let (down, up) = (curve.cost(sold, Rounding::Down).unwrap(), curve.cost(sold, Rounding::Up).unwrap());
+            // Each of the two rounded terms of a linear cost is off by up to a lamport
+            let tolerance = reference * 1e-9 + 2.0;
+            for cost in [down, up] {
+                let kind = kind_name(curve.kind);
+                assert!((cost as f64 - reference).abs() <= tolerance, "{kind} at {sold}: {cost} vs {reference}");
+            }
+        }
+    }
+}
+
+#[test]
+fn sold_out_costs_have_closed_forms() {
+    // (start + end) / 2 per token over a million tokens
+    let linear = &curves()[0];
+    assert_eq!(linear.cost(linear.supply, Rounding::Down), Some(2_000_000 * 1_000_000));
+    assert_eq!(linear.cost(linear.supply, Rounding::Up), Some(2_000_000 * 1_000_000));
+
+    // (end - start) * tokens / ln(end / start), with ln(2) = 0.693147...
+    let exponential = &curves()[5];
+    let exact = 1e9 * 10.0 / 2f64.ln();
+    let up = exponential.cost(exponential.supply, Rounding::Up).unwrap();
+    assert!((up as f64 - exact).abs() <= 1.0, "{up} vs {exact}");
+}
+
+#[test]
+fn cost_is_monotonic_in_sold() {
+    for curve in curves() {
+        for rounding in [Rounding::Down, Rounding::Up] {
+            let mut previous = 0;
+            for step in 0..=64 {
+                let sold = (curve.supply as u128 * step / 64) as u64;
+                let cost = curve.cost(sold, rounding).unwrap();
+                assert!(cost >= previous, "{} falls at {sold}", kind_name(curve.kind));
+                previous = cost;
+            }
+        }
+    }
+}
+
+#[test]
+fn buys_round_up_and_sells_round_down() {
+    for curve in curves() {
+        for sold in curve.positions() {
+            assert!(curve.cost(sold, Rounding::Up) >= curve.cost(sold, Rounding::Down));
+            for amount in [1, curve.supply / 10, curve.supply - sold] {
+                if amount == 0 || sold + amount > curve.supply {
+                    continue;
+                }
+                // Buying then selling back the same tokens never pays out more than it took in
+                assert!(curve.buy_cost(sold, amount) >= curve.sell_proceeds(sold + amount, amount));
+            }
+        }
+    }
+}
+
+#[test]
+fn empty_curves_cost_nothing() {
+    for curve in curves() {
+        assert_eq!(curve.cost(0, Rounding::Down), Some(0));
+        assert_eq!(curve.cost(0, Rounding::Up), Some(0));
+    }
+}
+
+#[test]
+fn flat_curves_price_every_token_the_same() {
+    let flat = &curves()[1];
+    assert_eq!(flat.buy_cost(0, 1_000_000), 5_000_000);
+    assert_eq!(flat.buy_cost(flat.supply - 1_000_000, 1_000_000), 5_000_000);
+    // A growth factor of one has no logarithm to divide by
+    assert_eq!(curve_cost(CurveKind::Exponential, 5, 5, 1_000, 0, 500, Rounding::Up), None);
+    assert_eq!(curve_cost(CurveKind::None, 5, 10, 1_000, 0, 500, Rounding::Up), None);
+}
+
+#[test]
+fn fixed_point_helpers_round_as_documented() {
+    assert_eq!(mul_div(7, 3, 2, Rounding::Down), Some(10));
+    assert_eq!(mul_div(7, 3, 2, Rounding::Up), Some(11));
+    assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down), Some(u128::MAX));
+    assert_eq!(mul_div(u128::MAX, 2, 1, Rounding::Down), None);
+    assert_eq!(mul_div(1, 1, 0, Rounding::Down), None);
+
+    assert_eq!(log2_q64(1, 1), 0);
+    assert_eq!(log2_q64(8, 1), 3 * Q64);
+    assert_eq!(exp2_q64(0), Some(Q64));
+    assert_eq!(exp2_q64(3 * Q64), Some(8 * Q64));
+    assert_eq!(exp2_q64(63 * Q64), None);
+    let sqrt2 = exp2_q64(Q64 / 2).unwrap() as f64 / Q64 as f64;
+    assert!((sqrt2 - 2f64.sqrt()).abs() < 1e-15);
+    let log2_3 = log2_q64(3, 1) as f64 / Q64 as f64;
+    assert!((log2_3 - 3f64.log2()).abs() < 1e-15);
+}
+
+/// Lines of `curve_vectors.txt` as the program computes them.
+fn vector_lines() -> Vec<String> {
+    let mut lines = Vec::new();
+    for curve in curves() {
+        for sold in curve.positions() {
+            lines.push(format!(
+                "{} {} {} {} {} {} {} {}",
+                kind_name(curve.kind),
+                curve.start,
+                curve.end,
+                curve.supply,
+                curve.decimals,
+                sold,
+                curve.cost(sold, Rounding::Down).unwrap(),
+                curve.cost(sold, Rounding::Up).unwrap(),
+            ));
+        }
+    }
+    lines
+}
+
+#[test]
+fn costs_match_the_shared_vectors() {
+    let expected: Vec<&str> = VECTORS.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(vector_lines(), expected);
+}
+
+#[test]
+#[ignore = "prints the body of curve_vectors.txt"]
+fn print_vectors() {
+    for line in vector_lines() {
+        println!("{line}");
+    }
+}
@@ -0,0 +1,299 @@
+//! Deposits and transfers into depositor records in every state: missing,
+//! prefunded, emptied by a transfer, and written before versioning, and the
+//! migration of records from before indexes.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{
+    composer, merkle_leaf, AccountMigrated, DepositorRecord, LaunchpadStats, Participant, Pool, PresaleError,
+};
+use runtime::{deposit_instruction, program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+/// Writes a record as the first program version laid it out, which had no
+/// index (`None`), or as the last one before versioning.
+fn write_v0_record(
+    runtime: &mut Runtime,
+    pool: &Pubkey,
+    depositor: &Pubkey,
+    amount: u64,
+    index: Option<u32>,
+) -> Pubkey {
+    let address = composer::depositor_record_address(pool, depositor).0;
+    let mut data = DepositorRecord::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(depositor.as_ref());
+    data.extend_from_slice(pool.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&runtime.clock().unix_timestamp.to_le_bytes());
+    if let Some(index) = index {
+        data.extend_from_slice(&index.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+    }
+    let lamports = Rent::default().minimum_balance(data.len());
+    runtime.set_account(address, AccountState { lamports, data, owner: presale_program::ID, executable: false });
+    address
+}
+
+/// Asserts the record at `address` has today's size, rent and version.
+fn assert_current(runtime: &Runtime, address: &Pubkey) -> DepositorRecord {
+    let account = runtime.account(address).expect("record");
+    assert_eq!(account.owner, presale_program::ID);
+    assert_eq!(account.data.len(), DepositorRecord::SPACE);
+    assert!(account.lamports >= Rent::default().minimum_balance(DepositorRecord::SPACE));
+    let record: DepositorRecord = runtime.load(address);
+    assert_eq!(record.version, DepositorRecord::VERSION);
+    record
+}
+
+/// `transfer_position` in a pool without gates.
+fn transfer_instruction(pool: &Pubkey, owner: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::TransferPosition {
+            pool: *pool,
+            from_record: composer::depositor_record_address(pool, owner).0,
+            to_record: composer::depositor_record_address(pool, recipient).0,
+            recipient: *recipient,
+            owner: *owner,
+            system_program: anchor_lang::system_program::ID,
+            instructions: anchor_lang::solana_program::sysvar::instructions::ID,
+            stats: composer::stats_address().0,
+            participant: composer::participant_address(recipient).0,
+            gate_token_account: None,
+            gate_metadata: None,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::TransferPosition { amount, whitelist_proof: Vec::new() }.data(),
+    }
+}
+
+fn migrated(runtime: &mut Runtime) -> bool {
+    runtime.take_events().iter().any(|event| event.starts_with(AccountMigrated::DISCRIMINATOR))
+}
+
+#[test]
+fn deposits_grow_records_from_before_versioning() {
+    let mut runtime = Runtime::new();
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 0);
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    let address = write_v0_record(&mut runtime, &pool, &depositor, LAMPORTS_PER_SOL, Some(0));
+    runtime.take_events();
+
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+
+    let record = assert_current(&runtime, &address);
+    assert_eq!(record.depositor, depositor);
+    assert_eq!(record.pool, pool);
+    assert_eq!(record.amount, 2 * LAMPORTS_PER_SOL);
+    assert!(migrated(&mut runtime));
+
+    // Grown once; later deposits leave it as is
+    runtime.advance(1);
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    assert_eq!(assert_current(&runtime, &address).amount, 3 * LAMPORTS_PER_SOL);
+    assert!(!migrated(&mut runtime));
+}
+
+#[test]
+fn first_deposits_create_records_at_prefunded_addresses() {
+    let mut runtime = Runtime::new();
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 0);
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    let address = composer::depositor_record_address(&pool, &depositor).0;
+    // Anyone can send lamports to the address before the record exists
+    runtime.airdrop(&address, 1);
+
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+
+    let record = assert_current(&runtime, &address);
+    assert_eq!(record.amount, LAMPORTS_PER_SOL);
+    assert_eq!(record.depositor, depositor);
+}
+
+#[test]
+fn transfers_grow_the_recipients_older_record() {
+    let mut runtime = Runtime::new();
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 0);
+    let (owner, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&owner, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &owner, &owner, 2 * LAMPORTS_PER_SOL)]).expect("deposit");
+    let address = write_v0_record(&mut runtime, &pool, &recipient, LAMPORTS_PER_SOL, Some(1));
+
+    let transfer = transfer_instruction(&pool, &owner, &recipient, LAMPORTS_PER_SOL);
+    runtime.process(&[transfer]).expect("transfer_position");
+
+    assert_eq!(assert_current(&runtime, &address).amount, 2 * LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn emptied_records_keep_their_index() {
+    let mut runtime = Runtime::new();
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 0);
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for depositor in [&first, &second] {
+        runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+        runtime.process(&[deposit_instruction(&pool, depositor, depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    }
+
+    // The first depositor hands over everything, then deposits again
+    runtime.process(&[transfer_instruction(&pool, &first, &second, LAMPORTS_PER_SOL)]).expect("transfer_position");
+    let address = composer::depositor_record_address(&pool, &first).0;
+    assert_eq!(runtime.load::<DepositorRecord>(&address).amount, 0);
+    runtime.advance(1);
+    runtime.process(&[deposit_instruction(&pool, &first, &first, LAMPORTS_PER_SOL)]).expect("deposit");
+
+    let record: DepositorRecord = runtime.load(&address);
+    assert_eq!(record.index, 0);
+    assert_eq!(record.amount, LAMPORTS_PER_SOL);
+    let pool: Pool = runtime.load(&pool);
+    assert_eq!(pool.depositor_count, 2);
+}
+
+#[test]
+fn recipients_count_as_depositors_once() {
+    let mut runtime = Runtime::new();
+    let pool = runtime.create_pool(&Pubkey::new_unique(), 0);
+    let (owner, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&owner, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &owner, &owner, 3 * LAMPORTS_PER_SOL)]).expect("deposit");
+    let unique_depositors =
+        |runtime: &Runtime| runtime.load::<LaunchpadStats>(&composer::stats_address().0).unique_depositors;
+    assert_eq!(unique_depositors(&runtime), 1);
+
+    runtime.process(&[transfer_instruction(&pool, &owner, &recipient, LAMPORTS_PER_SOL)]).expect("transfer_position");
+    assert_eq!(unique_depositors(&runtime), 2);
+    let participant: Participant = runtime.load(&composer::participant_address(&recipient).0);
+    assert_eq!(participant.wallet, recipient);
+
+    // Neither a second transfer nor the recipient's own deposit counts it again
+    runtime.advance(1);
+    runtime.process(&[transfer_instruction(&pool, &owner, &recipient, LAMPORTS_PER_SOL)]).expect("transfer_position");
+    runtime.airdrop(&recipient, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &recipient, &recipient, LAMPORTS_PER_SOL)]).expect("deposit");
+    assert_eq!(unique_depositors(&runtime), 2);
+}
+
+/// Cuts `pool` back to the first layout, holding `records` deposits of 1 SOL.
+fn write_first_pool(runtime: &mut Runtime, pool: &Pubkey, records: u32) {
+    let mut account = runtime.account(pool).expect("pool").clone();
+    account.data.truncate(Pool::UNINDEXED_SPACE);
+    // total_raised and depositor_count; the first layout had no version
+    let raised = records as u64 * LAMPORTS_PER_SOL;
+    account.data[48..56].copy_from_slice(&raised.to_le_bytes());
+    account.data[73..77].copy_from_slice(&records.to_le_bytes());
+    runtime.set_account(*pool, account);
+    runtime.airdrop(&composer::vault_address(pool).0, raised);
+}
+
+fn migrate_pool_instruction(pool: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::MigratePool {
+            pool: *pool,
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::MigratePool {}.data(),
+    }
+}
+
+fn migrate_record_instruction(pool: &Pubkey, depositor: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::MigrateDepositorRecord {
+            depositor_record: composer::depositor_record_address(pool, depositor).0,
+            pool: *pool,
+            payer: *payer,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::MigrateDepositorRecord {}.data(),
+    }
+}
+
+/// Sorted-pair parent of two leaves, as `verify_merkle_proof` hashes it.
+fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    anchor_lang::solana_program::hash::hashv(&[&[1u8], &left, &right]).to_bytes()
+}
+
+#[test]
+fn records_from_before_indexes_claim_separately() {
+    let mut runtime = Runtime::new();
+    runtime.add_token_program();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+    write_first_pool(&mut runtime, &pool, 2);
+    for depositor in &depositors {
+        write_v0_record(&mut runtime, &pool, depositor, LAMPORTS_PER_SOL, None);
+        runtime.airdrop(depositor, LAMPORTS_PER_SOL);
+    }
+
+    // Neither deposits nor record migrations index records before the pool is migrated
+    let deposit = deposit_instruction(&pool, &depositors[0], &depositors[0], LAMPORTS_PER_SOL);
+    assert_eq!(runtime.process(&[deposit]), Err(program_error(PresaleError::AccountNeedsMigration)));
+    let migrate = migrate_record_instruction(&pool, &depositors[0], &owner);
+    assert_eq!(runtime.process(&[migrate]), Err(program_error(PresaleError::AccountNeedsMigration)));
+
+    runtime.process(&[migrate_pool_instruction(&pool, &owner)]).expect("migrate_pool");
+    assert_eq!(runtime.load::<Pool>(&pool).unindexed_records, 2);
+    runtime.process(&[migrate_record_instruction(&pool, &depositors[0], &owner)]).expect("migrate_depositor_record");
+
+    // Finalizing waits for the second record
+    runtime.advance(3601);
+    let mint = runtime.create_mint(&owner);
+    let owner_tokens = runtime.create_token_account(&mint, &owner, 1_000);
+    let finalize = |merkle_root| Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::FinalizePool {
+            pool,
+            claim_bitmap: composer::claim_bitmap_address(&pool).0,
+            token_mint: mint,
+            pool_token_vault: composer::token_vault_address(&pool).0,
+            owner_token_account: owner_tokens,
+            owner,
+            token_program: anchor_spl::token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::FinalizePool { merkle_root, total_tokens: 1_000 }.data(),
+    };
+    assert_eq!(runtime.process(&[finalize([0; 32])]), Err(program_error(PresaleError::RecordsNeedMigration)));
+    runtime.process(&[migrate_record_instruction(&pool, &depositors[1], &owner)]).expect("migrate_depositor_record");
+
+    let records: Vec<DepositorRecord> = depositors
+        .iter()
+        .map(|depositor| assert_current(&runtime, &composer::depositor_record_address(&pool, depositor).0))
+        .collect();
+    assert_eq!((records[0].index, records[1].index), (2, 3));
+    let state: Pool = runtime.load(&pool);
+    assert_eq!((state.depositor_count, state.unindexed_records), (4, 0));
+
+    let leaves: Vec<[u8; 32]> =
+        records.iter().map(|record| merkle_leaf(record.index, &record.depositor, 500)).collect();
+    runtime.process(&[finalize(merkle_parent(leaves[0], leaves[1]))]).expect("finalize_pool");
+
+    for (depositor, sibling) in depositors.iter().zip([leaves[1], leaves[0]]) {
+        let tokens = runtime.create_token_account(&mint, depositor, 0);
+        let claim = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ClaimTokens {
+                pool,
+                claim_bitmap: composer::claim_bitmap_address(&pool).0,
+                pool_token_vault: composer::token_vault_address(&pool).0,
+                depositor_record: composer::depositor_record_address(&pool, depositor).0,
+                depositor_token_account: tokens,
+                depositor: *depositor,
+                token_program: anchor_spl::token::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::ClaimTokens { amount: 500, proof: vec![sibling] }.data(),
+        };
+        runtime.process(&[claim]).expect("claim_tokens");
+        assert_eq!(runtime.token_balance(&tokens), 500);
+    }
+}
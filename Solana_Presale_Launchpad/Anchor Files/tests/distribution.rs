@@ -0,0 +1,142 @@
+//! Pushing tokens out with `distribute_batch`, alone and alongside claims.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{composer, merkle_leaf, BatchDistributed, ClaimBitmap, DepositorRecord};
+use runtime::{deposit_instruction, Runtime, LAMPORTS_PER_SOL};
+
+/// Tokens for two 1 SOL deposits, i.e. 500 each.
+const TOTAL_TOKENS: u64 = 1_000;
+
+/// A finalized pool two depositors put 1 SOL each into, with a token account
+/// each and a merkle root paying each of them 500.
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    owner: Pubkey,
+    depositors: [Pubkey; 2],
+    token_accounts: [Pubkey; 2],
+    leaves: [[u8; 32]; 2],
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        runtime.add_token_program();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+        for depositor in &depositors {
+            runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+            runtime.process(&[deposit_instruction(&pool, depositor, depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+        }
+
+        let leaves = [0, 1].map(|index| merkle_leaf(index, &depositors[index as usize], TOTAL_TOKENS / 2));
+        let (left, right) = if leaves[0] <= leaves[1] { (leaves[0], leaves[1]) } else { (leaves[1], leaves[0]) };
+        let merkle_root = anchor_lang::solana_program::hash::hashv(&[&[1u8], &left, &right]).to_bytes();
+
+        runtime.advance(3601);
+        let mint = runtime.create_mint(&owner);
+        let owner_tokens = runtime.create_token_account(&mint, &owner, TOTAL_TOKENS);
+        let finalize = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::FinalizePool {
+                pool,
+                claim_bitmap: composer::claim_bitmap_address(&pool).0,
+                token_mint: mint,
+                pool_token_vault: composer::token_vault_address(&pool).0,
+                owner_token_account: owner_tokens,
+                owner,
+                token_program: anchor_spl::token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::FinalizePool { merkle_root, total_tokens: TOTAL_TOKENS }.data(),
+        };
+        runtime.process(&[finalize]).expect("finalize_pool");
+
+        let token_accounts = depositors.map(|depositor| runtime.create_token_account(&mint, &depositor, 0));
+        Fixture { runtime, pool, owner, depositors, token_accounts, leaves }
+    }
+
+    /// `distribute_batch` over the depositors at `indexes`.
+    fn distribute(&mut self, indexes: &[usize]) -> BatchDistributed {
+        let mut accounts = presale_program::accounts::DistributeBatch {
+            pool: self.pool,
+            claim_bitmap: composer::claim_bitmap_address(&self.pool).0,
+            pool_token_vault: composer::token_vault_address(&self.pool).0,
+            lottery_draw: None,
+            owner: self.owner,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None);
+        for &index in indexes {
+            let record = composer::depositor_record_address(&self.pool, &self.depositors[index]).0;
+            accounts.push(AccountMeta::new(record, false));
+            accounts.push(AccountMeta::new(self.token_accounts[index], false));
+        }
+        let distribute = Instruction {
+            program_id: presale_program::ID,
+            accounts,
+            data: presale_program::instruction::DistributeBatch {}.data(),
+        };
+        self.runtime.take_events();
+        self.runtime.advance(1);
+        self.runtime.process(&[distribute]).expect("distribute_batch");
+        let events = self.runtime.take_events();
+        let event = events.iter().find(|event| event.starts_with(BatchDistributed::DISCRIMINATOR)).expect("event");
+        BatchDistributed::try_from_slice(&event[8..]).unwrap()
+    }
+
+    fn balances(&self) -> [u64; 2] {
+        self.token_accounts.map(|account| self.runtime.token_balance(&account))
+    }
+}
+
+#[test]
+fn resent_batches_transfer_nothing() {
+    let mut fixture = Fixture::new();
+
+    let first = fixture.distribute(&[0, 1]);
+    assert_eq!((first.distributed, first.skipped), (2, 0));
+    assert_eq!(fixture.balances(), [500, 500]);
+
+    // A crank resending the same batch after a timeout pays nobody twice
+    let vault = composer::token_vault_address(&fixture.pool).0;
+    let second = fixture.distribute(&[0, 1]);
+    assert_eq!((second.distributed, second.skipped), (0, 2));
+    assert_eq!(fixture.balances(), [500, 500]);
+    assert_eq!(fixture.runtime.token_balance(&vault), 0);
+}
+
+#[test]
+fn batches_skip_records_already_claimed() {
+    let mut fixture = Fixture::new();
+    let claim = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::ClaimTokens {
+            pool: fixture.pool,
+            claim_bitmap: composer::claim_bitmap_address(&fixture.pool).0,
+            pool_token_vault: composer::token_vault_address(&fixture.pool).0,
+            depositor_record: composer::depositor_record_address(&fixture.pool, &fixture.depositors[0]).0,
+            depositor_token_account: fixture.token_accounts[0],
+            depositor: fixture.depositors[0],
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::ClaimTokens { amount: TOTAL_TOKENS / 2, proof: vec![fixture.leaves[1]] }
+            .data(),
+    };
+    fixture.runtime.process(&[claim]).expect("claim_tokens");
+
+    let batch = fixture.distribute(&[0, 1]);
+    assert_eq!((batch.distributed, batch.skipped), (1, 1));
+    assert_eq!(fixture.balances(), [500, 500]);
+
+    let bitmap: ClaimBitmap = fixture.runtime.load(&composer::claim_bitmap_address(&fixture.pool).0);
+    assert!(bitmap.is_claimed(0) && bitmap.is_claimed(1));
+    let record: DepositorRecord =
+        fixture.runtime.load(&composer::depositor_record_address(&fixture.pool, &fixture.depositors[1]).0);
+    assert_eq!(record.tokens_claimed, 500);
+}
@@ -0,0 +1,99 @@
+//! Expiry bounds of `create_pool` and the deadlines derived from the expiry.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    system_program, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, Pool, PresaleError, TimeBasis, MAX_POOL_DURATION};
+use runtime::{program_error, Runtime, LAMPORTS_PER_SOL};
+
+fn create_pool(runtime: &mut Runtime, owner: &Pubkey, expiry_timestamp: i64, time_basis: TimeBasis) -> ProgramResult {
+    let pool = composer::pool_address(owner).0;
+    let create = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::CreatePool {
+            pool,
+            pool_vault: composer::vault_address(&pool).0,
+            owner: *owner,
+            system_program: system_program::ID,
+            stats: composer::stats_address().0,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::CreatePool {
+            expiry_timestamp,
+            min_deposit: LAMPORTS_PER_SOL / 10,
+            max_deposit: 5 * LAMPORTS_PER_SOL,
+            soft_cap: 0,
+            hard_cap: 0,
+            time_basis,
+        }
+        .data(),
+    };
+    runtime.process(&[create])
+}
+
+fn owner(runtime: &mut Runtime) -> Pubkey {
+    let owner = Pubkey::new_unique();
+    runtime.airdrop(&owner, 10 * LAMPORTS_PER_SOL);
+    owner
+}
+
+#[test]
+fn expiries_are_at_most_a_year_away() {
+    let mut runtime = Runtime::new();
+    let now = runtime.clock().unix_timestamp;
+    let too_far = Err(program_error(PresaleError::InvalidExpiryTime));
+
+    for expiry in [now, now + MAX_POOL_DURATION + 1, i64::MAX] {
+        let owner = owner(&mut runtime);
+        assert_eq!(create_pool(&mut runtime, &owner, expiry, TimeBasis::UnixTimestamp), too_far);
+    }
+    let owner = owner(&mut runtime);
+    create_pool(&mut runtime, &owner, now + MAX_POOL_DURATION, TimeBasis::UnixTimestamp).expect("a year");
+}
+
+#[test]
+fn slot_pools_count_the_year_in_slots() {
+    let mut runtime = Runtime::new();
+    let slot = runtime.clock().slot as i64;
+    let year = MAX_POOL_DURATION * 1000 / presale_program::ESTIMATED_SLOT_MS;
+
+    let owner = owner(&mut runtime);
+    assert_eq!(
+        create_pool(&mut runtime, &owner, slot + year + 1, TimeBasis::Slot),
+        Err(program_error(PresaleError::InvalidExpiryTime))
+    );
+    create_pool(&mut runtime, &owner, slot + year, TimeBasis::Slot).expect("a year of slots");
+
+    let pool: Pool = runtime.load(&composer::pool_address(&owner).0);
+    let clock = runtime.clock();
+    let (timestamp, expiry_slot) = pool.expiry_in_both_bases(&clock);
+    assert_eq!(expiry_slot, (slot + year) as u64);
+    assert_eq!(timestamp, clock.unix_timestamp + MAX_POOL_DURATION);
+}
+
+#[test]
+fn deadlines_of_unbounded_expiries_saturate() {
+    // Pools created before the bound may expire at any time
+    let mut runtime = Runtime::new();
+    let owner = owner(&mut runtime);
+    let slot = runtime.clock().slot as i64;
+    create_pool(&mut runtime, &owner, slot + 9000, TimeBasis::Slot).expect("create_pool");
+    let mut pool: Pool = runtime.load(&composer::pool_address(&owner).0);
+    pool.expiry_timestamp = i64::MAX;
+
+    let clock = runtime.clock();
+    let (timestamp, slot) = pool.expiry_in_both_bases(&clock);
+    assert_eq!(slot, i64::MAX as u64);
+    assert!(timestamp > clock.unix_timestamp);
+    assert_eq!(pool.lottery_reveal_deadline(), i64::MAX);
+
+    pool.time_basis = TimeBasis::UnixTimestamp;
+    let (timestamp, slot) = pool.expiry_in_both_bases(&clock);
+    assert_eq!(timestamp, i64::MAX);
+    assert!(slot > clock.slot);
+    assert_eq!(pool.lottery_reveal_deadline(), i64::MAX);
+}
@@ -0,0 +1,172 @@
+//! NFT collection gates on deposits and position transfers, read from the
+//! Token Metadata account of the holder's NFT.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{composer, GateKind, PresaleError, TOKEN_METADATA_PROGRAM_ID};
+use runtime::{deposit_instruction, program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+/// A `MetadataV1` account naming `collection`, `verified` or not, cut to
+/// `len` bytes when given.
+fn metadata(mint: &Pubkey, collection: &Pubkey, verified: bool, len: Option<usize>) -> Vec<u8> {
+    let mut data = vec![4];
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(mint.as_ref());
+    for field in ["Pass #1", "PASS", "https://example.com/1.json"] {
+        data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    // Seller fee bps, no creators, primary sale happened, mutable, no edition nonce or token standard
+    data.extend_from_slice(&500u16.to_le_bytes());
+    data.extend_from_slice(&[0, 1, 1, 0, 0]);
+    data.extend_from_slice(&[1, verified as u8]);
+    data.extend_from_slice(collection.as_ref());
+    data.truncate(len.unwrap_or(data.len()));
+    data
+}
+
+/// A wallet holding one NFT, with its token account and the metadata
+/// account passed for it.
+#[derive(Clone, Copy)]
+struct Holder {
+    wallet: Pubkey,
+    token_account: Pubkey,
+    metadata: Pubkey,
+}
+
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    collection: Pubkey,
+}
+
+impl Fixture {
+    /// A pool gated on NFTs of a fresh collection.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let collection = Pubkey::new_unique();
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigurePool { pool, owner }.to_account_metas(None),
+            data: presale_program::instruction::ConfigureGate {
+                kind: GateKind::NftCollection,
+                mint: collection,
+                min_amount: 0,
+            }
+            .data(),
+        };
+        runtime.process(&[configure]).expect("configure_gate");
+        Fixture { runtime, pool, collection }
+    }
+
+    /// A funded wallet holding one NFT whose metadata is `metadata(mint)`.
+    fn holder(&mut self, metadata: impl FnOnce(&Pubkey) -> Vec<u8>) -> Holder {
+        let wallet = Pubkey::new_unique();
+        self.runtime.airdrop(&wallet, 10 * LAMPORTS_PER_SOL);
+        let mint = self.runtime.create_mint(&Pubkey::new_unique());
+        let token_account = self.runtime.create_token_account(&mint, &wallet, 1);
+        let (address, _) = Pubkey::find_program_address(
+            &[b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+            &TOKEN_METADATA_PROGRAM_ID,
+        );
+        let owner = TOKEN_METADATA_PROGRAM_ID;
+        let account = AccountState { lamports: LAMPORTS_PER_SOL, data: metadata(&mint), owner, executable: false };
+        self.runtime.set_account(address, account);
+        Holder { wallet, token_account, metadata: address }
+    }
+
+    fn deposit(&mut self, holder: Holder) -> std::result::Result<(), ProgramError> {
+        let mut deposit = deposit_instruction(&self.pool, &holder.wallet, &holder.wallet, LAMPORTS_PER_SOL);
+        // gate_token_account and gate_metadata come just before the price oracle
+        let len = deposit.accounts.len();
+        deposit.accounts[len - 3] = AccountMeta::new_readonly(holder.token_account, false);
+        deposit.accounts[len - 2] = AccountMeta::new_readonly(holder.metadata, false);
+        self.runtime.process(&[deposit])
+    }
+
+    /// `owner` hands half a SOL of their position to `recipient`.
+    fn transfer(&mut self, owner: &Pubkey, recipient: Holder) -> std::result::Result<(), ProgramError> {
+        let transfer = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::TransferPosition {
+                pool: self.pool,
+                from_record: composer::depositor_record_address(&self.pool, owner).0,
+                to_record: composer::depositor_record_address(&self.pool, &recipient.wallet).0,
+                recipient: recipient.wallet,
+                owner: *owner,
+                system_program: anchor_lang::system_program::ID,
+                instructions: anchor_lang::solana_program::sysvar::instructions::ID,
+                stats: composer::stats_address().0,
+                participant: composer::participant_address(&recipient.wallet).0,
+                gate_token_account: Some(recipient.token_account),
+                gate_metadata: Some(recipient.metadata),
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::TransferPosition {
+                amount: LAMPORTS_PER_SOL / 2,
+                whitelist_proof: Vec::new(),
+            }
+            .data(),
+        };
+        self.runtime.advance(1);
+        self.runtime.process(&[transfer])
+    }
+}
+
+#[test]
+fn deposits_need_an_nft_of_the_verified_collection() {
+    let mut fixture = Fixture::new();
+    let collection = fixture.collection;
+
+    let verified = fixture.holder(|mint| metadata(mint, &collection, true, None));
+    fixture.deposit(verified).expect("deposit");
+
+    let unverified = fixture.holder(|mint| metadata(mint, &collection, false, None));
+    assert_eq!(fixture.deposit(unverified), Err(program_error(PresaleError::GateNotMet)));
+    let other_collection = fixture.holder(|mint| metadata(mint, &Pubkey::new_unique(), true, None));
+    assert_eq!(fixture.deposit(other_collection), Err(program_error(PresaleError::GateNotMet)));
+    // Cut inside the collection key
+    let truncated = fixture.holder(|mint| {
+        let len = metadata(mint, &collection, true, None).len() - 1;
+        metadata(mint, &collection, true, Some(len))
+    });
+    assert_eq!(fixture.deposit(truncated), Err(program_error(PresaleError::GateNotMet)));
+}
+
+#[test]
+fn metadata_must_be_the_mints_token_metadata_account() {
+    let mut fixture = Fixture::new();
+    let collection = fixture.collection;
+
+    let holder = fixture.holder(|mint| metadata(mint, &collection, true, None));
+    // Verified metadata of another mint
+    let other = fixture.holder(|mint| metadata(mint, &collection, true, None));
+    let borrowed = Holder { metadata: other.metadata, ..holder };
+    assert_eq!(fixture.deposit(borrowed), Err(program_error(PresaleError::InvalidGateMetadata)));
+
+    let mut account = fixture.runtime.account(&holder.metadata).expect("metadata").clone();
+    account.owner = Pubkey::new_unique();
+    fixture.runtime.set_account(holder.metadata, account);
+    assert_eq!(fixture.deposit(holder), Err(program_error(PresaleError::InvalidGateMetadata)));
+}
+
+#[test]
+fn transfers_need_the_recipient_to_meet_the_gate() {
+    let mut fixture = Fixture::new();
+    let collection = fixture.collection;
+    let owner = fixture.holder(|mint| metadata(mint, &collection, true, None));
+    fixture.deposit(owner).expect("deposit");
+
+    let unverified = fixture.holder(|mint| metadata(mint, &collection, false, None));
+    assert_eq!(fixture.transfer(&owner.wallet, unverified), Err(program_error(PresaleError::GateNotMet)));
+    let other_collection = fixture.holder(|mint| metadata(mint, &Pubkey::new_unique(), true, None));
+    assert_eq!(fixture.transfer(&owner.wallet, other_collection), Err(program_error(PresaleError::GateNotMet)));
+    let truncated = fixture.holder(|mint| metadata(mint, &collection, true, Some(100)));
+    assert_eq!(fixture.transfer(&owner.wallet, truncated), Err(program_error(PresaleError::GateNotMet)));
+
+    let verified = fixture.holder(|mint| metadata(mint, &collection, true, None));
+    fixture.transfer(&owner.wallet, verified).expect("transfer_position");
+}
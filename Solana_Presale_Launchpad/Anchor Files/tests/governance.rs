@@ -0,0 +1,166 @@
+//! Escrow refunds of governed pools once the owner's proposal window closes.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    system_program, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, LaunchpadStats, Pool, PresaleError, GOVERNANCE_WINDOW};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+const VOTING_PERIOD: i64 = 3600;
+
+struct Fixture {
+    runtime: Runtime,
+    owner: Pubkey,
+    pool: Pubkey,
+    depositors: [Pubkey; 2],
+}
+
+impl Fixture {
+    /// An expired governed pool with deposits of 1 and 3 SOL.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigurePool { pool, owner }.to_account_metas(None),
+            data: presale_program::instruction::ConfigureGovernance {
+                quorum_bps: 2_000,
+                approval_bps: 5_000,
+                voting_period: VOTING_PERIOD,
+            }
+            .data(),
+        };
+        runtime.process(&[configure]).expect("configure_governance");
+
+        let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+        for (depositor, sol) in depositors.iter().zip([1, 3]) {
+            runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+            let deposit = deposit_instruction(&pool, depositor, depositor, sol * LAMPORTS_PER_SOL);
+            runtime.process(&[deposit]).expect("deposit");
+        }
+        runtime.advance(3600);
+        Fixture { runtime, owner, pool, depositors }
+    }
+
+    fn proposal_address(&self, id: u32) -> Pubkey {
+        let seeds: &[&[u8]] = &[b"proposal", self.pool.as_ref(), &id.to_le_bytes()];
+        Pubkey::find_program_address(seeds, &presale_program::ID).0
+    }
+
+    fn propose(&mut self, amount: u64) -> ProgramResult {
+        let proposal = self.proposal_address(self.runtime.load::<Pool>(&self.pool).proposal_count);
+        let open = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::OpenReleaseProposal {
+                pool: self.pool,
+                proposal,
+                owner: self.owner,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::OpenReleaseProposal { amount }.data(),
+        };
+        self.runtime.process(&[open])
+    }
+
+    fn vote(&mut self, id: u32, voter: &Pubkey) {
+        let proposal = self.proposal_address(id);
+        let vote = Pubkey::find_program_address(&[b"vote", proposal.as_ref(), voter.as_ref()], &presale_program::ID).0;
+        let cast = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::CastVote {
+                pool: self.pool,
+                proposal,
+                depositor_record: composer::depositor_record_address(&self.pool, voter).0,
+                vote,
+                voter: *voter,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::CastVote { approve: true }.data(),
+        };
+        self.runtime.process(&[cast]).expect("cast_vote");
+    }
+
+    fn execute(&mut self, id: u32) {
+        let execute = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ExecuteProposal {
+                pool: self.pool,
+                proposal: self.proposal_address(id),
+                pool_vault: composer::vault_address(&self.pool).0,
+                owner: self.owner,
+                stats: composer::stats_address().0,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::ExecuteProposal {}.data(),
+        };
+        self.runtime.process(&[execute]).expect("execute_proposal");
+    }
+
+    /// The depositor's `refund_escrow` and the lamports it paid out.
+    fn refund(&mut self, depositor: &Pubkey) -> std::result::Result<u64, ProgramError> {
+        let refund = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::RefundEscrow {
+                pool: self.pool,
+                pool_vault: composer::vault_address(&self.pool).0,
+                depositor_record: composer::depositor_record_address(&self.pool, depositor).0,
+                depositor: *depositor,
+                stats: composer::stats_address().0,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::RefundEscrow {}.data(),
+        };
+        let before = self.runtime.lamports(depositor);
+        self.runtime.process(&[refund])?;
+        Ok(self.runtime.lamports(depositor) - before)
+    }
+
+    fn close_window(&mut self) {
+        self.runtime.advance(GOVERNANCE_WINDOW);
+    }
+
+    fn active_pools(&self) -> u64 {
+        self.runtime.load::<LaunchpadStats>(&composer::stats_address().0).active_pools
+    }
+}
+
+#[test]
+fn unproposed_escrow_is_refunded_after_the_window() {
+    let mut fixture = Fixture::new();
+    let [first, second] = fixture.depositors;
+    assert_eq!(fixture.refund(&first), Err(program_error(PresaleError::RefundNotAvailable)));
+
+    fixture.close_window();
+    assert_eq!(fixture.propose(LAMPORTS_PER_SOL), Err(program_error(PresaleError::GovernanceWindowClosed)));
+    assert_eq!(fixture.refund(&first), Ok(LAMPORTS_PER_SOL));
+    assert_eq!(fixture.refund(&second), Ok(3 * LAMPORTS_PER_SOL));
+    assert_eq!(fixture.refund(&second), Err(program_error(PresaleError::AlreadyRefunded)));
+
+    assert!(fixture.runtime.load::<Pool>(&fixture.pool).release_rejected);
+    assert_eq!(fixture.active_pools(), 0);
+}
+
+#[test]
+fn releases_approved_in_the_window_are_kept() {
+    let mut fixture = Fixture::new();
+    let [first, second] = fixture.depositors;
+    fixture.propose(2 * LAMPORTS_PER_SOL).expect("open_release_proposal");
+    fixture.vote(0, &second);
+
+    // A vote running past the window holds refunds back until it is settled
+    fixture.close_window();
+    assert_eq!(fixture.refund(&first), Err(program_error(PresaleError::RefundNotAvailable)));
+    fixture.execute(0);
+
+    // Half the escrow was released, so each depositor gets half their deposit back
+    assert_eq!(fixture.refund(&first), Ok(LAMPORTS_PER_SOL / 2));
+    assert_eq!(fixture.refund(&second), Ok(3 * LAMPORTS_PER_SOL / 2));
+    assert_eq!(fixture.active_pools(), 0);
+}
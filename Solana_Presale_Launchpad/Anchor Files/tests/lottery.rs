@@ -0,0 +1,197 @@
+//! The lottery seed: the owner's revealed secret completed by a slot hash
+//! nobody knows at reveal time.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash::hashv, instruction::Instruction, sysvar},
+    system_program, InstructionData, ToAccountMetas,
+};
+use presale_program::{
+    composer, find_slot_hash, LotteryDraw, LotterySeedRescheduled, Pool, PresaleError, SlotHashLookup, TimeBasis,
+    LOTTERY_SEED_DELAY,
+};
+use runtime::{deposit_instruction, program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+const TICKETS: usize = 20;
+const WINNERS: u32 = 10;
+const SECRET: [u8; 32] = [42; 32];
+
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    draw: Pubkey,
+}
+
+impl Fixture {
+    /// A lottery of `TICKETS` one-SOL tickets whose owner has just revealed the secret.
+    fn revealed() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        runtime.airdrop(&owner, 10 * LAMPORTS_PER_SOL);
+        let pool = composer::pool_address(&owner).0;
+        let create = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::CreatePool {
+                pool,
+                pool_vault: composer::vault_address(&pool).0,
+                owner,
+                system_program: system_program::ID,
+                stats: composer::stats_address().0,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::CreatePool {
+                expiry_timestamp: runtime.clock().unix_timestamp + 3600,
+                min_deposit: LAMPORTS_PER_SOL,
+                max_deposit: LAMPORTS_PER_SOL,
+                soft_cap: 0,
+                hard_cap: 0,
+                time_basis: TimeBasis::UnixTimestamp,
+            }
+            .data(),
+        };
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigurePool { pool, owner }.to_account_metas(None),
+            data: presale_program::instruction::ConfigureLottery {
+                winner_count: WINNERS,
+                commitment: hashv(&[&SECRET]).to_bytes(),
+            }
+            .data(),
+        };
+        runtime.process(&[create, configure]).expect("create_pool and configure_lottery");
+
+        for _ in 0..TICKETS {
+            let depositor = Pubkey::new_unique();
+            runtime.airdrop(&depositor, 2 * LAMPORTS_PER_SOL);
+            runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+        }
+        runtime.advance(3600);
+
+        let draw = Pubkey::find_program_address(&[b"lottery", pool.as_ref()], &presale_program::ID).0;
+        let reveal = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::RevealLottery {
+                pool,
+                lottery_draw: draw,
+                owner,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::RevealLottery { secret: SECRET }.data(),
+        };
+        runtime.process(&[reveal]).expect("reveal_lottery");
+        Fixture { runtime, pool, draw }
+    }
+
+    fn pool(&self) -> Pool {
+        self.runtime.load(&self.pool)
+    }
+
+    /// Publishes SlotHashes with `(slot, hash)` entries, newest first.
+    fn set_slot_hashes(&mut self, entries: &[(u64, [u8; 32])]) {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, hash) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(hash);
+        }
+        let state = AccountState { lamports: 1, data, owner: sysvar::ID, executable: false };
+        self.runtime.set_account(sysvar::slot_hashes::ID, state);
+    }
+
+    fn draw(&mut self) -> std::result::Result<(), ProgramError> {
+        let draw = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::DrawWinners {
+                pool: self.pool,
+                lottery_draw: self.draw,
+                slot_hashes: sysvar::slot_hashes::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::DrawWinners { max_draws: 1_000 }.data(),
+        };
+        self.runtime.process(&[draw])
+    }
+
+    /// Winning ticket indexes once the seed slot has the given hash.
+    fn winners_with(&mut self, slot_hash: [u8; 32]) -> Vec<u32> {
+        let seed_slot = self.pool().lottery_seed_slot;
+        self.set_slot_hashes(&[(seed_slot + 1, [9; 32]), (seed_slot, slot_hash), (seed_slot - 1, [8; 32])]);
+        self.draw().expect("draw_winners");
+        assert!(self.pool().lottery_drawn);
+        let draw: LotteryDraw = self.runtime.load(&self.draw);
+        (0..TICKETS as u32).filter(|&index| draw.is_winner(index)).collect()
+    }
+}
+
+#[test]
+fn draws_wait_for_the_seed_slot() {
+    let mut fixture = Fixture::revealed();
+    let pool = fixture.pool();
+    assert_eq!(pool.lottery_seed_slot, fixture.runtime.clock().slot + LOTTERY_SEED_DELAY);
+    assert!(!pool.lottery_seeded());
+
+    let seed_slot = pool.lottery_seed_slot;
+    fixture.set_slot_hashes(&[(seed_slot - 1, [1; 32]), (seed_slot - 2, [2; 32])]);
+    assert_eq!(fixture.draw(), Err(program_error(PresaleError::LotterySeedPending)));
+}
+
+#[test]
+fn the_owner_cannot_predict_the_draw() {
+    let mut fixture = Fixture::revealed();
+    // Everything the owner knows once the secret is public
+    let (pool_at_reveal, draw_at_reveal) = (
+        fixture.runtime.account(&fixture.pool).unwrap().clone(),
+        fixture.runtime.account(&fixture.draw).unwrap().clone(),
+    );
+    let revealed_seed = fixture.pool().lottery_seed;
+
+    let first = fixture.winners_with([1; 32]);
+    assert_eq!(fixture.pool().lottery_seed, hashv(&[&revealed_seed, &[1; 32]]).to_bytes());
+
+    fixture.runtime.set_account(fixture.pool, pool_at_reveal);
+    fixture.runtime.set_account(fixture.draw, draw_at_reveal);
+    let second = fixture.winners_with([2; 32]);
+
+    assert_eq!(first.len(), WINNERS as usize);
+    assert_eq!(second.len(), WINNERS as usize);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn dropped_seed_slots_are_replaced() {
+    let mut fixture = Fixture::revealed();
+    let seed_slot = fixture.pool().lottery_seed_slot;
+
+    // The oldest recorded slot is past the seed slot, so its hash may be gone
+    fixture.runtime.advance(600);
+    fixture.set_slot_hashes(&[(seed_slot + 600, [1; 32]), (seed_slot + 100, [2; 32])]);
+    fixture.runtime.take_events();
+    fixture.draw().expect("draw_winners");
+    let pool = fixture.pool();
+    assert!(!pool.lottery_seeded());
+    assert_eq!(pool.lottery_seed_slot, fixture.runtime.clock().slot + LOTTERY_SEED_DELAY);
+
+    let events = fixture.runtime.take_events();
+    let event = events.iter().find(|event| event.starts_with(LotterySeedRescheduled::DISCRIMINATOR)).unwrap();
+    let event = LotterySeedRescheduled::try_from_slice(&event[8..]).unwrap();
+    assert_eq!((event.expired_slot, event.seed_slot), (seed_slot, pool.lottery_seed_slot));
+}
+
+#[test]
+fn skipped_seed_slots_use_the_next_slot() {
+    let entries = [(13u64, [3u8; 32]), (11, [1; 32]), (9, [9; 32])];
+    let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+    for (slot, hash) in entries {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&hash);
+    }
+    // The sysvar account keeps room for 512 entries past the count
+    data.resize(8 + 512 * 40, 0);
+
+    assert!(matches!(find_slot_hash(&data, 10), SlotHashLookup::Found(11, [1, ..])));
+    assert!(matches!(find_slot_hash(&data, 9), SlotHashLookup::Found(9, [9, ..])));
+    assert!(matches!(find_slot_hash(&data, 14), SlotHashLookup::Pending));
+    assert!(matches!(find_slot_hash(&data, 8), SlotHashLookup::Expired));
+}
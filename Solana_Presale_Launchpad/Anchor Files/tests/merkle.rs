@@ -0,0 +1,72 @@
+//! Proofs built by `presale-client`'s `MerkleTree` against the program's
+//! leaf hashing and proof verification.
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use presale_program::{merkle_leaf, verify_merkle_proof, whitelist_leaf};
+
+/// Shared with `presale-client/src/merkle.rs`, which builds the same proofs.
+const VECTORS: &str = include_str!("merkle_vectors.txt");
+
+fn node(hex: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    for (byte, chunk) in node.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16).unwrap();
+    }
+    node
+}
+
+fn proof(field: &str) -> Vec<[u8; 32]> {
+    match field {
+        "-" => Vec::new(),
+        nodes => nodes.split(',').map(node).collect(),
+    }
+}
+
+struct Vector {
+    leaf: [u8; 32],
+    root: [u8; 32],
+    proof: Vec<[u8; 32]>,
+}
+
+fn vectors() -> Vec<Vector> {
+    let vectors: Vec<_> = VECTORS
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            match fields[0] {
+                "allocation" => {
+                    let depositor = Pubkey::from_str(fields[3]).unwrap();
+                    let leaf = merkle_leaf(fields[2].parse().unwrap(), &depositor, fields[4].parse().unwrap());
+                    Vector { leaf, root: node(fields[5]), proof: proof(fields[6]) }
+                }
+                "whitelist" => {
+                    let leaf = whitelist_leaf(&Pubkey::from_str(fields[2]).unwrap());
+                    Vector { leaf, root: node(fields[3]), proof: proof(fields[4]) }
+                }
+                other => panic!("unknown vector kind {other}"),
+            }
+        })
+        .collect();
+    assert!(!vectors.is_empty());
+    vectors
+}
+
+#[test]
+fn client_proofs_verify() {
+    for Vector { leaf, root, proof } in vectors() {
+        assert!(verify_merkle_proof(&proof, root, leaf), "{proof:?} under {root:?}");
+    }
+}
+
+#[test]
+fn proofs_only_verify_their_own_leaf() {
+    let vectors = vectors();
+    for vector in &vectors {
+        for other in vectors.iter().filter(|other| other.root == vector.root && other.leaf != vector.leaf) {
+            assert!(!verify_merkle_proof(&vector.proof, vector.root, other.leaf));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Pool metadata: the name, symbol, URI and links an owner publishes for
+//! their pool.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{PoolMetadata, PresaleError};
+use runtime::{program_error, Runtime};
+
+fn metadata_address(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"metadata", pool.as_ref()], &presale_program::ID).0
+}
+
+fn set_metadata(pool: &Pubkey, owner: &Pubkey, name: &str, links: &[&str]) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::SetPoolMetadata {
+            pool: *pool,
+            metadata: metadata_address(pool),
+            owner: *owner,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::SetPoolMetadata {
+            name: name.to_string(),
+            symbol: "PRE".to_string(),
+            uri: "https://example.com/pool.json".to_string(),
+            links: links.iter().map(|link| link.to_string()).collect(),
+        }
+        .data(),
+    }
+}
+
+#[test]
+fn owners_set_and_replace_metadata() {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+
+    runtime.process(&[set_metadata(&pool, &owner, "Presale", &["https://example.com"])]).expect("set_pool_metadata");
+    let metadata: PoolMetadata = runtime.load(&metadata_address(&pool));
+    assert_eq!((metadata.pool, metadata.name.as_str()), (pool, "Presale"));
+    assert_eq!(metadata.links, ["https://example.com"]);
+
+    // The account is reused, and no link is a valid list of links
+    runtime.process(&[set_metadata(&pool, &owner, "Renamed", &[])]).expect("set_pool_metadata");
+    let metadata: PoolMetadata = runtime.load(&metadata_address(&pool));
+    assert_eq!(metadata.name, "Renamed");
+    assert!(metadata.links.is_empty());
+}
+
+#[test]
+fn metadata_must_come_from_the_owner_and_fit() {
+    let mut runtime = Runtime::new();
+    let owner = Pubkey::new_unique();
+    let pool = runtime.create_pool(&owner, 0);
+    let stranger = Pubkey::new_unique();
+    runtime.airdrop(&stranger, 1_000_000_000);
+
+    assert_eq!(
+        runtime.process(&[set_metadata(&pool, &stranger, "Presale", &[])]),
+        Err(program_error(PresaleError::Unauthorized))
+    );
+
+    let invalid = Err(program_error(PresaleError::InvalidMetadata));
+    assert_eq!(runtime.process(&[set_metadata(&pool, &owner, "", &[])]), invalid);
+    let long_name = "n".repeat(PoolMetadata::MAX_NAME_LEN + 1);
+    assert_eq!(runtime.process(&[set_metadata(&pool, &owner, &long_name, &[])]), invalid);
+    let long_link = "l".repeat(PoolMetadata::MAX_LINK_LEN + 1);
+    assert_eq!(runtime.process(&[set_metadata(&pool, &owner, "Presale", &[&long_link])]), invalid);
+    let links = ["https://example.com"; PoolMetadata::MAX_LINKS + 1];
+    assert_eq!(runtime.process(&[set_metadata(&pool, &owner, "Presale", &links)]), invalid);
+    assert!(runtime.account(&metadata_address(&pool)).is_none());
+
+    // Every field at its limit still fits the account
+    let name = "n".repeat(PoolMetadata::MAX_NAME_LEN);
+    let link = "l".repeat(PoolMetadata::MAX_LINK_LEN);
+    let links = [link.as_str(); PoolMetadata::MAX_LINKS];
+    runtime.process(&[set_metadata(&pool, &owner, &name, &links)]).expect("set_pool_metadata");
+}
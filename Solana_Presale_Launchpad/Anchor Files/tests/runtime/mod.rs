@@ -0,0 +1,525 @@
+//! A native stand-in for the Solana runtime, enough to run the program's
+//! instructions in `cargo test` without an SBF toolchain or a validator.
+//!
+//! Accounts are serialized in the loader's input layout, so the program sees
+//! the same `AccountInfo`s (and can resize them) as on-chain. Cross-program
+//! invocations go through the syscall stubs: calls into the system program are
+//! emulated, calls into registered native programs run them in place, and PDA
+//! signatures are checked against the calling program's id. A transaction
+//! either commits every account it touched or none.
+
+#![allow(dead_code)]
+
+use std::{cell::RefCell, collections::HashMap, mem::size_of, sync::Once};
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        entrypoint::{
+            deserialize, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER, SUCCESS,
+        },
+        instruction::Instruction,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        sysvar::instructions as instructions_sysvar,
+    },
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::token::spl_token::{self, solana_program::program_pack::Pack};
+use presale_program::{composer, TimeBasis};
+use solana_instruction::{BorrowedAccountMeta, BorrowedInstruction};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+/// Unix time of the runtime's first slot.
+pub const GENESIS_TIMESTAMP: i64 = 1_700_000_000;
+
+/// A program's entrypoint, as Anchor's `entry` and native test programs have it.
+pub type Processor = for<'a> fn(&Pubkey, &'a [AccountInfo<'a>], &[u8]) -> ProgramResult;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+/// Per-thread state the syscall stubs read, so tests can run in parallel.
+#[derive(Default)]
+struct Invocation {
+    programs: HashMap<Pubkey, Processor>,
+    /// Programs of the current instruction, outermost first
+    stack: Vec<Pubkey>,
+    clock: Clock,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    /// Event payloads logged with `sol_log_data`, i.e. by `emit!`
+    events: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    static INVOCATION: RefCell<Invocation> = RefCell::new(Invocation::default());
+}
+
+fn with_invocation<R>(f: impl FnOnce(&mut Invocation) -> R) -> R {
+    INVOCATION.with(|invocation| f(&mut invocation.borrow_mut()))
+}
+
+struct NativeStubs;
+
+impl SyscallStubs for NativeStubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        with_invocation(|invocation| invocation.events.push(fields.concat()));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = with_invocation(|invocation| *invocation.stack.last().expect("CPI outside of an instruction"));
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+
+        // The callee gets the privileges the instruction asks for, as long as
+        // the caller holds them or signs for the PDA
+        let mut infos = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            if meta.is_signer && !info.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            infos.push(info);
+        }
+
+        // Entrypoints take `&'a [AccountInfo<'a>]`; the clones share the
+        // caller's lamports and data, and leaking the slice keeps it alive
+        let infos: &[AccountInfo] = Box::leak(infos.into_boxed_slice());
+        dispatch(&instruction.program_id, infos, &instruction.data)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = with_invocation(|invocation| invocation.clock.clone());
+        unsafe { std::ptr::write_unaligned(var_addr as *mut Clock, clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write_unaligned(var_addr as *mut Rent, Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_invocation(|invocation| invocation.return_data.clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with_invocation(|invocation| {
+            let program = *invocation.stack.last().expect("return data outside of an instruction");
+            invocation.return_data = (!data.is_empty()).then(|| (program, data.to_vec()));
+        });
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        with_invocation(|invocation| invocation.stack.len() as u64)
+    }
+}
+
+fn dispatch<'a>(program_id: &Pubkey, accounts: &'a [AccountInfo<'a>], data: &[u8]) -> ProgramResult {
+    if *program_id == system_program::ID {
+        return system_instruction(accounts, data);
+    }
+    let processor = with_invocation(|invocation| invocation.programs.get(program_id).copied())
+        .ok_or(ProgramError::IncorrectProgramId)?;
+    with_invocation(|invocation| invocation.stack.push(*program_id));
+    let result = processor(program_id, accounts, data);
+    with_invocation(|invocation| invocation.stack.pop());
+    result
+}
+
+/// The system program instructions Anchor's `init` and lamport transfers use.
+fn system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let u64_at = |offset: usize| -> std::result::Result<u64, ProgramError> {
+        let bytes = data.get(offset..offset + 8).ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let pubkey_at = |offset: usize| -> std::result::Result<Pubkey, ProgramError> {
+        let bytes = data.get(offset..offset + 32).ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+    };
+    let account = |index: usize, signer: bool| -> std::result::Result<&AccountInfo, ProgramError> {
+        let info = accounts.get(index).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if signer && !info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(info)
+    };
+    let transfer = |from: &AccountInfo, to: &AccountInfo, lamports: u64| -> ProgramResult {
+        if *from.owner != system_program::ID || !from.data_is_empty() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let balance = from.lamports();
+        **from.try_borrow_mut_lamports()? = balance.checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+        **to.try_borrow_mut_lamports()? += lamports;
+        Ok(())
+    };
+
+    let tag = u32::from_le_bytes(data.get(..4).ok_or(ProgramError::InvalidInstructionData)?.try_into().unwrap());
+    match tag {
+        // CreateAccount { lamports, space, owner }
+        0 => {
+            let (from, to) = (account(0, true)?, account(1, true)?);
+            if to.lamports() > 0 || !to.data_is_empty() || *to.owner != system_program::ID {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            transfer(from, to, u64_at(4)?)?;
+            to.resize(u64_at(12)? as usize)?;
+            to.assign(&pubkey_at(20)?);
+        }
+        // Assign { owner }
+        1 => account(0, true)?.assign(&pubkey_at(4)?),
+        // Transfer { lamports }
+        2 => transfer(account(0, true)?, account(1, false)?, u64_at(4)?)?,
+        // Allocate { space }
+        8 => account(0, true)?.resize(u64_at(4)? as usize)?,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+/// The loader's serialized input of one instruction.
+#[derive(Default)]
+struct Input(Vec<u8>);
+
+impl Input {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.0.extend_from_slice(value);
+    }
+
+    fn zeros(&mut self, count: usize) {
+        self.0.resize(self.0.len() + count, 0);
+    }
+
+    /// Copies the input into 8-byte aligned memory, as the loader maps it.
+    fn into_aligned(self) -> Vec<u64> {
+        let mut words = vec![0u64; self.0.len().div_ceil(size_of::<u64>())];
+        unsafe { std::ptr::copy_nonoverlapping(self.0.as_ptr(), words.as_mut_ptr() as *mut u8, self.0.len()) };
+        words
+    }
+}
+
+pub struct Runtime {
+    accounts: HashMap<Pubkey, AccountState>,
+}
+
+impl Runtime {
+    /// A runtime running the presale program at slot 1.
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(NativeStubs));
+        });
+        with_invocation(|invocation| *invocation = Invocation::default());
+        let mut runtime = Runtime { accounts: HashMap::new() };
+        runtime.add_program(presale_program::ID, presale_program::entry);
+        // Emulated inside sol_invoke_signed, but programs check it is executable
+        let system = AccountState { lamports: 1, executable: true, ..AccountState::default() };
+        runtime.accounts.insert(system_program::ID, system);
+        runtime.warp(1, GENESIS_TIMESTAMP);
+        runtime
+    }
+
+    pub fn add_program(&mut self, program_id: Pubkey, processor: Processor) {
+        with_invocation(|invocation| invocation.programs.insert(program_id, processor));
+        let account = AccountState { lamports: 1, executable: true, ..AccountState::default() };
+        self.accounts.insert(program_id, account);
+    }
+
+    pub fn warp(&mut self, slot: u64, unix_timestamp: i64) {
+        with_invocation(|invocation| {
+            invocation.clock = Clock { slot, unix_timestamp, ..Clock::default() };
+        });
+    }
+
+    /// Moves the clock forward by `seconds`, at the target slot time.
+    pub fn advance(&mut self, seconds: i64) {
+        let clock = self.clock();
+        self.warp(clock.slot + (seconds as u64 * 1000).div_ceil(400), clock.unix_timestamp + seconds);
+    }
+
+    pub fn clock(&self) -> Clock {
+        with_invocation(|invocation| invocation.clock.clone())
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: AccountState) {
+        self.accounts.insert(key, account);
+    }
+
+    /// Credits a system account.
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_default().lamports += lamports;
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<&AccountState> {
+        self.accounts.get(key)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    /// Deserializes a program account, panicking if it is missing or invalid.
+    pub fn load<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self.accounts.get(key).unwrap_or_else(|| panic!("no account {key}"));
+        T::try_deserialize(&mut account.data.as_slice()).unwrap_or_else(|err| panic!("invalid account {key}: {err}"))
+    }
+
+    /// Return data of the last instruction, if any.
+    pub fn return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_invocation(|invocation| invocation.return_data.clone())
+    }
+
+    /// Event payloads logged since the last call, discriminator first.
+    pub fn take_events(&mut self) -> Vec<Vec<u8>> {
+        with_invocation(|invocation| std::mem::take(&mut invocation.events))
+    }
+
+    /// Runs the instructions as one transaction. Every meta marked as signer
+    /// counts as signed; on failure no account changes.
+    pub fn process(&mut self, instructions: &[Instruction]) -> ProgramResult {
+        let snapshot = self.accounts.clone();
+        let borrowed: Vec<BorrowedInstruction> = instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|meta| BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &instruction.data,
+            })
+            .collect();
+        let mut sysvar = solana_instructions_sysvar::construct_instructions_data(&borrowed);
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            solana_instructions_sysvar::store_current_index_checked(&mut sysvar, index as u16)
+                .expect("instructions sysvar holds the current index");
+            with_invocation(|invocation| invocation.return_data = None);
+            if let Err(err) = self.execute(instruction, &sysvar) {
+                self.accounts = snapshot;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, instruction: &Instruction, sysvar: &[u8]) -> ProgramResult {
+        let metas = &instruction.accounts;
+        // Privileges of an account are the union of those of all its metas
+        let is_signer = |key: &Pubkey| metas.iter().any(|meta| meta.pubkey == *key && meta.is_signer);
+        let is_writable = |key: &Pubkey| metas.iter().any(|meta| meta.pubkey == *key && meta.is_writable);
+
+        let mut input = Input::default();
+        input.u64(metas.len() as u64);
+        let mut lamports_before = 0u128;
+        for (index, meta) in metas.iter().enumerate() {
+            let first = metas.iter().position(|other| other.pubkey == meta.pubkey).unwrap();
+            if first < index {
+                input.u8(first as u8);
+                input.zeros(7);
+                continue;
+            }
+            let account = if meta.pubkey == instructions_sysvar::ID {
+                AccountState { lamports: 1, data: sysvar.to_vec(), owner: Pubkey::default(), executable: false }
+            } else {
+                self.accounts.get(&meta.pubkey).cloned().unwrap_or_default()
+            };
+            lamports_before += account.lamports as u128;
+            input.u8(NON_DUP_MARKER);
+            input.u8(is_signer(&meta.pubkey) as u8);
+            input.u8(is_writable(&meta.pubkey) as u8);
+            input.u8(account.executable as u8);
+            input.zeros(4);
+            input.bytes(meta.pubkey.as_ref());
+            input.bytes(account.owner.as_ref());
+            input.u64(account.lamports);
+            input.u64(account.data.len() as u64);
+            input.bytes(&account.data);
+            input.zeros(MAX_PERMITTED_DATA_INCREASE);
+            input.zeros((BPF_ALIGN_OF_U128 - input.0.len() % BPF_ALIGN_OF_U128) % BPF_ALIGN_OF_U128);
+            input.u64(0);
+        }
+        input.u64(instruction.data.len() as u64);
+        input.bytes(&instruction.data);
+        input.bytes(instruction.program_id.as_ref());
+
+        let mut memory = input.into_aligned();
+        let (program_id, infos, data) = unsafe { deserialize(memory.as_mut_ptr() as *mut u8) };
+        dispatch(program_id, &infos, data)?;
+
+        // Commit what the program wrote, as the runtime would after its checks
+        let mut lamports_after = 0u128;
+        for (index, info) in infos.iter().enumerate() {
+            if metas.iter().position(|meta| meta.pubkey == *info.key) != Some(index) {
+                continue;
+            }
+            lamports_after += info.lamports() as u128;
+            if *info.key == instructions_sysvar::ID || info.executable {
+                continue;
+            }
+            let account = AccountState {
+                lamports: info.lamports(),
+                data: info.try_borrow_data()?.to_vec(),
+                owner: *info.owner,
+                executable: false,
+            };
+            if self.accounts.get(info.key).cloned().unwrap_or_default() != account {
+                if !is_writable(info.key) {
+                    return Err(ProgramError::InvalidArgument);
+                }
+                self.accounts.insert(*info.key, account);
+            }
+        }
+        assert_eq!(lamports_before, lamports_after, "instruction created or destroyed lamports");
+        Ok(())
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pools and deposits the tests build on.
+impl Runtime {
+    /// Creates `owner`'s pool taking 0.1 to 5 SOL per deposit for an hour,
+    /// funding the owner.
+    pub fn create_pool(&mut self, owner: &Pubkey, soft_cap: u64) -> Pubkey {
+        self.airdrop(owner, 10 * LAMPORTS_PER_SOL);
+        let pool = composer::pool_address(owner).0;
+        let create = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::CreatePool {
+                pool,
+                pool_vault: composer::vault_address(&pool).0,
+                owner: *owner,
+                system_program: system_program::ID,
+                stats: composer::stats_address().0,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::CreatePool {
+                expiry_timestamp: self.clock().unix_timestamp + 3600,
+                min_deposit: LAMPORTS_PER_SOL / 10,
+                max_deposit: 5 * LAMPORTS_PER_SOL,
+                soft_cap,
+                hard_cap: 0,
+                time_basis: TimeBasis::UnixTimestamp,
+            }
+            .data(),
+        };
+        self.process(&[create]).expect("create_pool");
+        pool
+    }
+}
+
+/// SPL token accounts for finalizing pools and claiming from them.
+impl Runtime {
+    /// Runs the SPL token program in place for the program's token CPIs.
+    pub fn add_token_program(&mut self) {
+        self.add_program(spl_token::ID, spl_token::processor::Processor::process);
+    }
+
+    /// An initialized mint of `authority` without decimals.
+    pub fn create_mint(&mut self, authority: &Pubkey) -> Pubkey {
+        let mint = spl_token::state::Mint {
+            mint_authority: Some(*authority).into(),
+            is_initialized: true,
+            ..spl_token::state::Mint::default()
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+        let key = Pubkey::new_unique();
+        let account = AccountState { lamports: LAMPORTS_PER_SOL, data, owner: spl_token::ID, executable: false };
+        self.set_account(key, account);
+        key
+    }
+
+    /// An initialized token account of `owner` holding `amount` of `mint`.
+    pub fn create_token_account(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let account = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(account, &mut data).unwrap();
+        let key = Pubkey::new_unique();
+        let account = AccountState { lamports: LAMPORTS_PER_SOL, data, owner: spl_token::ID, executable: false };
+        self.set_account(key, account);
+        key
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        let account = self.accounts.get(key).unwrap_or_else(|| panic!("no account {key}"));
+        spl_token::state::Account::unpack(&account.data).expect("token account").amount
+    }
+}
+
+/// `deposit_sol` into a pool without tiers, gates or USD limits.
+pub fn deposit_instruction(pool: &Pubkey, beneficiary: &Pubkey, payer: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::DepositSol {
+            pool: *pool,
+            pool_vault: composer::vault_address(pool).0,
+            depositor_record: composer::depositor_record_address(pool, beneficiary).0,
+            beneficiary: *beneficiary,
+            payer: *payer,
+            system_program: system_program::ID,
+            instructions: instructions_sysvar::ID,
+            stats: composer::stats_address().0,
+            participant: composer::participant_address(beneficiary).0,
+            stake_record: None,
+            gate_token_account: None,
+            gate_metadata: None,
+            price_oracle: None,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::DepositSol { amount, whitelist_proof: Vec::new() }.data(),
+    }
+}
+
+/// The error a failed instruction returns for a program error.
+pub fn program_error(error: impl Into<anchor_lang::error::Error>) -> ProgramError {
+    error.into().into()
+}
@@ -0,0 +1,200 @@
+//! `LaunchpadStats::active_pools` as failed pools settle, including pools
+//! from before the stats account.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*, solana_program::instruction::Instruction, AccountSerialize, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, LaunchpadStats, Pool, PoolSettled, PresaleError};
+use runtime::{deposit_instruction, program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+/// More than the fixtures ever deposit, so every pool fails.
+const SOFT_CAP: u64 = 100 * LAMPORTS_PER_SOL;
+
+/// Length of version 9 pools, which end before the `settled` flag.
+const V9_SPACE: usize = Pool::SPACE - 1 - 8 - 32 - 4 - 1;
+
+fn refund_instruction(pool: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::Refund {
+            pool: *pool,
+            pool_vault: composer::vault_address(pool).0,
+            depositor_record: composer::depositor_record_address(pool, depositor).0,
+            depositor: *depositor,
+            stats: composer::stats_address().0,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::Refund {}.data(),
+    }
+}
+
+fn settle_instruction(pool: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::SettleFailedPool { pool: *pool, stats: composer::stats_address().0 }
+            .to_account_metas(None),
+        data: presale_program::instruction::SettleFailedPool {}.data(),
+    }
+}
+
+fn active_pools(runtime: &Runtime) -> u64 {
+    runtime.load::<LaunchpadStats>(&composer::stats_address().0).active_pools
+}
+
+/// Two pools, so a second settlement of the first would show in the count.
+fn setup() -> (Runtime, Pubkey) {
+    let mut runtime = Runtime::new();
+    runtime.create_pool(&Pubkey::new_unique(), SOFT_CAP);
+    let pool = runtime.create_pool(&Pubkey::new_unique(), SOFT_CAP);
+    assert_eq!(active_pools(&runtime), 2);
+    (runtime, pool)
+}
+
+#[test]
+fn refunds_settle_a_pool_once() {
+    let (mut runtime, pool) = setup();
+    let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+    for depositor in &depositors {
+        runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+        runtime.process(&[deposit_instruction(&pool, depositor, depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    }
+    runtime.advance(3601);
+
+    for depositor in &depositors {
+        runtime.process(&[refund_instruction(&pool, depositor)]).expect("refund");
+    }
+    assert_eq!(active_pools(&runtime), 1);
+    assert!(runtime.load::<Pool>(&pool).settled);
+    assert_eq!(runtime.process(&[settle_instruction(&pool)]), Err(program_error(PresaleError::PoolAlreadySettled)));
+}
+
+#[test]
+fn empty_records_have_nothing_to_refund() {
+    let (mut runtime, pool) = setup();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&first, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &first, &first, LAMPORTS_PER_SOL)]).expect("deposit");
+    let transfer = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::TransferPosition {
+            pool,
+            from_record: composer::depositor_record_address(&pool, &first).0,
+            to_record: composer::depositor_record_address(&pool, &second).0,
+            recipient: second,
+            owner: first,
+            system_program: anchor_lang::system_program::ID,
+            instructions: anchor_lang::solana_program::sysvar::instructions::ID,
+            stats: composer::stats_address().0,
+            participant: composer::participant_address(&second).0,
+            gate_token_account: None,
+            gate_metadata: None,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::TransferPosition { amount: LAMPORTS_PER_SOL, whitelist_proof: Vec::new() }
+            .data(),
+    };
+    runtime.process(&[transfer]).expect("transfer_position");
+    runtime.advance(3601);
+
+    assert_eq!(runtime.process(&[refund_instruction(&pool, &first)]), Err(program_error(PresaleError::NothingToRefund)));
+    runtime.process(&[refund_instruction(&pool, &second)]).expect("refund");
+    assert_eq!(active_pools(&runtime), 1);
+}
+
+#[test]
+fn failed_pools_without_deposits_settle() {
+    let (mut runtime, pool) = setup();
+    assert_eq!(runtime.process(&[settle_instruction(&pool)]), Err(program_error(PresaleError::RefundNotAvailable)));
+
+    runtime.advance(3601);
+    runtime.take_events();
+    runtime.process(&[settle_instruction(&pool)]).expect("settle_failed_pool");
+    assert_eq!(active_pools(&runtime), 1);
+    let events = runtime.take_events();
+    let event = events.iter().find(|event| event.starts_with(PoolSettled::DISCRIMINATOR)).expect("PoolSettled");
+    let event = PoolSettled::try_from_slice(&event[8..]).unwrap();
+    assert_eq!((event.pool, event.active_pools), (pool, 1));
+
+    runtime.advance(1);
+    assert_eq!(runtime.process(&[settle_instruction(&pool)]), Err(program_error(PresaleError::PoolAlreadySettled)));
+    assert_eq!(active_pools(&runtime), 1);
+}
+
+#[test]
+fn pools_without_the_flag_settle_on_their_first_refund() {
+    let (mut runtime, pool) = setup();
+    let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+    for depositor in &depositors {
+        runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+        runtime.process(&[deposit_instruction(&pool, depositor, depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    }
+
+    // Rewrite the pool as version 9 laid it out, without the flag
+    let mut state: Pool = runtime.load(&pool);
+    state.version = 9;
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    data.truncate(V9_SPACE);
+    let lamports = runtime.lamports(&pool);
+    runtime.set_account(pool, AccountState { lamports, data, owner: presale_program::ID, executable: false });
+    runtime.advance(3601);
+
+    assert_eq!(runtime.process(&[settle_instruction(&pool)]), Err(program_error(PresaleError::AccountNeedsMigration)));
+    for depositor in &depositors {
+        runtime.process(&[refund_instruction(&pool, depositor)]).expect("refund");
+    }
+    assert_eq!(active_pools(&runtime), 1);
+}
+
+#[test]
+fn pools_from_before_the_stats_account_stay_uncounted() {
+    let (mut runtime, pool) = setup();
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+
+    // Version 1 pools were created before `create_pool` counted them
+    let mut state: Pool = runtime.load(&pool);
+    state.version = 1;
+    state.counted = false;
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    let lamports = runtime.lamports(&pool);
+    runtime.set_account(pool, AccountState { lamports, data, owner: presale_program::ID, executable: false });
+    runtime.advance(3601);
+
+    runtime.process(&[refund_instruction(&pool, &depositor)]).expect("refund");
+    assert_eq!(active_pools(&runtime), 2);
+}
+
+#[test]
+fn existing_pools_settle_once_the_stats_account_is_created() {
+    let (mut runtime, pool) = setup();
+    let depositor = Pubkey::new_unique();
+    runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+    runtime.process(&[deposit_instruction(&pool, &depositor, &depositor, LAMPORTS_PER_SOL)]).expect("deposit");
+    runtime.advance(3601);
+
+    // As after an upgrade from a version without the stats account
+    let stats = composer::stats_address().0;
+    runtime.set_account(stats, AccountState::default());
+    assert!(runtime.process(&[refund_instruction(&pool, &depositor)]).is_err());
+
+    let payer = Pubkey::new_unique();
+    runtime.airdrop(&payer, LAMPORTS_PER_SOL);
+    let init = Instruction {
+        program_id: presale_program::ID,
+        accounts: presale_program::accounts::InitStats {
+            stats,
+            payer,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: presale_program::instruction::InitStats {}.data(),
+    };
+    runtime.process(&[init]).expect("init_stats");
+    runtime.process(&[refund_instruction(&pool, &depositor)]).expect("refund");
+    assert!(runtime.load::<Pool>(&pool).settled);
+}
@@ -0,0 +1,135 @@
+//! Streaming pools, which pay the raise out to the owner over time unless
+//! depositors vote to halt them.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    system_program, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, Pool, PresaleError};
+use runtime::{deposit_instruction, program_error, Runtime, LAMPORTS_PER_SOL};
+
+const DURATION: i64 = 1000;
+
+struct Fixture {
+    runtime: Runtime,
+    owner: Pubkey,
+    pool: Pubkey,
+    depositors: [Pubkey; 2],
+}
+
+impl Fixture {
+    /// A pool streaming its raise over 1000 seconds from expiry, halted by
+    /// half of it, with deposits of 1 and 3 SOL.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigurePool { pool, owner }.to_account_metas(None),
+            data: presale_program::instruction::ConfigureStream { duration: DURATION, halt_bps: 5_000 }.data(),
+        };
+        runtime.process(&[configure]).expect("configure_stream");
+
+        let depositors = [Pubkey::new_unique(), Pubkey::new_unique()];
+        for (depositor, sol) in depositors.iter().zip([1, 3]) {
+            runtime.airdrop(depositor, 10 * LAMPORTS_PER_SOL);
+            let deposit = deposit_instruction(&pool, depositor, depositor, sol * LAMPORTS_PER_SOL);
+            runtime.process(&[deposit]).expect("deposit");
+        }
+        Fixture { runtime, owner, pool, depositors }
+    }
+
+    /// Moves the clock to `seconds` after the pool's expiry.
+    fn warp_past_expiry(&mut self, seconds: i64) {
+        let expiry = self.runtime.load::<Pool>(&self.pool).expiry_timestamp;
+        let clock = self.runtime.clock();
+        self.runtime.warp(clock.slot + 1, expiry + seconds);
+    }
+
+    /// Runs `withdraw_stream` and returns what the owner received.
+    fn withdraw(&mut self) -> std::result::Result<u64, ProgramError> {
+        let withdraw = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ClaimFunds {
+                pool: self.pool,
+                pool_vault: composer::vault_address(&self.pool).0,
+                owner: self.owner,
+                stats: composer::stats_address().0,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::WithdrawStream {}.data(),
+        };
+        let before = self.runtime.lamports(&self.owner);
+        self.runtime.process(&[withdraw])?;
+        Ok(self.runtime.lamports(&self.owner) - before)
+    }
+
+    fn vote_halt(&mut self, voter: &Pubkey) -> ProgramResult {
+        let vote = Pubkey::find_program_address(
+            &[b"halt_vote", self.pool.as_ref(), voter.as_ref()],
+            &presale_program::ID,
+        )
+        .0;
+        let cast = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::VoteHalt {
+                pool: self.pool,
+                pool_vault: composer::vault_address(&self.pool).0,
+                depositor_record: composer::depositor_record_address(&self.pool, voter).0,
+                vote,
+                owner: self.owner,
+                stats: composer::stats_address().0,
+                voter: *voter,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::VoteHalt {}.data(),
+        };
+        self.runtime.process(&[cast])
+    }
+}
+
+#[test]
+fn owners_withdraw_the_raise_as_it_accrues() {
+    let mut fixture = Fixture::new();
+
+    assert_eq!(fixture.withdraw(), Err(program_error(PresaleError::PoolNotExpired)));
+
+    // A quarter of the 4 SOL raise a quarter of the way through
+    fixture.warp_past_expiry(DURATION / 4);
+    assert_eq!(fixture.withdraw(), Ok(LAMPORTS_PER_SOL));
+    assert_eq!(fixture.withdraw(), Err(program_error(PresaleError::NothingToWithdraw)));
+
+    // Accrual stops at the raise
+    fixture.warp_past_expiry(2 * DURATION);
+    assert_eq!(fixture.withdraw(), Ok(3 * LAMPORTS_PER_SOL));
+    assert_eq!(fixture.runtime.load::<Pool>(&fixture.pool).total_released, 4 * LAMPORTS_PER_SOL);
+    let voter = fixture.depositors[1];
+    assert_eq!(fixture.vote_halt(&voter), Err(program_error(PresaleError::StreamEnded)));
+}
+
+#[test]
+fn depositors_holding_the_threshold_halt_the_stream() {
+    let mut fixture = Fixture::new();
+    let [small, large] = fixture.depositors;
+    fixture.warp_past_expiry(DURATION / 2);
+
+    // A quarter of the raise is short of the half needed
+    fixture.vote_halt(&small).expect("vote_halt");
+    assert!(!fixture.runtime.load::<Pool>(&fixture.pool).release_rejected);
+
+    // Halting pays the owner the 2 SOL accrued by now; the rest stays for refunds
+    let before = fixture.runtime.lamports(&fixture.owner);
+    fixture.vote_halt(&large).expect("vote_halt");
+    assert_eq!(fixture.runtime.lamports(&fixture.owner) - before, 2 * LAMPORTS_PER_SOL);
+    let pool: Pool = fixture.runtime.load(&fixture.pool);
+    assert!(pool.release_rejected);
+    assert_eq!(pool.total_released, 2 * LAMPORTS_PER_SOL);
+
+    fixture.warp_past_expiry(DURATION);
+    assert_eq!(fixture.withdraw(), Err(program_error(PresaleError::StreamHalted)));
+}
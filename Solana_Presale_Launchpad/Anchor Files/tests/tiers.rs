@@ -0,0 +1,121 @@
+//! Deposits into tiered pools, which lock the beneficiary's stake.
+
+mod runtime;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    system_program, AccountSerialize, InstructionData, ToAccountMetas,
+};
+use presale_program::{composer, PresaleError, StakeRecord};
+use runtime::{program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+/// Smallest stake of the pool's only tier.
+const MIN_STAKE: u64 = 1_000;
+
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    mint: Pubkey,
+    staker: Pubkey,
+}
+
+impl Fixture {
+    /// A pool with one tier allowing 5 SOL, and a staker holding that tier.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+
+        // An initialized SPL mint without authorities
+        let mint = Pubkey::new_unique();
+        let mut data = vec![0; 82];
+        data[45] = 1;
+        runtime.set_account(
+            mint,
+            AccountState { lamports: LAMPORTS_PER_SOL, data, owner: anchor_spl::token::ID, executable: false },
+        );
+        let configure = Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigureTiers { pool, stake_mint: mint, owner }
+                .to_account_metas(None),
+            data: presale_program::instruction::ConfigureTiers {
+                min_stakes: vec![MIN_STAKE],
+                max_allocations: vec![5 * LAMPORTS_PER_SOL],
+            }
+            .data(),
+        };
+        runtime.process(&[configure]).expect("configure_tiers");
+
+        let staker = Pubkey::new_unique();
+        runtime.airdrop(&staker, 10 * LAMPORTS_PER_SOL);
+        let (address, bump) = composer::stake_record_address(&mint, &staker);
+        let record = StakeRecord { mint, staker, amount: MIN_STAKE, active_positions: 0, bump };
+        let mut data = Vec::new();
+        record.try_serialize(&mut data).unwrap();
+        runtime.set_account(
+            address,
+            AccountState { lamports: LAMPORTS_PER_SOL, data, owner: presale_program::ID, executable: false },
+        );
+        Fixture { runtime, pool, mint, staker }
+    }
+
+    /// The staker's deposit paid by `payer`, signed by the staker if `signed`.
+    fn deposit(&mut self, payer: &Pubkey, signed: bool) -> ProgramResult {
+        let (pool, staker) = (self.pool, self.staker);
+        let mut accounts = presale_program::accounts::DepositSol {
+            pool,
+            pool_vault: composer::vault_address(&pool).0,
+            depositor_record: composer::depositor_record_address(&pool, &staker).0,
+            beneficiary: staker,
+            payer: *payer,
+            system_program: system_program::ID,
+            instructions: anchor_lang::solana_program::sysvar::instructions::ID,
+            stats: composer::stats_address().0,
+            participant: composer::participant_address(&staker).0,
+            stake_record: Some(composer::stake_record_address(&self.mint, &staker).0),
+            gate_token_account: None,
+            gate_metadata: None,
+            price_oracle: None,
+        }
+        .to_account_metas(None);
+        accounts[3].is_signer = signed;
+        let deposit = Instruction {
+            program_id: presale_program::ID,
+            accounts,
+            data: presale_program::instruction::DepositSol { amount: LAMPORTS_PER_SOL, whitelist_proof: Vec::new() }
+                .data(),
+        };
+        self.runtime.process(&[deposit])
+    }
+
+    fn active_positions(&self) -> u32 {
+        let address = composer::stake_record_address(&self.mint, &self.staker).0;
+        self.runtime.load::<StakeRecord>(&address).active_positions
+    }
+}
+
+#[test]
+fn others_cannot_lock_a_stakers_stake() {
+    let mut fixture = Fixture::new();
+    let payer = Pubkey::new_unique();
+    fixture.runtime.airdrop(&payer, 10 * LAMPORTS_PER_SOL);
+
+    assert_eq!(fixture.deposit(&payer, false), Err(program_error(PresaleError::BeneficiarySignatureRequired)));
+    assert_eq!(fixture.active_positions(), 0);
+}
+
+#[test]
+fn signed_deposits_lock_the_stake_once() {
+    let mut fixture = Fixture::new();
+    let staker = fixture.staker;
+    fixture.deposit(&staker, true).expect("deposit");
+    assert_eq!(fixture.active_positions(), 1);
+
+    // Someone else may still pay, as long as the staker signs
+    let payer = Pubkey::new_unique();
+    fixture.runtime.airdrop(&payer, 10 * LAMPORTS_PER_SOL);
+    fixture.runtime.advance(1);
+    fixture.deposit(&payer, true).expect("deposit");
+    assert_eq!(fixture.active_positions(), 1);
+}
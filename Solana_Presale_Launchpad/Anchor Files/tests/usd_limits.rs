@@ -0,0 +1,161 @@
+//! Deposits valued against USD limits at the pool's SOL/USD price account.
+
+mod runtime;
+
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use presale_program::{
+    OracleKind, Pool, PresaleError, MOCK_PRICE_MAGIC, PYTH_PRICE_UPDATE_DISCRIMINATOR,
+    PYTH_RECEIVER_PROGRAM_ID, PYTH_SOL_USD_FEED_ID,
+};
+use runtime::{deposit_instruction, program_error, AccountState, Runtime, LAMPORTS_PER_SOL};
+
+/// $150 per SOL, as `price × 10^-8`.
+const PRICE: i64 = 150_00000000;
+const MAX_PRICE_AGE: i64 = 60;
+/// $30 to $300 per deposit in micro-USD, i.e. 0.2 to 2 SOL.
+const USD_MIN_DEPOSIT: u64 = 30_000_000;
+const USD_MAX_DEPOSIT: u64 = 300_000_000;
+
+struct Fixture {
+    runtime: Runtime,
+    pool: Pubkey,
+    owner: Pubkey,
+    oracle: Pubkey,
+    depositor: Pubkey,
+}
+
+impl Fixture {
+    /// A pool taking $30 to $300 per deposit, priced by a fresh mock account.
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let owner = Pubkey::new_unique();
+        let pool = runtime.create_pool(&owner, 0);
+        let depositor = Pubkey::new_unique();
+        runtime.airdrop(&depositor, 10 * LAMPORTS_PER_SOL);
+        let mut fixture = Fixture { runtime, pool, owner, oracle: Pubkey::new_unique(), depositor };
+        fixture.publish(fixture.oracle, 0);
+        let configure = fixture.configure(OracleKind::Mock, fixture.oracle);
+        fixture.runtime.process(&[configure]).expect("configure_usd_limits");
+        fixture
+    }
+
+    /// Writes a mock price published `offset` seconds from now to `oracle`.
+    fn publish(&mut self, oracle: Pubkey, offset: i64) {
+        let mut data = MOCK_PRICE_MAGIC.to_vec();
+        data.extend_from_slice(&PRICE.to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes());
+        data.extend_from_slice(&(self.runtime.clock().unix_timestamp + offset).to_le_bytes());
+        // Any program may own a mock price account
+        let state = AccountState { lamports: LAMPORTS_PER_SOL, data, owner: Pubkey::new_unique(), executable: false };
+        self.runtime.set_account(oracle, state);
+    }
+
+    fn configure(&self, kind: OracleKind, oracle: Pubkey) -> Instruction {
+        Instruction {
+            program_id: presale_program::ID,
+            accounts: presale_program::accounts::ConfigureUsdLimits {
+                pool: self.pool,
+                price_oracle: Some(oracle),
+                owner: self.owner,
+            }
+            .to_account_metas(None),
+            data: presale_program::instruction::ConfigureUsdLimits {
+                kind,
+                max_price_age: MAX_PRICE_AGE,
+                min_deposit: USD_MIN_DEPOSIT,
+                max_deposit: USD_MAX_DEPOSIT,
+                soft_cap: 0,
+                hard_cap: 0,
+            }
+            .data(),
+        }
+    }
+
+    /// The depositor's deposit of `amount`, priced by `oracle`.
+    fn deposit(&mut self, amount: u64, oracle: Option<Pubkey>) -> std::result::Result<(), ProgramError> {
+        let mut deposit = deposit_instruction(&self.pool, &self.depositor, &self.depositor, amount);
+        // The optional price account is the last; the program id marks it as absent
+        deposit.accounts.last_mut().unwrap().pubkey = oracle.unwrap_or(presale_program::ID);
+        self.runtime.advance(1);
+        self.runtime.process(&[deposit])
+    }
+}
+
+#[test]
+fn deposits_stay_within_the_usd_bounds() {
+    let mut fixture = Fixture::new();
+    let oracle = Some(fixture.oracle);
+
+    // $15 and $450 at $150 per SOL
+    assert_eq!(fixture.deposit(LAMPORTS_PER_SOL / 10, oracle), Err(program_error(PresaleError::DepositTooLow)));
+    fixture.publish(fixture.oracle, 0);
+    assert_eq!(fixture.deposit(3 * LAMPORTS_PER_SOL, oracle), Err(program_error(PresaleError::DepositTooHigh)));
+
+    fixture.publish(fixture.oracle, 0);
+    fixture.deposit(LAMPORTS_PER_SOL, oracle).expect("deposit");
+    let pool: Pool = fixture.runtime.load(&fixture.pool);
+    assert_eq!(pool.total_raised_usd, 150_000_000);
+}
+
+#[test]
+fn stale_and_future_prices_are_rejected() {
+    let mut fixture = Fixture::new();
+    let oracle = Some(fixture.oracle);
+
+    fixture.runtime.advance(MAX_PRICE_AGE);
+    assert_eq!(fixture.deposit(LAMPORTS_PER_SOL, oracle), Err(program_error(PresaleError::StalePrice)));
+
+    // Publishers' clocks may run slightly ahead of the cluster's, but not by a minute
+    fixture.publish(fixture.oracle, 60);
+    assert_eq!(fixture.deposit(LAMPORTS_PER_SOL, oracle), Err(program_error(PresaleError::StalePrice)));
+    fixture.publish(fixture.oracle, 5);
+    fixture.deposit(LAMPORTS_PER_SOL, oracle).expect("deposit");
+}
+
+#[test]
+fn only_the_pools_price_account_counts() {
+    let mut fixture = Fixture::new();
+
+    let other = Pubkey::new_unique();
+    fixture.publish(other, 0);
+    assert_eq!(
+        fixture.deposit(LAMPORTS_PER_SOL, Some(other)),
+        Err(program_error(PresaleError::InvalidPriceOracle))
+    );
+    assert_eq!(fixture.deposit(LAMPORTS_PER_SOL, None), Err(program_error(PresaleError::MissingPriceOracle)));
+}
+
+#[test]
+fn pyth_prices_need_the_receiver_and_the_sol_usd_feed() {
+    let mut fixture = Fixture::new();
+    let now = fixture.runtime.clock().unix_timestamp;
+    let price_update = |feed_id: [u8; 32]| {
+        let mut data = PYTH_PRICE_UPDATE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        // Fully verified
+        data.push(1);
+        data.extend_from_slice(&feed_id);
+        data.extend_from_slice(&PRICE.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes());
+        data.extend_from_slice(&now.to_le_bytes());
+        data
+    };
+    let mut set_oracle = |data: Vec<u8>, owner: Pubkey| {
+        let oracle = Pubkey::new_unique();
+        let state = AccountState { lamports: LAMPORTS_PER_SOL, data, owner, executable: false };
+        fixture.runtime.set_account(oracle, state);
+        oracle
+    };
+
+    let wrong_owner = set_oracle(price_update(PYTH_SOL_USD_FEED_ID), Pubkey::new_unique());
+    let wrong_feed = set_oracle(price_update([7; 32]), PYTH_RECEIVER_PROGRAM_ID);
+    let sol_usd = set_oracle(price_update(PYTH_SOL_USD_FEED_ID), PYTH_RECEIVER_PROGRAM_ID);
+
+    for oracle in [wrong_owner, wrong_feed] {
+        let configure = fixture.configure(OracleKind::Pyth, oracle);
+        assert_eq!(fixture.runtime.process(&[configure]), Err(program_error(PresaleError::InvalidPriceOracle)));
+    }
+    fixture.runtime.process(&[fixture.configure(OracleKind::Pyth, sol_usd)]).expect("configure_usd_limits");
+    fixture.deposit(LAMPORTS_PER_SOL, Some(sol_usd)).expect("deposit");
+}
@@ -1,11 +1,37 @@
 import {
   Connection,
+  Ed25519Program,
   PublicKey,
   SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
   Transaction,
   LAMPORTS_PER_SOL,
 } from '@solana/web3.js';
 
+// sha256("account:DepositorRecord")[..8]
+const DEPOSITOR_RECORD_DISCRIMINATOR = [120, 180, 8, 71, 198, 212, 93, 187];
+
+// Pool.min_slot_gap, a u64
+const POOL_MIN_SLOT_GAP_OFFSET = 247;
+// Pool.time_basis, added in version 2; older pools are unix-timed
+const POOL_TIME_BASIS_OFFSET = 358;
+// Pool.tier_mint and tier_count, added in version 3
+const POOL_TIER_MINT_OFFSET = 359;
+const POOL_TIER_COUNT_OFFSET = 391;
+// Pool.gate_kind, added in version 6
+const POOL_GATE_KIND_OFFSET = 517;
+// Pool.oracle_kind and price_oracle, added in version 9
+const POOL_ORACLE_KIND_OFFSET = 625;
+const POOL_PRICE_ORACLE_OFFSET = 626;
+// Pool fields getPoolInfo reads, up to time_basis
+const POOL_INFO_LENGTH = POOL_TIME_BASIS_OFFSET + 1;
+const ESTIMATED_SLOT_MS = 400;
+
+export const TimeBasis = { UNIX_TIMESTAMP: 0, SLOT: 1 };
+export const GateKind = { NONE: 0, TOKEN_HOLDER: 1, NFT_COLLECTION: 2 };
+
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey('metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s');
+
 /**
  * Complete Solana Presale Client Implementation
  * Ready-to-use client for interacting with your presale program
@@ -50,30 +76,107 @@ class PresaleClient {
     return depositorRecordPDA;
   }
 
+  /**
+   * Get pool metadata PDA
+   */
+  getPoolMetadataPDA(pool) {
+    const [metadataPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from('metadata'), pool.toBuffer()],
+      this.programId
+    );
+    return metadataPDA;
+  }
+
+  /**
+   * Get the program-wide stats PDA
+   */
+  getStatsPDA() {
+    const [statsPDA] = PublicKey.findProgramAddressSync([Buffer.from('stats')], this.programId);
+    return statsPDA;
+  }
+
+  /**
+   * Get a wallet's stake record PDA for a staking mint
+   */
+  getStakeRecordPDA(mint, staker) {
+    const [stakeRecordPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from('stake'), mint.toBuffer(), staker.toBuffer()],
+      this.programId
+    );
+    return stakeRecordPDA;
+  }
+
+  /**
+   * Get the Token Metadata account of a mint, read by NFT-collection gates
+   */
+  getTokenMetadataPDA(mint) {
+    const [metadataPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from('metadata'), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    return metadataPDA;
+  }
+
+  /**
+   * Get the PDA marking a wallet as counted among unique depositors
+   */
+  getParticipantPDA(wallet) {
+    const [participantPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from('participant'), wallet.toBuffer()],
+      this.programId
+    );
+    return participantPDA;
+  }
+
   /**
    * Create instruction data for create_pool
    */
-  createPoolInstructionData(expiryTimestamp, minDepositLamports, maxDepositLamports) {
+  createPoolInstructionData(
+    expiryTimestamp,
+    minDepositLamports,
+    maxDepositLamports,
+    softCapLamports = 0,
+    hardCapLamports = 0,
+    timeBasis = TimeBasis.UNIX_TIMESTAMP
+  ) {
     const discriminator = Buffer.from([233, 146, 209, 142, 207, 104, 64, 188]);
-    const data = Buffer.alloc(8 + 8 + 8 + 8);
+    const data = Buffer.alloc(8 + 8 + 8 + 8 + 8 + 8 + 1);
     discriminator.copy(data, 0);
     data.writeBigInt64LE(BigInt(expiryTimestamp), 8);
     data.writeBigUInt64LE(BigInt(minDepositLamports), 16);
     data.writeBigUInt64LE(BigInt(maxDepositLamports), 24);
+    data.writeBigUInt64LE(BigInt(softCapLamports), 32);
+    data.writeBigUInt64LE(BigInt(hardCapLamports), 40);
+    data.writeUInt8(timeBasis, 48);
     return data;
   }
 
   /**
    * Create instruction data for deposit_sol
    */
-  depositSolInstructionData(amountLamports) {
+  depositSolInstructionData(amountLamports, whitelistProof = []) {
     const discriminator = Buffer.from([108, 81, 78, 117, 125, 155, 56, 200]);
-    const data = Buffer.alloc(8 + 8);
+    const data = Buffer.alloc(8 + 8 + 4 + 32 * whitelistProof.length);
     discriminator.copy(data, 0);
     data.writeBigUInt64LE(BigInt(amountLamports), 8);
+    data.writeUInt32LE(whitelistProof.length, 16);
+    whitelistProof.forEach((node, i) => Buffer.from(node).copy(data, 20 + 32 * i));
     return data;
   }
 
+  /**
+   * Message an attestation authority signs for a depositor:
+   * pool || depositor || expiry (i64 LE) || max allocation in lamports (u64 LE)
+   */
+  attestationMessage(pool, depositor, expiry, maxAllocationLamports) {
+    const message = Buffer.alloc(32 + 32 + 8 + 8);
+    pool.toBuffer().copy(message, 0);
+    depositor.toBuffer().copy(message, 32);
+    message.writeBigInt64LE(BigInt(expiry), 64);
+    message.writeBigUInt64LE(BigInt(maxAllocationLamports), 72);
+    return message;
+  }
+
   /**
    * Create instruction data for claim_funds
    */
@@ -82,9 +185,17 @@ class PresaleClient {
   }
 
   /**
-   * Create a presale pool
+   * Create a presale pool. A soft or hard cap of 0 leaves that cap unset.
+   * With TimeBasis.SLOT, expiryTimestamp is a slot rather than a unix timestamp.
    */
-  async createPool(expiryTimestamp, minDepositSOL, maxDepositSOL) {
+  async createPool(
+    expiryTimestamp,
+    minDepositSOL,
+    maxDepositSOL,
+    softCapSOL = 0,
+    hardCapSOL = 0,
+    timeBasis = TimeBasis.UNIX_TIMESTAMP
+  ) {
     if (!this.wallet || !this.wallet.publicKey) {
       throw new Error('Wallet not connected');
     }
@@ -95,6 +206,8 @@ class PresaleClient {
 
     const minDepositLamports = minDepositSOL * LAMPORTS_PER_SOL;
     const maxDepositLamports = maxDepositSOL * LAMPORTS_PER_SOL;
+    const softCapLamports = softCapSOL * LAMPORTS_PER_SOL;
+    const hardCapLamports = hardCapSOL * LAMPORTS_PER_SOL;
 
     const instruction = {
       programId: this.programId,
@@ -103,8 +216,16 @@ class PresaleClient {
         { pubkey: poolVault, isSigner: false, isWritable: true },
         { pubkey: owner, isSigner: true, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: this.getStatsPDA(), isSigner: false, isWritable: true },
       ],
-      data: this.createPoolInstructionData(expiryTimestamp, minDepositLamports, maxDepositLamports),
+      data: this.createPoolInstructionData(
+        expiryTimestamp,
+        minDepositLamports,
+        maxDepositLamports,
+        softCapLamports,
+        hardCapLamports,
+        timeBasis
+      ),
     };
 
     const transaction = new Transaction().add(instruction);
@@ -125,18 +246,70 @@ class PresaleClient {
   }
 
   /**
-   * Deposit SOL into a pool
+   * Deposit SOL into a pool. Gated pools also need an attestation
+   * `{ authority, expiry, maxAllocation, signature }` from the pool's
+   * attestation authority, with `maxAllocation` in lamports and `signature`
+   * as the 64 raw signature bytes. During a pool's whitelist window pass the
+   * wallet's `whitelistProof` as an array of 32-byte nodes. Token-holder and
+   * NFT-collection gated pools need `gateTokenAccount`, a token account of
+   * the beneficiary's holding the gate token or NFT. Pools with USD limits
+   * read the price account stored in the pool. Deposits into tiered pools
+   * lock the beneficiary's stake, and deposits into pools with a slot gap
+   * restart its wait, so in those only the beneficiary can make them.
    */
-  async depositSOL(poolAddress, amountSOL) {
+  async depositSOL(
+    poolAddress,
+    amountSOL,
+    attestation = null,
+    whitelistProof = [],
+    beneficiaryAddress = null,
+    gateTokenAccount = null
+  ) {
     if (!this.wallet || !this.wallet.publicKey) {
       throw new Error('Wallet not connected');
     }
 
     const pool = new PublicKey(poolAddress);
     const poolVault = this.getVaultPDA(pool);
-    const depositor = this.wallet.publicKey;
+    const payer = this.wallet.publicKey;
+    // The record belongs to the beneficiary, who defaults to the connected wallet
+    const depositor = beneficiaryAddress ? new PublicKey(beneficiaryAddress) : payer;
     const depositorRecord = this.getDepositorRecordPDA(pool, depositor);
 
+    // Tiered pools read the beneficiary's stake; the program id marks the slot as empty
+    let stakeRecord = this.programId;
+    const poolAccount = await this.connection.getAccountInfo(pool);
+    const tiered =
+      poolAccount && poolAccount.data.length > POOL_TIER_COUNT_OFFSET && poolAccount.data[POOL_TIER_COUNT_OFFSET] > 0;
+    const slotGap =
+      poolAccount &&
+      poolAccount.data.length >= POOL_MIN_SLOT_GAP_OFFSET + 8 &&
+      poolAccount.data.readBigUInt64LE(POOL_MIN_SLOT_GAP_OFFSET) > 0n;
+    if ((tiered || slotGap) && !depositor.equals(payer)) {
+      throw new Error('This pool only takes deposits signed by the beneficiary');
+    }
+    if (tiered) {
+      const tierMint = new PublicKey(poolAccount.data.slice(POOL_TIER_MINT_OFFSET, POOL_TIER_MINT_OFFSET + 32));
+      stakeRecord = this.getStakeRecordPDA(tierMint, depositor);
+    }
+
+    // NFT gates also read the metadata of the token account's mint
+    let gateToken = this.programId;
+    let gateMetadata = this.programId;
+    if (gateTokenAccount) {
+      gateToken = new PublicKey(gateTokenAccount);
+      if (poolAccount && poolAccount.data[POOL_GATE_KIND_OFFSET] === GateKind.NFT_COLLECTION) {
+        const tokenAccount = await this.connection.getAccountInfo(gateToken);
+        gateMetadata = this.getTokenMetadataPDA(new PublicKey(tokenAccount.data.slice(0, 32)));
+      }
+    }
+
+    // USD-limited pools value the deposit at their SOL/USD price account
+    let priceOracle = this.programId;
+    if (poolAccount && poolAccount.data.length > POOL_ORACLE_KIND_OFFSET && poolAccount.data[POOL_ORACLE_KIND_OFFSET] > 0) {
+      priceOracle = new PublicKey(poolAccount.data.slice(POOL_PRICE_ORACLE_OFFSET, POOL_PRICE_ORACLE_OFFSET + 32));
+    }
+
     const amountLamports = amountSOL * LAMPORTS_PER_SOL;
 
     const instruction = {
@@ -145,16 +318,35 @@ class PresaleClient {
         { pubkey: pool, isSigner: false, isWritable: true },
         { pubkey: poolVault, isSigner: false, isWritable: true },
         { pubkey: depositorRecord, isSigner: false, isWritable: true },
-        { pubkey: depositor, isSigner: true, isWritable: true },
+        { pubkey: depositor, isSigner: false, isWritable: false },
+        { pubkey: payer, isSigner: true, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: SYSVAR_INSTRUCTIONS_PUBKEY, isSigner: false, isWritable: false },
+        { pubkey: this.getStatsPDA(), isSigner: false, isWritable: true },
+        { pubkey: this.getParticipantPDA(depositor), isSigner: false, isWritable: true },
+        { pubkey: stakeRecord, isSigner: false, isWritable: !stakeRecord.equals(this.programId) },
+        { pubkey: gateToken, isSigner: false, isWritable: false },
+        { pubkey: gateMetadata, isSigner: false, isWritable: false },
+        { pubkey: priceOracle, isSigner: false, isWritable: false },
       ],
-      data: this.depositSolInstructionData(amountLamports),
+      data: this.depositSolInstructionData(amountLamports, whitelistProof),
     };
 
-    const transaction = new Transaction().add(instruction);
+    const transaction = new Transaction();
+    if (attestation) {
+      // Gated pools verify the authority's signature from the preceding ed25519 instruction
+      transaction.add(
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: new PublicKey(attestation.authority).toBytes(),
+          message: this.attestationMessage(pool, depositor, attestation.expiry, attestation.maxAllocation),
+          signature: attestation.signature,
+        })
+      );
+    }
+    transaction.add(instruction);
     const { blockhash } = await this.connection.getLatestBlockhash();
     transaction.recentBlockhash = blockhash;
-    transaction.feePayer = depositor;
+    transaction.feePayer = payer;
 
     const signedTransaction = await this.wallet.signTransaction(transaction);
     const signature = await this.connection.sendRawTransaction(signedTransaction.serialize());
@@ -182,6 +374,7 @@ class PresaleClient {
         { pubkey: pool, isSigner: false, isWritable: true },
         { pubkey: poolVault, isSigner: false, isWritable: true },
         { pubkey: owner, isSigner: true, isWritable: true },
+        { pubkey: this.getStatsPDA(), isSigner: false, isWritable: true },
       ],
       data: this.claimFundsInstructionData(),
     };
@@ -210,7 +403,9 @@ class PresaleClient {
       throw new Error('Pool not found');
     }
 
-    const data = accountInfo.data;
+    // Older, shorter pools read their missing fields as zero, as the program reads them
+    const missing = Math.max(0, POOL_INFO_LENGTH - accountInfo.data.length);
+    const data = Buffer.concat([accountInfo.data, Buffer.alloc(missing)]);
     let offset = 8; 
 
     const owner = new PublicKey(data.slice(offset, offset + 32));
@@ -226,18 +421,94 @@ class PresaleClient {
     const isClaimed = data[offset] === 1;
     offset += 1;
     const depositorCount = data.readUInt32LE(offset);
+    // Skip depositor_count, bump, token_mint, total_tokens, merkle_root, is_finalized
+    offset += 4 + 1 + 32 + 8 + 32 + 1;
+    const softCap = Number(data.readBigUInt64LE(offset));
+    offset += 8;
+    const hardCap = Number(data.readBigUInt64LE(offset));
+    offset += 8 + 8;
+    const attestationAuthority = new PublicKey(data.slice(offset, offset + 32));
+    const timeBasis = data[POOL_TIME_BASIS_OFFSET];
+
+    // Slot-timed pools expire at a slot; estimate its wall-clock time
+    let expiryDate = new Date(expiryTimestamp * 1000);
+    let isExpired = Date.now() / 1000 > expiryTimestamp;
+    if (timeBasis === TimeBasis.SLOT) {
+      const slot = await this.connection.getSlot();
+      expiryDate = new Date(Date.now() + (expiryTimestamp - slot) * ESTIMATED_SLOT_MS);
+      isExpired = slot > expiryTimestamp;
+    }
 
     return {
       address: poolAddress,
       owner: owner.toString(),
+      timeBasis,
       expiryTimestamp,
-      expiryDate: new Date(expiryTimestamp * 1000),
+      expiryDate,
       totalRaised: totalRaised / LAMPORTS_PER_SOL,
       minDeposit: minDeposit / LAMPORTS_PER_SOL,
       maxDeposit: maxDeposit / LAMPORTS_PER_SOL,
       isClaimed,
       depositorCount,
-      isExpired: Date.now() / 1000 > expiryTimestamp,
+      softCap: softCap / LAMPORTS_PER_SOL,
+      hardCap: hardCap / LAMPORTS_PER_SOL,
+      attestationAuthority: attestationAuthority.equals(PublicKey.default) ? null : attestationAuthority.toString(),
+      isExpired,
+    };
+  }
+
+  /**
+   * Get a pool's name, symbol, URI and links, or null if the owner never set them
+   */
+  async getPoolMetadata(poolAddress) {
+    const pool = new PublicKey(poolAddress);
+    const accountInfo = await this.connection.getAccountInfo(this.getPoolMetadataPDA(pool));
+
+    if (!accountInfo) {
+      return null;
+    }
+
+    const data = accountInfo.data;
+    // Skip the discriminator and pool key
+    let offset = 8 + 32;
+    const readString = () => {
+      const length = data.readUInt32LE(offset);
+      offset += 4;
+      const value = data.slice(offset, offset + length).toString('utf8');
+      offset += length;
+      return value;
+    };
+
+    const name = readString();
+    const symbol = readString();
+    const uri = readString();
+    const linkCount = data.readUInt32LE(offset);
+    offset += 4;
+    const links = Array.from({ length: linkCount }, readString);
+
+    return { name, symbol, uri, links };
+  }
+
+  /**
+   * Get program-wide totals across every pool, or null before the first pool
+   */
+  async getLaunchpadStats() {
+    const accountInfo = await this.connection.getAccountInfo(this.getStatsPDA());
+
+    if (!accountInfo) {
+      return null;
+    }
+
+    const data = accountInfo.data;
+    // Skip the discriminator
+    const readU64 = (index) => Number(data.readBigUInt64LE(8 + 8 * index));
+
+    return {
+      totalPools: readU64(0),
+      activePools: readU64(1),
+      totalRaised: readU64(2) / LAMPORTS_PER_SOL,
+      totalClaimed: readU64(3) / LAMPORTS_PER_SOL,
+      uniqueDepositors: readU64(4),
     };
   }
 
@@ -249,8 +520,10 @@ class PresaleClient {
     const depositorRecords = await this.connection.getProgramAccounts(
       this.programId,
       {
+        // Matched by discriminator rather than size, so records from before
+        // the versioned layout are included
         filters: [
-          { dataSize: 88 },
+          { memcmp: { offset: 0, bytes: Buffer.from(DEPOSITOR_RECORD_DISCRIMINATOR).toString('base64'), encoding: 'base64' } },
           { memcmp: { offset: 40, bytes: pool.toBase58() } },
         ],
       }
@@ -0,0 +1,1398 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, Subcommand, ValueEnum};
+use presale_client::{
+    attestation,
+    instruction::{self, GateAccounts},
+    lottery,
+    merkle::{self, MerkleTree},
+    oracle::{MockPrice, MICRO_USD},
+    pda,
+    rpc::{
+        fetch_account, fetch_depositor_info, fetch_depositor_records, fetch_launchpad_stats, fetch_pool,
+        fetch_pool_audit, fetch_pool_info, fetch_pool_metadata, fetch_pool_status,
+    },
+    state::{
+        ClaimBitmap, CurveKind, DepositorRecord, GateKind, LotteryDraw, OracleKind, Pool, ReleaseProposal, TimeBasis,
+    },
+    PresaleError,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    native_token::{lamports_to_sol, sol_to_lamports},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    system_program,
+    transaction::Transaction,
+};
+
+/// Migration instructions sent per transaction, well inside the size limit.
+const MIGRATIONS_PER_TX: usize = 8;
+
+#[derive(Parser)]
+#[command(name = "presale", version, about = "Operate presale_program pools")]
+struct Cli {
+    /// RPC URL or moniker: mainnet-beta, devnet, testnet, localhost (or m, d, t, l)
+    #[arg(short = 'u', long, global = true, default_value = "devnet")]
+    url: String,
+
+    /// Keypair that signs and pays for transactions
+    #[arg(short = 'k', long, global = true, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the pool owned by the keypair
+    CreatePool {
+        /// Unix timestamp (slot with --slots) at which deposits close
+        #[arg(long, required_unless_present = "duration", conflicts_with = "duration")]
+        expiry: Option<i64>,
+        /// Seconds from now at which deposits close
+        #[arg(long)]
+        duration: Option<i64>,
+        /// Measure the pool's deadlines in slots instead of unix time
+        #[arg(long)]
+        slots: bool,
+        /// Minimum deposit in SOL
+        #[arg(long)]
+        min: f64,
+        /// Maximum deposit in SOL
+        #[arg(long)]
+        max: f64,
+        /// Raise in SOL below which depositors are refunded (0 for none)
+        #[arg(long, default_value_t = 0.0)]
+        soft_cap: f64,
+        /// Maximum total raise in SOL (0 for unlimited)
+        #[arg(long, default_value_t = 0.0)]
+        hard_cap: f64,
+    },
+    /// Set the display name, symbol, URI and links of the keypair's pool
+    SetMetadata {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        symbol: String,
+        /// Description or image URI
+        #[arg(long, default_value = "")]
+        uri: String,
+        /// Project link; repeat for several
+        #[arg(long = "link")]
+        links: Vec<String>,
+    },
+    /// Require deposits into the keypair's pool to carry a signed attestation
+    SetAttestationAuthority {
+        /// Authority whose ed25519 signatures admit depositors; omit to turn gating off
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+    /// Sign an attestation for a depositor with the keypair (attestation authority only)
+    Attest {
+        #[arg(long)]
+        pool: Pubkey,
+        #[arg(long)]
+        depositor: Pubkey,
+        /// Unix timestamp after which the attestation is rejected
+        #[arg(long, required_unless_present = "duration", conflicts_with = "duration")]
+        expiry: Option<i64>,
+        /// Seconds from now after which the attestation is rejected
+        #[arg(long)]
+        duration: Option<i64>,
+        /// Maximum total deposit in SOL
+        #[arg(long)]
+        max: f64,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Build a whitelist tree from a file with one wallet address per line
+    Whitelist {
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Set the anti-bot rules of the keypair's pool (before the first deposit)
+    ConfigureAntiBot {
+        /// Whitelist file from `presale whitelist`; only its wallets may deposit during the window
+        #[arg(long, requires = "whitelist_duration")]
+        whitelist: Option<PathBuf>,
+        /// Seconds from now during which deposits are whitelist-only
+        #[arg(long)]
+        whitelist_duration: Option<i64>,
+        /// Slots a wallet must wait between its deposits, at most a day's worth
+        #[arg(long, default_value_t = 0)]
+        min_slot_gap: u64,
+        /// Reject deposits made through other programs
+        #[arg(long)]
+        reject_cpi: bool,
+    },
+    /// Require depositors in the keypair's pool to hold a token or a collection NFT (before the first deposit)
+    ConfigureGate {
+        /// Holding to require; `none` removes the gate
+        #[arg(long, value_enum)]
+        kind: GateShape,
+        /// SPL mint for token gates, collection mint for NFT gates
+        #[arg(long, required_if_eq_any = [("kind", "token"), ("kind", "nft")])]
+        mint: Option<Pubkey>,
+        /// Minimum balance in base units, for token gates
+        #[arg(long, default_value_t = 1)]
+        min_amount: u64,
+    },
+    /// Bound deposits and caps of the keypair's pool in US dollars at the oracle's SOL price (before the first deposit)
+    ConfigureUsdLimits {
+        /// Price account format; `none` removes the USD limits
+        #[arg(long, value_enum)]
+        kind: OracleShape,
+        /// SOL/USD price account, a Pyth price update or an account from `presale mock-price`
+        #[arg(long, required_if_eq_any = [("kind", "mock"), ("kind", "pyth")])]
+        oracle: Option<Pubkey>,
+        /// Oldest price, in seconds, that deposits accept
+        #[arg(long, default_value_t = 60)]
+        max_price_age: i64,
+        /// Minimum deposit in USD
+        #[arg(long, default_value_t = 0.0)]
+        min: f64,
+        /// Maximum deposit in USD; 0 means no limit
+        #[arg(long, default_value_t = 0.0)]
+        max: f64,
+        /// Raise in USD the pool needs to succeed, on top of its SOL soft cap
+        #[arg(long, default_value_t = 0.0)]
+        soft_cap: f64,
+        /// Raise in USD that fills the pool; 0 means no cap
+        #[arg(long, default_value_t = 0.0)]
+        hard_cap: f64,
+    },
+    /// Write a mock SOL/USD price account for `solana-test-validator --account`
+    MockPrice {
+        /// Price in USD per SOL
+        #[arg(long)]
+        price: f64,
+        /// Unix time the price was published; defaults to now
+        #[arg(long)]
+        publish_time: Option<i64>,
+        /// Account address; defaults to a fresh one
+        #[arg(long)]
+        address: Option<Pubkey>,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Turn the keypair's pool into a lottery (before the first deposit)
+    ConfigureLottery {
+        /// Number of winning tickets
+        #[arg(long)]
+        winners: u32,
+        /// Where to save the generated secret; keep it until the reveal
+        #[arg(long)]
+        secret_file: PathBuf,
+    },
+    /// Size allocations in the keypair's pool by stake (before the first deposit)
+    ConfigureTiers {
+        /// Token depositors stake for their tier
+        #[arg(long)]
+        mint: Pubkey,
+        /// MIN_STAKE:MAX_SOL, the stake in base units and the allocation in SOL;
+        /// repeat in ascending order, omit to turn tiers off
+        #[arg(long = "tier", value_parser = parse_tier)]
+        tiers: Vec<(u64, f64)>,
+    },
+    /// Weigh early deposits in the keypair's pool more in the token split (before the first deposit)
+    ConfigureBonus {
+        /// SECONDS:BPS, deposits in the next SECONDS count BPS basis points extra;
+        /// repeat with growing SECONDS and shrinking BPS, omit to turn the bonus off
+        #[arg(long = "step", value_parser = parse_bonus_step)]
+        steps: Vec<(i64, u16)>,
+    },
+    /// Let depositors vote on releases of the keypair's pool (before the first deposit)
+    ConfigureGovernance {
+        /// Share of raised SOL that must vote, in basis points
+        #[arg(long, default_value_t = 0)]
+        quorum_bps: u16,
+        /// Share of cast votes that must approve, in basis points
+        #[arg(long, default_value_t = 5000)]
+        approval_bps: u16,
+        /// Seconds each vote stays open; 0 turns governance off
+        #[arg(long)]
+        voting_period: i64,
+    },
+    /// Ask depositors to release part of the keypair's expired, governed pool
+    ProposeRelease {
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+    },
+    /// Vote on a pool's open release proposal with your deposit as weight
+    Vote {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Approve the release; omit to vote against it
+        #[arg(long)]
+        approve: bool,
+    },
+    /// Settle a release proposal whose voting has ended (anyone can run this)
+    ExecuteProposal {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Defaults to the pool's latest proposal
+        #[arg(long)]
+        id: Option<u32>,
+    },
+    /// Stream the raise of the keypair's pool to it over time after expiry (before the first deposit)
+    ConfigureStream {
+        /// Seconds over which the raise accrues; 0 turns streaming off
+        #[arg(long)]
+        duration: i64,
+        /// Share of raised SOL whose votes halt the stream, in basis points
+        #[arg(long, default_value_t = 5000)]
+        halt_bps: u16,
+    },
+    /// Vote to halt a pool's stream with your deposit as weight; halted streams refund the rest
+    HaltStream {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// Sell tokens from the keypair's pool along a bonding curve (before the first deposit)
+    ConfigureCurve {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Token account funding the curve
+        #[arg(long)]
+        token_account: Pubkey,
+        #[arg(long, value_enum)]
+        kind: CurveShape,
+        /// Price of the first token in SOL per whole token
+        #[arg(long)]
+        start_price: f64,
+        /// Price of the last token in SOL per whole token
+        #[arg(long)]
+        end_price: f64,
+        /// Tokens for sale in base units
+        #[arg(long)]
+        supply: u64,
+        /// Let buyers sell tokens back along the curve until expiry
+        #[arg(long)]
+        sell_back: bool,
+    },
+    /// Buy tokens from a curve pool
+    Buy {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Token account receiving the tokens
+        #[arg(long)]
+        token_account: Pubkey,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+        /// Accepted price increase over the quote, in basis points
+        #[arg(long, default_value_t = 100)]
+        slippage_bps: u16,
+    },
+    /// Sell tokens back to a curve pool that allows it
+    Sell {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Token account the tokens are paid from
+        #[arg(long)]
+        token_account: Pubkey,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+        /// Accepted price decrease under the quote, in basis points
+        #[arg(long, default_value_t = 100)]
+        slippage_bps: u16,
+    },
+    /// Return the unsold tokens of the keypair's expired curve pool
+    WithdrawUnsold {
+        /// Token account receiving the tokens
+        #[arg(long)]
+        token_account: Pubkey,
+    },
+    /// Stake tokens toward allocation tiers
+    Stake {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Token account the stake is paid from
+        #[arg(long)]
+        token_account: Pubkey,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Withdraw staked tokens once no active tiered pool locks them
+    Unstake {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Token account receiving the tokens
+        #[arg(long)]
+        token_account: Pubkey,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Unlock the stake behind a position in an expired tiered pool (anyone can run this)
+    ReleaseStake {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        depositor: Option<Pubkey>,
+    },
+    /// Reveal the lottery secret of the keypair's expired pool
+    RevealLottery {
+        #[arg(long)]
+        secret_file: PathBuf,
+    },
+    /// Draw a revealed lottery's winners (anyone can run this)
+    Draw {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Picks per transaction
+        #[arg(long, default_value_t = 200)]
+        batch: u32,
+    },
+    /// Replay a lottery draw from on-chain data and compare it with the stored result
+    VerifyDraw {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Revealed secret (hex) to also check the commitment and seed
+        #[arg(long)]
+        secret: Option<String>,
+    },
+    /// Deposit SOL into a pool
+    Deposit {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+        /// Attestation file from `presale attest`, for gated pools
+        #[arg(long)]
+        attestation: Option<PathBuf>,
+        /// Whitelist file from `presale whitelist`, during the whitelist window
+        #[arg(long)]
+        whitelist: Option<PathBuf>,
+        /// Wallet that owns the deposit; the keypair only pays. Not for tiered
+        /// pools or pools with a slot gap, where the beneficiary signs
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+        /// Beneficiary's token account holding the gate token or NFT, for gated pools
+        #[arg(long)]
+        gate_token_account: Option<Pubkey>,
+    },
+    /// Move part or all of your position to another wallet
+    Transfer {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Wallet receiving the position
+        #[arg(long)]
+        to: Pubkey,
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+        /// Recipient's attestation file, for gated pools
+        #[arg(long)]
+        attestation: Option<PathBuf>,
+        /// Whitelist file, during the whitelist window
+        #[arg(long)]
+        whitelist: Option<PathBuf>,
+        /// Recipient's token account holding the gate token or NFT, for gated pools
+        #[arg(long)]
+        gate_token_account: Option<Pubkey>,
+    },
+    /// Claim the raised SOL of an expired pool, or what its stream has accrued (owner only)
+    Claim {
+        /// Defaults to the pool owned by the keypair
+        #[arg(long)]
+        pool: Option<Pubkey>,
+    },
+    /// Send a finalized pool's tokens to its depositors' associated token accounts (owner only)
+    Distribute {
+        /// Defaults to the pool owned by the keypair
+        #[arg(long)]
+        pool: Option<Pubkey>,
+        /// Depositors per transaction
+        #[arg(long, default_value_t = 10)]
+        batch: usize,
+    },
+    /// Withdraw your deposit from a failed pool, a losing lottery ticket or a rejected release
+    Refund {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// Stop counting a failed pool as active, e.g. one nobody deposited into
+    Settle {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// Show a pool's configuration and current status
+    Info {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// Check a pool's bookkeeping against its vault; exits with an error when a check fails
+    Audit {
+        #[arg(long)]
+        pool: Pubkey,
+        /// On failure, also send the audit so an AuditFailed event is logged on-chain
+        #[arg(long)]
+        alert: bool,
+    },
+    /// Show program-wide totals across every pool
+    Stats,
+    /// Show a depositor's position in a pool
+    Position {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        depositor: Option<Pubkey>,
+    },
+    /// Upgrade a pool and its depositor records to the current account layout,
+    /// creating the stats account if the program predates it
+    Migrate {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// List a pool's depositors
+    Depositors {
+        #[arg(long)]
+        pool: Pubkey,
+    },
+    /// Export a pool's depositors as CSV or JSON
+    Export {
+        #[arg(long)]
+        pool: Pubkey,
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CurveShape {
+    Linear,
+    Exponential,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GateShape {
+    None,
+    Token,
+    Nft,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OracleShape {
+    None,
+    Mock,
+    Pyth,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// A whitelist tree with the proof of every wallet.
+#[derive(Serialize, Deserialize)]
+struct WhitelistFile {
+    root: String,
+    wallets: Vec<WhitelistEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WhitelistEntry {
+    wallet: String,
+    proof: Vec<String>,
+}
+
+/// A signed attestation as exchanged between the authority and a depositor.
+#[derive(Serialize, Deserialize)]
+struct AttestationFile {
+    pool: String,
+    depositor: String,
+    authority: String,
+    expiry: i64,
+    max_allocation: u64,
+    signature: String,
+}
+
+/// An account as `solana-test-validator --account` loads it.
+#[derive(Serialize)]
+struct AccountFile {
+    pubkey: String,
+    account: AccountFileData,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountFileData {
+    lamports: u64,
+    /// Data and its encoding
+    data: (String, &'static str),
+    owner: String,
+    executable: bool,
+    rent_epoch: u64,
+    space: usize,
+}
+
+#[derive(Serialize)]
+struct DepositorRow {
+    index: u32,
+    record: String,
+    depositor: String,
+    amount_lamports: u64,
+    amount_sol: f64,
+    last_deposit: i64,
+    tokens_claimed: u64,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let client = RpcClient::new_with_commitment(resolve_url(&cli.url), CommitmentConfig::confirmed());
+
+    match cli.command {
+        Command::CreatePool { expiry, duration, slots, min, max, soft_cap, hard_cap } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let time_basis = if slots { TimeBasis::Slot } else { TimeBasis::UnixTimestamp };
+            let expiry = resolve_expiry(&client, time_basis, expiry, duration)?;
+            let ix = instruction::create_pool(
+                &signer.pubkey(),
+                expiry,
+                sol_to_lamports(min),
+                sol_to_lamports(max),
+                sol_to_lamports(soft_cap),
+                sol_to_lamports(hard_cap),
+                time_basis,
+            );
+            let signature = send(&client, &[ix], &signer)?;
+            println!("Pool:      {}", pda::find_pool_address(&signer.pubkey()).0);
+            println!("Signature: {signature}");
+        }
+        Command::SetMetadata { name, symbol, uri, links } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let ix = instruction::set_pool_metadata(&pool, &signer.pubkey(), name, symbol, uri, links);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::SetAttestationAuthority { authority } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let ix = instruction::set_attestation_authority(&pool, &signer.pubkey(), &authority.unwrap_or_default());
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Attest { pool, depositor, expiry, duration, max, output } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let expiry = resolve_expiry(&client, TimeBasis::UnixTimestamp, expiry, duration)?;
+            let max_allocation = sol_to_lamports(max);
+            let message = attestation::message(&pool, &depositor, expiry, max_allocation);
+            let file = AttestationFile {
+                pool: pool.to_string(),
+                depositor: depositor.to_string(),
+                authority: signer.pubkey().to_string(),
+                expiry,
+                max_allocation,
+                signature: signer.sign_message(&message).to_string(),
+            };
+            let mut buffer = serde_json::to_vec_pretty(&file)?;
+            buffer.push(b'\n');
+            match output {
+                Some(path) => fs::write(&path, buffer).with_context(|| format!("writing {}", path.display()))?,
+                None => io::stdout().write_all(&buffer)?,
+            }
+        }
+        Command::Whitelist { input, output } => {
+            let contents = fs::read_to_string(&input).with_context(|| format!("reading {}", input.display()))?;
+            let mut wallets = Vec::new();
+            for line in contents.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                let wallet = Pubkey::from_str(line).with_context(|| format!("invalid wallet {line}"))?;
+                if !wallets.contains(&wallet) {
+                    wallets.push(wallet);
+                }
+            }
+            let tree = MerkleTree::from_whitelist(&wallets);
+            let file = WhitelistFile {
+                root: merkle::to_hex(&tree.root()),
+                wallets: wallets
+                    .iter()
+                    .enumerate()
+                    .map(|(position, wallet)| WhitelistEntry {
+                        wallet: wallet.to_string(),
+                        proof: tree.proof(position).iter().map(|node| merkle::to_hex(node)).collect(),
+                    })
+                    .collect(),
+            };
+            fs::write(&output, serde_json::to_vec_pretty(&file)?)
+                .with_context(|| format!("writing {}", output.display()))?;
+            println!("Root:    {}", file.root);
+            println!("Wallets: {}", wallets.len());
+        }
+        Command::ConfigureAntiBot { whitelist, whitelist_duration, min_slot_gap, reject_cpi } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let (root, end) = match (whitelist, whitelist_duration) {
+                (Some(path), Some(duration)) => {
+                    let time_basis = fetch_pool(&client, &pool).context("fetching pool")?.time_basis;
+                    (read_whitelist(&path)?.0, deadline(&client, time_basis, duration)?)
+                }
+                _ => ([0; 32], 0),
+            };
+            let ix = instruction::configure_anti_bot(&pool, &signer.pubkey(), root, end, min_slot_gap, reject_cpi);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ConfigureGate { kind, mint, min_amount } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let kind = match kind {
+                GateShape::None => GateKind::None,
+                GateShape::Token => GateKind::TokenHolder,
+                GateShape::Nft => GateKind::NftCollection,
+            };
+            let ix = instruction::configure_gate(&pool, &signer.pubkey(), kind, &mint.unwrap_or_default(), min_amount);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ConfigureUsdLimits { kind, oracle, max_price_age, min, max, soft_cap, hard_cap } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let kind = match kind {
+                OracleShape::None => OracleKind::None,
+                OracleShape::Mock => OracleKind::Mock,
+                OracleShape::Pyth => OracleKind::Pyth,
+            };
+            let ix = instruction::configure_usd_limits(
+                &pool,
+                &signer.pubkey(),
+                kind,
+                oracle.as_ref(),
+                max_price_age,
+                dollars_to_micro_usd(min),
+                dollars_to_micro_usd(max),
+                dollars_to_micro_usd(soft_cap),
+                dollars_to_micro_usd(hard_cap),
+            );
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::MockPrice { price, publish_time, address, output } => {
+            // Eight decimals, as Pyth publishes SOL/USD
+            let mock = MockPrice {
+                price: (price * 1e8).round() as i64,
+                exponent: -8,
+                publish_time: match publish_time {
+                    Some(publish_time) => publish_time,
+                    None => now()?,
+                },
+            };
+            let address = address.unwrap_or_else(|| Keypair::new().pubkey());
+            let file = AccountFile {
+                pubkey: address.to_string(),
+                account: AccountFileData {
+                    lamports: Rent::default().minimum_balance(MockPrice::LEN),
+                    data: (STANDARD.encode(mock.to_bytes()), "base64"),
+                    owner: system_program::ID.to_string(),
+                    executable: false,
+                    rent_epoch: 0,
+                    space: MockPrice::LEN,
+                },
+            };
+            fs::write(&output, serde_json::to_vec_pretty(&file)?)
+                .with_context(|| format!("writing {}", output.display()))?;
+            println!("Oracle: {address}");
+            println!("Load it with: solana-test-validator --account {address} {}", output.display());
+        }
+        Command::ConfigureLottery { winners, secret_file } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            // A fresh keypair's 32-byte seed comes straight from the OS RNG
+            let mut secret = [0u8; 32];
+            secret.copy_from_slice(&Keypair::new().to_bytes()[..32]);
+            fs::write(&secret_file, merkle::to_hex(&secret))
+                .with_context(|| format!("writing {}", secret_file.display()))?;
+            let ix = instruction::configure_lottery(&pool, &signer.pubkey(), winners, lottery::commitment(&secret));
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            println!("Secret saved to {}", secret_file.display());
+        }
+        Command::ConfigureTiers { mint, tiers } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let (min_stakes, max_allocations) =
+                tiers.into_iter().map(|(stake, max)| (stake, sol_to_lamports(max))).unzip();
+            let ix = instruction::configure_tiers(&pool, &signer.pubkey(), &mint, min_stakes, max_allocations);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ConfigureBonus { steps } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let time_basis = fetch_pool(&client, &pool).context("fetching pool")?.time_basis;
+            let mut ends = Vec::with_capacity(steps.len());
+            let mut bonus_bps = Vec::with_capacity(steps.len());
+            for (seconds, bps) in steps {
+                ends.push(deadline(&client, time_basis, seconds)?);
+                bonus_bps.push(bps);
+            }
+            let ix = instruction::configure_bonus(&pool, &signer.pubkey(), ends, bonus_bps);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ConfigureGovernance { quorum_bps, approval_bps, voting_period } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let time_basis = fetch_pool(&client, &pool).context("fetching pool")?.time_basis;
+            let ix = instruction::configure_governance(
+                &pool,
+                &signer.pubkey(),
+                quorum_bps,
+                approval_bps,
+                time_basis.from_seconds(voting_period),
+            );
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ProposeRelease { amount } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let id = fetch_pool(&client, &pool).context("fetching pool")?.proposal_count;
+            let ix = instruction::open_release_proposal(&pool, &signer.pubkey(), id, sol_to_lamports(amount));
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            println!("Proposal:  {} ({})", id, pda::find_proposal_address(&pool, id).0);
+        }
+        Command::Vote { pool, approve } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            if !state.proposal_open {
+                return Err(anyhow!("{pool} has no open release proposal"));
+            }
+            let ix = instruction::cast_vote(&pool, &signer.pubkey(), state.proposal_count - 1, approve);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ExecuteProposal { pool, id } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            let id = match id {
+                Some(id) => id,
+                None => state.proposal_count.checked_sub(1).ok_or_else(|| anyhow!("{pool} has no proposals"))?,
+            };
+            let ix = instruction::execute_proposal(&pool, &state.owner, id);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            let proposal: ReleaseProposal = fetch_account(&client, &pda::find_proposal_address(&pool, id).0)
+                .context("fetching proposal")?;
+            println!("Approved:  {}", proposal.approved);
+        }
+        Command::ConfigureStream { duration, halt_bps } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let time_basis = fetch_pool(&client, &pool).context("fetching pool")?.time_basis;
+            let duration = time_basis.from_seconds(duration);
+            let ix = instruction::configure_stream(&pool, &signer.pubkey(), duration, halt_bps);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::HaltStream { pool } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let owner = fetch_pool(&client, &pool).context("fetching pool")?.owner;
+            let ix = instruction::vote_halt(&pool, &owner, &signer.pubkey());
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            println!("Halt votes: {} SOL (halts at {})", lamports_to_sol(state.halt_votes), percent(state.halt_bps));
+            println!("Halted:     {}", state.release_rejected);
+        }
+        Command::ConfigureCurve { mint, token_account, kind, start_price, end_price, supply, sell_back } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let kind = match kind {
+                CurveShape::Linear => CurveKind::Linear,
+                CurveShape::Exponential => CurveKind::Exponential,
+            };
+            let ix = instruction::configure_curve(
+                &pool,
+                &signer.pubkey(),
+                &mint,
+                &token_account,
+                kind,
+                sol_to_lamports(start_price),
+                sol_to_lamports(end_price),
+                supply,
+                sell_back,
+            );
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Buy { pool, token_account, amount, slippage_bps } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            let cost = state
+                .curve_buy_cost(amount)
+                .ok_or_else(|| anyhow!("{pool} cannot sell {amount} more tokens"))?;
+            let max_cost = cost + (cost as u128 * slippage_bps as u128 / 10_000) as u64;
+            let ix = instruction::buy_tokens(&pool, &signer.pubkey(), &token_account, amount, max_cost);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            println!("Quoted:    {} SOL", lamports_to_sol(cost));
+        }
+        Command::Sell { pool, token_account, amount, slippage_bps } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            let proceeds = state
+                .curve_sell_proceeds(amount)
+                .ok_or_else(|| anyhow!("{pool} cannot buy back {amount} tokens"))?;
+            let min_proceeds = proceeds - (proceeds as u128 * slippage_bps as u128 / 10_000) as u64;
+            let ix = instruction::sell_tokens(&pool, &signer.pubkey(), &token_account, amount, min_proceeds);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+            println!("Quoted:    {} SOL", lamports_to_sol(proceeds));
+        }
+        Command::WithdrawUnsold { token_account } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let ix = instruction::withdraw_unsold_tokens(&pool, &signer.pubkey(), &token_account);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Stake { mint, token_account, amount } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let ix = instruction::stake(&mint, &signer.pubkey(), &token_account, amount);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Unstake { mint, token_account, amount } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let ix = instruction::unstake(&mint, &signer.pubkey(), &token_account, amount);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::ReleaseStake { pool, depositor } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let depositor = depositor.unwrap_or_else(|| signer.pubkey());
+            let tier_mint = fetch_pool(&client, &pool).context("fetching pool")?.tier_mint;
+            let ix = instruction::release_stake(&pool, &tier_mint, &depositor);
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::RevealLottery { secret_file } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pda::find_pool_address(&signer.pubkey()).0;
+            let contents =
+                fs::read_to_string(&secret_file).with_context(|| format!("reading {}", secret_file.display()))?;
+            let secret = merkle::from_hex(contents.trim()).ok_or_else(|| anyhow!("invalid secret"))?;
+            // The reveal stores the seed slot, which older pool layouts have no room for
+            let mut ixs = Vec::new();
+            if fetch_pool(&client, &pool).context("fetching pool")?.version < Pool::VERSION {
+                ixs.push(instruction::migrate_pool(&pool, &signer.pubkey()));
+            }
+            ixs.push(instruction::reveal_lottery(&pool, &signer.pubkey(), secret));
+            println!("Signature: {}", send(&client, &ixs, &signer)?);
+        }
+        Command::Draw { pool, batch } => {
+            let signer = load_keypair(&cli.keypair)?;
+            loop {
+                let state = fetch_pool(&client, &pool).context("fetching pool")?;
+                if state.lottery_drawn {
+                    break;
+                }
+                // The seed slot's hash only exists once the slot has passed
+                let slot = client.get_slot().context("fetching current slot")?;
+                if !state.lottery_seeded() && slot <= state.lottery_seed_slot {
+                    std::thread::sleep(std::time::Duration::from_millis(TimeBasis::ESTIMATED_SLOT_MS as u64));
+                    continue;
+                }
+                let ix = instruction::draw_winners(&pool, batch);
+                println!("Signature: {}", send(&client, &[ix], &signer)?);
+            }
+            println!("Lottery drawn");
+        }
+        Command::VerifyDraw { pool, secret } => {
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            if !state.lottery_drawn {
+                return Err(anyhow!("lottery winners of {pool} have not been drawn yet"));
+            }
+            if let Some(secret) = secret {
+                let secret = merkle::from_hex(&secret).ok_or_else(|| anyhow!("invalid secret"))?;
+                let mut seed = lottery::seed(&secret, &state.lottery_entropy);
+                if state.lottery_slot_hash != [0; 32] {
+                    seed = lottery::seeded(&seed, &state.lottery_slot_hash);
+                }
+                if lottery::commitment(&secret) != state.lottery_commitment || seed != state.lottery_seed {
+                    return Err(anyhow!("secret does not match the pool's commitment and seed"));
+                }
+                println!("Commitment and seed match the revealed secret");
+            }
+            let draw: LotteryDraw = fetch_account(&client, &pda::find_lottery_draw_address(&pool).0)
+                .context("fetching lottery draw")?;
+            let replayed = lottery::replay_draw(&state.lottery_seed, state.depositor_count, state.lottery_winners);
+            if (0..state.depositor_count).any(|index| draw.is_winner(index) != replayed[index as usize]) {
+                return Err(anyhow!("stored winners differ from the replayed draw"));
+            }
+            let winners: Vec<u32> = (0..state.depositor_count).filter(|&index| replayed[index as usize]).collect();
+            println!("Draw verified: {} winner(s) of {} ticket(s)", winners.len(), state.depositor_count);
+            println!("Winning indexes: {winners:?}");
+        }
+        Command::Deposit { pool, amount, attestation, whitelist, beneficiary, gate_token_account } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let beneficiary = beneficiary.unwrap_or_else(|| signer.pubkey());
+            let mut ixs = Vec::new();
+            if let Some(path) = attestation {
+                ixs.push(attestation_instruction(&path, &pool, &beneficiary)?);
+            }
+            let proof = whitelist_proof(whitelist.as_deref(), &beneficiary)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            if (state.is_tiered() || state.min_slot_gap > 0) && beneficiary != signer.pubkey() {
+                return Err(anyhow!("{pool} needs the beneficiary's signature; deposit with its keypair"));
+            }
+            let gate = gate_accounts(&client, &state, gate_token_account)?;
+            ixs.push(instruction::deposit_sol_for(
+                &pool,
+                &signer.pubkey(),
+                &beneficiary,
+                beneficiary == signer.pubkey(),
+                sol_to_lamports(amount),
+                proof,
+                state.is_tiered().then_some(&state.tier_mint),
+                gate.as_ref(),
+                state.has_usd_limits().then_some(&state.price_oracle),
+            ));
+            println!("Signature: {}", send(&client, &ixs, &signer)?);
+        }
+        Command::Transfer { pool, to, amount, attestation, whitelist, gate_token_account } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let mut ixs = Vec::new();
+            if let Some(path) = attestation {
+                ixs.push(attestation_instruction(&path, &pool, &to)?);
+            }
+            let proof = whitelist_proof(whitelist.as_deref(), &to)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            let gate = gate_accounts(&client, &state, gate_token_account)?;
+            ixs.push(instruction::transfer_position(
+                &pool,
+                &signer.pubkey(),
+                &to,
+                sol_to_lamports(amount),
+                proof,
+                gate.as_ref(),
+            ));
+            println!("Signature: {}", send(&client, &ixs, &signer)?);
+        }
+        Command::Distribute { pool, batch } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pool.unwrap_or_else(|| pda::find_pool_address(&signer.pubkey()).0);
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            if !state.is_finalized {
+                return Err(anyhow!("{pool} has not been finalized"));
+            }
+            let bitmap: ClaimBitmap = fetch_account(&client, &pda::find_claim_bitmap_address(&pool).0)
+                .context("fetching claim bitmap")?;
+            let draw: Option<LotteryDraw> = if state.is_lottery() {
+                Some(fetch_account(&client, &pda::find_lottery_draw_address(&pool).0).context("fetching lottery draw")?)
+            } else {
+                None
+            };
+            // Only records the program would pay; resent batches skip the rest on-chain anyway
+            let pending: Vec<Pubkey> = fetch_depositor_records(&client, &pool)
+                .context("fetching depositor records")?
+                .into_iter()
+                .map(|(_, record)| record)
+                .filter(|record| {
+                    !bitmap.is_claimed(record.index)
+                        && !record.is_refunded
+                        && draw.as_ref().is_none_or(|draw| draw.is_winner(record.index))
+                        && state.entitlement(record.allocation_weight()) > 0
+                })
+                .map(|record| record.depositor)
+                .collect();
+
+            let mut recipients = Vec::new();
+            for depositors in pending.chunks(100) {
+                let token_accounts: Vec<Pubkey> = depositors
+                    .iter()
+                    .map(|depositor| pda::find_associated_token_address(depositor, &state.token_mint).0)
+                    .collect();
+                let accounts = client.get_multiple_accounts(&token_accounts).context("fetching token accounts")?;
+                for ((depositor, token_account), account) in depositors.iter().zip(token_accounts).zip(accounts) {
+                    match account {
+                        Some(_) => recipients.push((*depositor, token_account)),
+                        None => eprintln!("Skipping {depositor}: no associated token account for {}", state.token_mint),
+                    }
+                }
+            }
+            for chunk in recipients.chunks(batch.max(1)) {
+                let ix = instruction::distribute_batch(&pool, &signer.pubkey(), state.is_lottery(), chunk);
+                println!("Signature: {}", send(&client, &[ix], &signer)?);
+            }
+            println!("Distributed to {} depositor(s)", recipients.len());
+        }
+        Command::Claim { pool } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let pool = pool.unwrap_or_else(|| pda::find_pool_address(&signer.pubkey()).0);
+            let ix = if fetch_pool(&client, &pool).context("fetching pool")?.is_streaming() {
+                instruction::withdraw_stream(&pool, &signer.pubkey())
+            } else {
+                instruction::claim_funds(&pool, &signer.pubkey())
+            };
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Refund { pool } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            // Governed raises are refunded from the escrow, after a rejection or the governance window
+            let ix = if state.release_rejected || (state.is_governed() && state.soft_cap_reached()) {
+                instruction::refund_escrow(&pool, &signer.pubkey())
+            } else if state.lottery_drawn {
+                instruction::refund_losing_ticket(&pool, &signer.pubkey())
+            } else {
+                instruction::refund(&pool, &signer.pubkey())
+            };
+            println!("Signature: {}", send(&client, &[ix], &signer)?);
+        }
+        Command::Settle { pool } => {
+            let signer = load_keypair(&cli.keypair)?;
+            println!("Signature: {}", send(&client, &[instruction::settle_failed_pool(&pool)], &signer)?);
+        }
+        Command::Info { pool } => {
+            let info = fetch_pool_info(&client, &pool).context("fetching pool info")?;
+            let status = fetch_pool_status(&client, &pool).context("fetching pool status")?;
+            println!("Pool:            {pool}");
+            if let Some(metadata) = fetch_pool_metadata(&client, &pool).context("fetching pool metadata")? {
+                println!("Name:            {} ({})", metadata.name, metadata.symbol);
+                println!("URI:             {}", metadata.uri);
+                for link in &metadata.links {
+                    println!("Link:            {link}");
+                }
+            }
+            println!("Owner:           {}", info.owner);
+            println!("Phase:           {:?}", status.phase);
+            println!("Time basis:      {:?}", info.time_basis);
+            println!("Expiry:          {} ({}s remaining)", info.expiry_timestamp, status.seconds_remaining);
+            println!("Expiry slot:     {} ({} slots remaining)", info.expiry_slot, status.slots_remaining);
+            println!("Total raised:    {} SOL", lamports_to_sol(info.total_raised));
+            println!("Min deposit:     {} SOL", lamports_to_sol(info.min_deposit));
+            println!("Max deposit:     {} SOL", lamports_to_sol(info.max_deposit));
+            println!("Soft cap:        {} SOL ({})", lamports_to_sol(info.soft_cap), percent(status.soft_cap_bps));
+            println!("Hard cap:        {} SOL ({})", lamports_to_sol(info.hard_cap), percent(status.hard_cap_bps));
+            println!("Depositors:      {}", info.depositor_count);
+            println!("Claimed:         {}", info.is_claimed);
+            println!("Owner can claim: {}", status.owner_can_claim);
+            println!("Refunds open:    {}", status.refunds_open);
+            println!("Whitelist only:  {}", status.whitelist_only);
+            let state = fetch_pool(&client, &pool).context("fetching pool")?;
+            if state.is_tiered() {
+                println!("Tier mint:       {}", state.tier_mint);
+                for (min_stake, max_allocation) in state.tiers() {
+                    println!("Tier:            {min_stake} staked -> {} SOL", lamports_to_sol(max_allocation));
+                }
+            }
+            if state.is_gated() {
+                println!("Gate:            {:?} ({})", state.gate_kind, state.gate_mint);
+                if state.gate_kind == GateKind::TokenHolder {
+                    println!("Gate minimum:    {}", state.gate_min_amount);
+                }
+            }
+            if state.has_bonus() {
+                for (end, bps) in state.bonus_steps() {
+                    println!("Bonus:           +{} until {end}", percent(bps));
+                }
+                println!("Weighted total:  {} SOL", lamports_to_sol(state.total_weighted));
+            }
+            if state.is_curve() {
+                let price = state.curve().price(state.curve_sold).unwrap_or_default();
+                println!("Curve:           {:?} ({})", state.curve_kind, state.token_mint);
+                println!(
+                    "Curve price:     {} -> {} SOL/token",
+                    lamports_to_sol(state.curve_start_price),
+                    lamports_to_sol(state.curve_end_price)
+                );
+                println!("Current price:   {} SOL/token", lamports_to_sol(price));
+                println!("Tokens sold:     {} of {}", state.curve_sold, state.curve_supply);
+                println!("Sell-back:       {}", state.curve_sell_back);
+            }
+            if state.is_governed() {
+                println!("Quorum:          {}", percent(state.quorum_bps));
+                println!("Approval:        {}", percent(state.approval_bps));
+                println!("Voting period:   {}", state.voting_period);
+                println!("Proposals:       {} (open: {})", state.proposal_count, state.proposal_open);
+                println!("Proposals until: {}", state.governance_deadline());
+                println!("Released:        {} SOL", lamports_to_sol(state.total_released));
+                println!("Release vetoed:  {}", state.release_rejected);
+            }
+            if state.is_streaming() {
+                let now = deadline(&client, state.time_basis, 0)?;
+                println!("Stream duration: {}", state.stream_duration);
+                println!("Streamed:        {} SOL", lamports_to_sol(state.stream_accrued(now)));
+                println!("Withdrawn:       {} SOL", lamports_to_sol(state.total_released));
+                println!("Withdrawable:    {} SOL", lamports_to_sol(state.stream_withdrawable(now)));
+                println!(
+                    "Halt votes:      {} SOL (halts at {})",
+                    lamports_to_sol(state.halt_votes),
+                    percent(state.halt_bps)
+                );
+                println!("Halted:          {}", state.release_rejected);
+            }
+            if state.has_usd_limits() {
+                println!("Oracle:          {:?} ({})", state.oracle_kind, state.price_oracle);
+                println!("Max price age:   {}s", state.max_price_age);
+                println!("Min deposit:     ${}", micro_usd_to_dollars(state.usd_min_deposit));
+                println!("Max deposit:     ${}", micro_usd_to_dollars(state.usd_max_deposit));
+                println!("Soft cap:        ${}", micro_usd_to_dollars(state.usd_soft_cap));
+                println!("Hard cap:        ${}", micro_usd_to_dollars(state.usd_hard_cap));
+                println!("Raised:          ${}", micro_usd_to_dollars(state.total_raised_usd));
+            }
+        }
+        Command::Audit { pool, alert } => {
+            let report = fetch_pool_audit(&client, &pool).context("auditing pool")?;
+            println!("Vault:           {} SOL", lamports_to_sol(report.vault_lamports));
+            println!("Liabilities:     {} SOL", lamports_to_sol(report.liabilities));
+            println!("Vault solvent:   {}", report.vault_solvent);
+            println!("Claim flag ok:   {}", report.claim_consistent);
+            println!("Counters ok:     {}", report.counters_consistent);
+            if !report.passed() {
+                if alert {
+                    let signer = load_keypair(&cli.keypair)?;
+                    let finalized = fetch_pool(&client, &pool).context("fetching pool")?.is_finalized;
+                    println!("Signature: {}", send(&client, &[instruction::audit_pool(&pool, finalized)], &signer)?);
+                }
+                return Err(anyhow!("{pool} failed its audit"));
+            }
+        }
+        Command::Stats => {
+            let stats = fetch_launchpad_stats(&client).context("fetching launchpad stats")?;
+            println!("Pools:           {} ({} active)", stats.total_pools, stats.active_pools);
+            println!("Total raised:    {} SOL", lamports_to_sol(stats.total_raised));
+            println!("Total claimed:   {} SOL", lamports_to_sol(stats.total_claimed));
+            println!("Depositors:      {}", stats.unique_depositors);
+        }
+        Command::Position { pool, depositor } => {
+            let depositor = match depositor {
+                Some(depositor) => depositor,
+                None => load_keypair(&cli.keypair)?.pubkey(),
+            };
+            let info = fetch_depositor_info(&client, &pool, &depositor).context("fetching depositor info")?;
+            println!("Depositor:       {}", info.depositor);
+            println!("Deposited:       {} SOL ({})", lamports_to_sol(info.amount), percent(info.share_bps));
+            if info.weighted_amount != info.amount {
+                println!("Weighted:        {} SOL", lamports_to_sol(info.weighted_amount));
+            }
+            println!("Entitlement:     {} tokens", info.entitlement);
+            println!("Tokens claimed:  {}", info.tokens_claimed);
+            println!("Refunded:        {}", info.is_refunded);
+            println!("Can claim:       {}", info.can_claim_tokens);
+            println!("Can refund:      {}", info.can_refund);
+            println!("Won lottery:     {}", info.won_lottery);
+        }
+        Command::Migrate { pool } => {
+            let signer = load_keypair(&cli.keypair)?;
+            let mut ixs = Vec::new();
+            // Claims and refunds of pools from before the stats account need it
+            let stats = pda::find_stats_address().0;
+            if client.get_account_with_commitment(&stats, client.commitment())?.value.is_none() {
+                ixs.push(instruction::init_stats(&signer.pubkey()));
+            }
+            if fetch_pool(&client, &pool).context("fetching pool")?.version < Pool::VERSION {
+                ixs.push(instruction::migrate_pool(&pool, &signer.pubkey()));
+            }
+            for (_, record) in fetch_depositor_records(&client, &pool).context("fetching depositor records")? {
+                if record.version < DepositorRecord::VERSION {
+                    ixs.push(instruction::migrate_depositor_record(&pool, &record.depositor, &signer.pubkey()));
+                }
+            }
+            for batch in ixs.chunks(MIGRATIONS_PER_TX) {
+                println!("Signature: {}", send(&client, batch, &signer)?);
+            }
+            println!("{} account(s) migrated", ixs.len());
+        }
+        Command::Depositors { pool } => {
+            let rows = depositor_rows(&client, &pool)?;
+            println!("{:>6}  {:<44}  {:>16}  {:>12}", "INDEX", "DEPOSITOR", "AMOUNT (SOL)", "LAST DEPOSIT");
+            for row in &rows {
+                println!(
+                    "{:>6}  {:<44}  {:>16}  {:>12}",
+                    row.index, row.depositor, row.amount_sol, row.last_deposit
+                );
+            }
+            println!("{} depositor(s)", rows.len());
+        }
+        Command::Export { pool, format, output } => {
+            let rows = depositor_rows(&client, &pool)?;
+            let mut buffer = Vec::new();
+            match format {
+                Format::Csv => {
+                    let mut writer = csv::Writer::from_writer(&mut buffer);
+                    for row in &rows {
+                        writer.serialize(row)?;
+                    }
+                    writer.flush()?;
+                }
+                Format::Json => {
+                    serde_json::to_writer_pretty(&mut buffer, &rows)?;
+                    buffer.push(b'\n');
+                }
+            }
+            match output {
+                Some(path) => fs::write(&path, buffer).with_context(|| format!("writing {}", path.display()))?,
+                None => io::stdout().write_all(&buffer)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn depositor_rows(client: &RpcClient, pool: &Pubkey) -> Result<Vec<DepositorRow>> {
+    Ok(fetch_depositor_records(client, pool)
+        .context("fetching depositor records")?
+        .into_iter()
+        .map(|(address, record)| DepositorRow {
+            index: record.index,
+            record: address.to_string(),
+            depositor: record.depositor.to_string(),
+            amount_lamports: record.amount,
+            amount_sol: lamports_to_sol(record.amount),
+            last_deposit: record.timestamp,
+            tokens_claimed: record.tokens_claimed,
+        })
+        .collect())
+}
+
+/// Reads a whitelist file into its root and each wallet's proof.
+fn read_whitelist(path: &Path) -> Result<(merkle::Node, HashMap<Pubkey, Vec<merkle::Node>>)> {
+    let contents = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let file: WhitelistFile = serde_json::from_slice(&contents).context("parsing whitelist")?;
+    let node = |hex: &str| merkle::from_hex(hex).ok_or_else(|| anyhow!("invalid hash {hex}"));
+
+    let mut proofs = HashMap::new();
+    for entry in file.wallets {
+        let proof = entry.proof.iter().map(|hex| node(hex)).collect::<Result<_>>()?;
+        proofs.insert(Pubkey::from_str(&entry.wallet)?, proof);
+    }
+    Ok((node(&file.root)?, proofs))
+}
+
+/// `wallet`'s proof from an optional whitelist file, empty without one.
+fn whitelist_proof(path: Option<&Path>, wallet: &Pubkey) -> Result<Vec<merkle::Node>> {
+    match path {
+        Some(path) => read_whitelist(path)?
+            .1
+            .remove(wallet)
+            .ok_or_else(|| anyhow!("{wallet} is not on the whitelist")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Builds the ed25519 verification for an attestation file, checking that it
+/// was issued for this pool and depositor.
+fn attestation_instruction(path: &Path, pool: &Pubkey, depositor: &Pubkey) -> Result<Instruction> {
+    let contents = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let file: AttestationFile = serde_json::from_slice(&contents).context("parsing attestation")?;
+    if Pubkey::from_str(&file.pool)? != *pool || Pubkey::from_str(&file.depositor)? != *depositor {
+        return Err(anyhow!("attestation was issued for a different pool or depositor"));
+    }
+    let message = attestation::message(pool, depositor, file.expiry, file.max_allocation);
+    Ok(attestation::verify_instruction(
+        &Pubkey::from_str(&file.authority)?,
+        &Signature::from_str(&file.signature)?,
+        &message,
+    ))
+}
+
+/// Sends a transaction, surfacing `PresaleError`s by name.
+/// Gate accounts for `token_account`. NFT gates also need the NFT's mint,
+/// read from the token account, to derive its metadata account.
+fn gate_accounts(client: &RpcClient, state: &Pool, token_account: Option<Pubkey>) -> Result<Option<GateAccounts>> {
+    let Some(token_account) = token_account else {
+        return Ok(None);
+    };
+    let nft_mint = match state.gate_kind {
+        GateKind::NftCollection => {
+            let data = client.get_account_data(&token_account).context("fetching gate token account")?;
+            // SPL token accounts start with their mint
+            let mint = data.get(..32).ok_or_else(|| anyhow!("{token_account} is not a token account"))?;
+            Some(Pubkey::try_from(mint)?)
+        }
+        _ => None,
+    };
+    Ok(Some(GateAccounts { token_account, nft_mint }))
+}
+
+fn send(client: &RpcClient, ixs: &[Instruction], signer: &Keypair) -> Result<Signature> {
+    let blockhash = client.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(ixs, Some(&signer.pubkey()), &[signer], blockhash);
+    client.send_and_confirm_transaction(&transaction).map_err(|err| {
+        match err.get_transaction_error().as_ref().and_then(PresaleError::from_transaction_error) {
+            Some(program_error) => anyhow!("{program_error} (error {})", program_error.code()),
+            None => anyhow!(err),
+        }
+    })
+}
+
+fn percent(bps: u16) -> String {
+    format!("{}.{:02}%", bps / 100, bps % 100)
+}
+
+fn resolve_url(url: &str) -> String {
+    match url {
+        "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+        "d" | "devnet" => "https://api.devnet.solana.com",
+        "t" | "testnet" => "https://api.testnet.solana.com",
+        "l" | "localhost" => "http://127.0.0.1:8899",
+        url => url,
+    }
+    .to_string()
+}
+
+fn load_keypair(path: &str) -> Result<Keypair> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").context("HOME is not set")?).join(rest),
+        None => PathBuf::from(path),
+    };
+    read_keypair_file(&path).map_err(|err| anyhow!("reading keypair {}: {err}", path.display()))
+}
+
+fn resolve_expiry(client: &RpcClient, time_basis: TimeBasis, expiry: Option<i64>, duration: Option<i64>) -> Result<i64> {
+    match (expiry, duration) {
+        (Some(expiry), _) => Ok(expiry),
+        (None, Some(duration)) => deadline(client, time_basis, duration),
+        (None, None) => unreachable!("clap requires one of --expiry/--duration"),
+    }
+}
+
+/// The deadline `seconds` from now, in `time_basis` units.
+fn deadline(client: &RpcClient, time_basis: TimeBasis, seconds: i64) -> Result<i64> {
+    let now = match time_basis {
+        TimeBasis::UnixTimestamp => now()?,
+        TimeBasis::Slot => client.get_slot().context("fetching current slot")? as i64,
+    };
+    Ok(now + time_basis.from_seconds(seconds))
+}
+
+fn dollars_to_micro_usd(dollars: f64) -> u64 {
+    (dollars * MICRO_USD as f64).round() as u64
+}
+
+fn micro_usd_to_dollars(micro_usd: u64) -> f64 {
+    micro_usd as f64 / MICRO_USD as f64
+}
+
+fn parse_tier(value: &str) -> Result<(u64, f64), String> {
+    let (stake, max) = value.split_once(':').ok_or("expected MIN_STAKE:MAX_SOL")?;
+    let stake = stake.parse().map_err(|err| format!("invalid stake: {err}"))?;
+    let max = max.parse().map_err(|err| format!("invalid allocation: {err}"))?;
+    Ok((stake, max))
+}
+
+fn parse_bonus_step(value: &str) -> Result<(i64, u16), String> {
+    let (seconds, bps) = value.split_once(':').ok_or("expected SECONDS:BPS")?;
+    let seconds = seconds.parse().map_err(|err| format!("invalid duration: {err}"))?;
+    let bps = bps.parse().map_err(|err| format!("invalid bonus: {err}"))?;
+    Ok((seconds, bps))
+}
+
+fn now() -> Result<i64> {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    i64::try_from(elapsed.as_secs()).map_err(|_| anyhow!("system clock out of range"))
+}
+
@@ -0,0 +1,53 @@
+//! Attestations for pools gated by an attestation authority.
+//!
+//! The authority (for example a KYC provider) signs [`message`] off-chain
+//! with its ed25519 key. The depositor places [`verify_instruction`] before
+//! `deposit_sol` in the same transaction; the program finds it through the
+//! instructions sysvar.
+
+use solana_sdk::{ed25519_program, instruction::Instruction, pubkey::Pubkey, signature::Signature};
+
+/// pool || depositor || expiry || max allocation
+pub const MESSAGE_LEN: usize = 32 + 32 + 8 + 8;
+
+/// Bytes the attestation authority signs to allow `depositor` to deposit up
+/// to `max_allocation` lamports in total into `pool` until `expiry`.
+pub fn message(pool: &Pubkey, depositor: &Pubkey, expiry: i64, max_allocation: u64) -> [u8; MESSAGE_LEN] {
+    let mut message = [0u8; MESSAGE_LEN];
+    message[..32].copy_from_slice(pool.as_ref());
+    message[32..64].copy_from_slice(depositor.as_ref());
+    message[64..72].copy_from_slice(&expiry.to_le_bytes());
+    message[72..].copy_from_slice(&max_allocation.to_le_bytes());
+    message
+}
+
+/// ed25519 program instruction verifying `signature` by `authority` over
+/// `message`, with the key, signature and message stored inline.
+pub fn verify_instruction(authority: &Pubkey, signature: &Signature, message: &[u8]) -> Instruction {
+    const PUBKEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBKEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    let mut data = vec![1u8, 0];
+    for field in [
+        SIGNATURE_OFFSET,
+        THIS_INSTRUCTION,
+        PUBKEY_OFFSET,
+        THIS_INSTRUCTION,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        THIS_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(authority.as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
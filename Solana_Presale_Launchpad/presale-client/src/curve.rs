@@ -0,0 +1,254 @@
+//! Bonding-curve pricing, bit-for-bit the math of `buy_tokens` and
+//! `sell_tokens`.
+//!
+//! A curve's price rises from its start to its end price, in lamports per
+//! whole token, as tokens are sold. [`cost`] integrates it in unsigned 64.64
+//! fixed point with 256-bit intermediates. Buys round against the buyer and
+//! sells against the seller, so the vault always holds at least the rounded-up
+//! cost of the tokens in circulation.
+
+use crate::state::CurveKind;
+
+/// Largest mint precision a curve accepts.
+pub const MAX_DECIMALS: u8 = 18;
+
+/// 1.0 in 64.64 fixed point.
+pub const Q64: u128 = 1 << 64;
+
+/// ln(2) in 64.64 fixed point, rounded down.
+const LN2_Q64: u128 = 12_786_308_645_202_655_659;
+
+/// Direction every intermediate step of a computation rounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+impl Rounding {
+    fn reverse(self) -> Self {
+        match self {
+            Rounding::Down => Rounding::Up,
+            Rounding::Up => Rounding::Down,
+        }
+    }
+}
+
+/// Shape and size of a curve, as stored on its pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Curve {
+    pub kind: CurveKind,
+    pub start_price: u64,
+    pub end_price: u64,
+    pub supply: u64,
+    pub decimals: u8,
+}
+
+impl Curve {
+    /// Lamports the first `sold` base units cost. `None` when the parameters
+    /// overflow the fixed-point math.
+    pub fn cost(&self, sold: u64, rounding: Rounding) -> Option<u64> {
+        cost(self.kind, self.start_price, self.end_price, self.supply, self.decimals, sold, rounding)
+    }
+
+    /// Lamports `buy_tokens` charges for `amount` more tokens at position `sold`.
+    pub fn buy_cost(&self, sold: u64, amount: u64) -> Option<u64> {
+        self.cost(sold.checked_add(amount)?, Rounding::Up)?
+            .checked_sub(self.cost(sold, Rounding::Down)?)
+    }
+
+    /// Lamports `sell_tokens` pays for `amount` tokens at position `sold`.
+    pub fn sell_proceeds(&self, sold: u64, amount: u64) -> Option<u64> {
+        Some(self.cost(sold, Rounding::Down)?.saturating_sub(self.cost(sold.checked_sub(amount)?, Rounding::Up)?))
+    }
+
+    /// Marginal price at position `sold`, in lamports per whole token.
+    pub fn price(&self, sold: u64) -> Option<u64> {
+        let (start, sold, supply) = (self.start_price as u128, sold as u128, self.supply as u128);
+        let price = match self.kind {
+            CurveKind::None => return None,
+            CurveKind::Linear => {
+                start + mul_div((self.end_price - self.start_price) as u128, sold, supply, Rounding::Down)?
+            }
+            CurveKind::Exponential => {
+                let exponent = mul_div(log2_q64(self.end_price, self.start_price), sold, supply, Rounding::Down)?;
+                mul_div(start, exp2_q64(exponent)?, Q64, Rounding::Down)?
+            }
+        };
+        u64::try_from(price).ok()
+    }
+}
+
+/// Integral of the curve's price from zero to `sold` base units, in
+/// lamports. Each step rounds in `rounding`'s direction, so the result is
+/// monotonic in `sold`.
+pub fn cost(
+    kind: CurveKind,
+    start_price: u64,
+    end_price: u64,
+    supply: u64,
+    decimals: u8,
+    sold: u64,
+    rounding: Rounding,
+) -> Option<u64> {
+    let unit = 10u128.pow(decimals as u32);
+    let (start, sold, supply) = (start_price as u128, sold as u128, supply as u128);
+    let cost = match kind {
+        CurveKind::None => return None,
+        // start * x / unit + (end - start) * x^2 / (2 * supply * unit)
+        CurveKind::Linear => {
+            let flat = mul_div(start, sold, unit, rounding)?;
+            let slope = (end_price - start_price) as u128 * sold;
+            flat.checked_add(mul_div(slope, sold, 2 * supply * unit, rounding)?)?
+        }
+        // start * supply * (2^(g * x / supply) - 1) / (g * ln 2 * unit), g = log2(end / start)
+        CurveKind::Exponential => {
+            let growth = log2_q64(end_price, start_price);
+            let exponent = mul_div(growth, sold, supply, rounding)?;
+            let factor = exp2_q64(exponent)? - Q64;
+            let divisor = mul_div(growth, LN2_Q64, Q64, rounding.reverse())?;
+            if divisor == 0 {
+                return None;
+            }
+            let scaled = mul_div(start * supply, factor, divisor, rounding)?;
+            mul_div(scaled, 1, unit, rounding)?
+        }
+    };
+    u64::try_from(cost).ok()
+}
+
+/// `a * b / divisor` with a 256-bit intermediate product. `None` when the
+/// quotient does not fit 128 bits or `divisor` is zero.
+pub fn mul_div(a: u128, b: u128, divisor: u128, rounding: Rounding) -> Option<u128> {
+    const LOW: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & LOW);
+    let (b_high, b_low) = (b >> 64, b & LOW);
+    let low_low = a_low * b_low;
+    let (low_high, high_low) = (a_low * b_high, a_high * b_low);
+    let middle = (low_low >> 64) + (low_high & LOW) + (high_low & LOW);
+    let low = (low_low & LOW) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    if high >= divisor {
+        return None;
+    }
+
+    let (mut remainder, mut quotient) = (high, 0u128);
+    for bit in (0..128).rev() {
+        let overflow = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if overflow == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    if rounding == Rounding::Up && remainder > 0 {
+        quotient = quotient.checked_add(1)?;
+    }
+    Some(quotient)
+}
+
+/// log2(numerator / denominator) in 64.64 fixed point, rounded down, for
+/// `numerator >= denominator > 0`.
+pub fn log2_q64(numerator: u64, denominator: u64) -> u128 {
+    let (numerator, denominator) = (numerator as u128, denominator as u128);
+    let mut integer = 0;
+    while denominator << (integer + 1) <= numerator {
+        integer += 1;
+    }
+
+    let mut factor = (numerator << 63) / (denominator << integer);
+    let mut result = (integer as u128) << 64;
+    for bit in (0..64).rev() {
+        factor = (factor * factor) >> 63;
+        if factor >= 1 << 64 {
+            factor >>= 1;
+            result |= 1 << bit;
+        }
+    }
+    result
+}
+
+/// 2^exponent for a 64.64 fixed-point exponent, rounded down; `None` from 2^63 on.
+pub fn exp2_q64(exponent: u128) -> Option<u128> {
+    let integer = exponent >> 64;
+    if integer >= 63 {
+        return None;
+    }
+
+    let z = (((exponent & (Q64 - 1)) >> 1) * (LN2_Q64 >> 1)) >> 63;
+    let (mut sum, mut term) = (1u128 << 63, 1u128 << 63);
+    for k in 1..=24 {
+        term = ((term * z) >> 63) / k;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    Some(sum << (integer + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Computed by the program's `curve_cost`, which asserts the same file.
+    const VECTORS: &str = include_str!("../../Anchor Files/tests/curve_vectors.txt");
+
+    /// Each vector's curve, position and expected rounded-down and -up costs.
+    fn vectors() -> Vec<(Curve, u64, u64, u64)> {
+        let vectors: Vec<_> = VECTORS
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<&str> = line.split(' ').collect();
+                let number = |index: usize| fields[index].parse::<u64>().unwrap();
+                let kind = match fields[0] {
+                    "linear" => CurveKind::Linear,
+                    "exponential" => CurveKind::Exponential,
+                    other => panic!("unknown curve kind {other}"),
+                };
+                let curve = Curve {
+                    kind,
+                    start_price: number(1),
+                    end_price: number(2),
+                    supply: number(3),
+                    decimals: fields[4].parse().unwrap(),
+                };
+                (curve, number(5), number(6), number(7))
+            })
+            .collect();
+        assert!(!vectors.is_empty());
+        vectors
+    }
+
+    #[test]
+    fn costs_match_the_program() {
+        for (curve, sold, down, up) in vectors() {
+            assert_eq!(curve.cost(sold, Rounding::Down), Some(down), "{curve:?} at {sold}");
+            assert_eq!(curve.cost(sold, Rounding::Up), Some(up), "{curve:?} at {sold}");
+        }
+    }
+
+    #[test]
+    fn buys_cost_at_least_what_selling_back_pays() {
+        for (curve, sold, _, _) in vectors() {
+            for amount in [1, curve.supply / 10, curve.supply - sold] {
+                if amount == 0 || sold + amount > curve.supply {
+                    continue;
+                }
+                let buy = curve.buy_cost(sold, amount).unwrap();
+                assert!(buy >= curve.sell_proceeds(sold + amount, amount).unwrap(), "{curve:?} at {sold}");
+            }
+        }
+    }
+
+    #[test]
+    fn prices_run_from_start_to_end() {
+        for (curve, _, _, _) in vectors() {
+            assert_eq!(curve.price(0), Some(curve.start_price));
+            let end = curve.price(curve.supply).unwrap();
+            assert!(end.abs_diff(curve.end_price) <= 1, "{curve:?} ends at {end}");
+        }
+    }
+}
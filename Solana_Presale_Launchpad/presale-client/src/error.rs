@@ -0,0 +1,323 @@
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+/// Custom errors returned by `presale_program`, numbered from Anchor's
+/// `6000` offset in declaration order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[repr(u32)]
+pub enum PresaleError {
+    #[error("Invalid expiry time - must be in the future and at most a year away")]
+    InvalidExpiryTime = 6000,
+    #[error("Invalid deposit amount")]
+    InvalidDepositAmount,
+    #[error("Pool has expired")]
+    PoolExpired,
+    #[error("Deposit amount is too low")]
+    DepositTooLow,
+    #[error("Deposit amount is too high")]
+    DepositTooHigh,
+    #[error("Pool has not expired yet")]
+    PoolNotExpired,
+    #[error("Funds have already been claimed")]
+    AlreadyClaimed,
+    #[error("Unauthorized - only pool owner can perform this action")]
+    Unauthorized,
+    #[error("Pool allocations have already been finalized")]
+    AlreadyFinalized,
+    #[error("Pool allocations have not been finalized yet")]
+    PoolNotFinalized,
+    #[error("Pool has no depositors")]
+    NoDepositors,
+    #[error("Invalid token amount")]
+    InvalidTokenAmount,
+    #[error("Invalid Merkle proof for this allocation")]
+    InvalidMerkleProof,
+    #[error("Allocation has already been claimed")]
+    AllocationAlreadyClaimed,
+    #[error("Invalid caps - hard cap must cover the soft cap and minimum deposit")]
+    InvalidCaps,
+    #[error("Deposit would exceed the pool's hard cap")]
+    HardCapExceeded,
+    #[error("Pool did not reach its soft cap")]
+    SoftCapNotReached,
+    #[error("Refunds are only available for expired pools below their soft cap")]
+    RefundNotAvailable,
+    #[error("Deposit has already been refunded")]
+    AlreadyRefunded,
+    #[error("Pool settings can only change before the first deposit")]
+    PoolAlreadyActive,
+    #[error("Deposit requires an ed25519 attestation from the pool's attestation authority")]
+    MissingAttestation,
+    #[error("Attestation has expired")]
+    AttestationExpired,
+    #[error("Deposit exceeds the attested maximum allocation")]
+    AllocationExceeded,
+    #[error("Whitelist window must end before the pool expires")]
+    InvalidWhitelistWindow,
+    #[error("Only whitelisted wallets can deposit during the opening window")]
+    NotWhitelisted,
+    #[error("Wallet must wait more slots before depositing again")]
+    DepositTooSoon,
+    #[error("Deposits through cross-program invocation are not allowed")]
+    CpiDepositRejected,
+    #[error("Lottery pools need at least one winner and a fixed ticket price (min deposit equal to max deposit)")]
+    InvalidLotteryConfig,
+    #[error("Pool does not use lottery allocation")]
+    NotLotteryPool,
+    #[error("Wallet already holds a ticket in this lottery")]
+    TicketAlreadyRegistered,
+    #[error("Revealed secret does not match the lottery commitment")]
+    InvalidLotteryReveal,
+    #[error("Lottery reveal window has closed")]
+    LotteryRevealClosed,
+    #[error("Lottery winners have not been drawn yet")]
+    LotteryNotDrawn,
+    #[error("Lottery winners have already been drawn")]
+    LotteryAlreadyDrawn,
+    #[error("Winning tickets cannot be refunded")]
+    WinningTicket,
+    #[error("Positions cannot be transferred once allocations are finalized")]
+    PositionsFrozen,
+    #[error("Lottery tickets cannot be transferred")]
+    TicketNotTransferable,
+    #[error("Transfer must move part of the sender's position to another wallet")]
+    InvalidTransfer,
+    #[error("Metadata needs a name, and its fields must fit their maximum lengths")]
+    InvalidMetadata,
+    #[error("Account must be migrated to the current layout first")]
+    AccountNeedsMigration,
+    #[error("Account was written by a newer program version")]
+    UnsupportedAccountVersion,
+    #[error("Account already uses the current layout")]
+    AlreadyMigrated,
+    #[error("Tiers need up to four ascending stake thresholds, each allowing at least the minimum deposit")]
+    InvalidTiers,
+    #[error("Deposit exceeds the maximum allocation of the wallet's stake tier")]
+    TierAllocationExceeded,
+    #[error("Stake amount must be non-zero and within the staked balance")]
+    InvalidStakeAmount,
+    #[error("Stake is locked while the wallet has positions in active tiered pools")]
+    StakeLocked,
+    #[error("Position does not lock any stake")]
+    StakeNotLocked,
+    #[error("Positions in tiered pools cannot be transferred")]
+    TieredPositionNotTransferable,
+    #[error("Governance needs a positive voting period, quorum and approval of at most 100%, and a non-lottery pool")]
+    InvalidGovernanceConfig,
+    #[error("Pool does not use depositor governance")]
+    NotGovernedPool,
+    #[error("Funds of governed pools are released by depositor vote")]
+    GovernedPool,
+    #[error("Positions in governed or streaming pools cannot be transferred after expiry")]
+    GovernedPositionFrozen,
+    #[error("Another release proposal is still open")]
+    ProposalAlreadyOpen,
+    #[error("Release must be non-zero and within the remaining escrow")]
+    InvalidReleaseAmount,
+    #[error("Depositors rejected a release; the escrow is being refunded")]
+    ReleaseRejected,
+    #[error("Voting on this proposal has closed")]
+    VotingClosed,
+    #[error("Voting on this proposal has not ended yet")]
+    VotingNotEnded,
+    #[error("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[error("Only depositors with an unrefunded deposit can vote")]
+    NoVotingWeight,
+    #[error("Curves need a start price, a rising end price, a supply, at most 18 decimals, and no soft cap, lottery or governance")]
+    InvalidCurveConfig,
+    #[error("Pool does not sell along a bonding curve")]
+    NotCurvePool,
+    #[error("Curve pools trade through buy_tokens and sell_tokens")]
+    CurvePool,
+    #[error("Not enough tokens left on the curve")]
+    CurveSoldOut,
+    #[error("Curve price moved past the given limit")]
+    SlippageExceeded,
+    #[error("This curve does not buy tokens back")]
+    SellBackDisabled,
+    #[error("Curve price computation overflowed")]
+    CurveMathOverflow,
+    #[error("Token gates need a mint and a minimum balance; NFT gates need a collection")]
+    InvalidGateConfig,
+    #[error("Gated pools need the depositor's gate token account")]
+    MissingGateAccount,
+    #[error("Depositor does not hold the pool's gate token or collection NFT")]
+    GateNotMet,
+    #[error("Gate metadata account does not belong to the token's mint")]
+    InvalidGateMetadata,
+    #[error("Distribution accounts must be record and token account pairs of the pool's depositors")]
+    InvalidDistributionAccounts,
+    #[error("Bonus steps need ascending ends up to expiry and decaying bonuses of at most 100%, outside lottery and curve pools")]
+    InvalidBonusConfig,
+    #[error("Streams need a positive duration and a halt threshold of at most 100%, outside lottery and governed pools")]
+    InvalidStreamConfig,
+    #[error("Pool does not stream its raise")]
+    NotStreamingPool,
+    #[error("Funds of streaming pools are paid out through withdraw_stream")]
+    StreamingPool,
+    #[error("Depositors halted the stream; the escrow is being refunded")]
+    StreamHalted,
+    #[error("Nothing has accrued since the last withdrawal")]
+    NothingToWithdraw,
+    #[error("The stream has fully accrued and can no longer be halted")]
+    StreamEnded,
+    #[error("USD limits need a positive price age, a maximum above the minimum and a hard cap above the soft cap, outside lotteries")]
+    InvalidUsdConfig,
+    #[error("Pools with USD limits need their price oracle account")]
+    MissingPriceOracle,
+    #[error("Price oracle is not the pool's, or holds no positive SOL/USD price of its kind")]
+    InvalidPriceOracle,
+    #[error("Oracle price is older than the pool's maximum price age, or dated in the future")]
+    StalePrice,
+    #[error("The beneficiary must sign deposits into this pool")]
+    BeneficiarySignatureRequired,
+    #[error("Minimum slot gap is longer than a day")]
+    InvalidSlotGap,
+    #[error("Nothing to refund")]
+    NothingToRefund,
+    #[error("Pool is already settled")]
+    PoolAlreadySettled,
+    #[error("The governance window has closed; the escrow is being refunded")]
+    GovernanceWindowClosed,
+    #[error("The slot completing the lottery seed has not passed yet")]
+    LotterySeedPending,
+    #[error("Depositor records of the pool still need migrate_depositor_record")]
+    RecordsNeedMigration,
+}
+
+impl PresaleError {
+    const ALL: &'static [PresaleError] = &[
+        PresaleError::InvalidExpiryTime,
+        PresaleError::InvalidDepositAmount,
+        PresaleError::PoolExpired,
+        PresaleError::DepositTooLow,
+        PresaleError::DepositTooHigh,
+        PresaleError::PoolNotExpired,
+        PresaleError::AlreadyClaimed,
+        PresaleError::Unauthorized,
+        PresaleError::AlreadyFinalized,
+        PresaleError::PoolNotFinalized,
+        PresaleError::NoDepositors,
+        PresaleError::InvalidTokenAmount,
+        PresaleError::InvalidMerkleProof,
+        PresaleError::AllocationAlreadyClaimed,
+        PresaleError::InvalidCaps,
+        PresaleError::HardCapExceeded,
+        PresaleError::SoftCapNotReached,
+        PresaleError::RefundNotAvailable,
+        PresaleError::AlreadyRefunded,
+        PresaleError::PoolAlreadyActive,
+        PresaleError::MissingAttestation,
+        PresaleError::AttestationExpired,
+        PresaleError::AllocationExceeded,
+        PresaleError::InvalidWhitelistWindow,
+        PresaleError::NotWhitelisted,
+        PresaleError::DepositTooSoon,
+        PresaleError::CpiDepositRejected,
+        PresaleError::InvalidLotteryConfig,
+        PresaleError::NotLotteryPool,
+        PresaleError::TicketAlreadyRegistered,
+        PresaleError::InvalidLotteryReveal,
+        PresaleError::LotteryRevealClosed,
+        PresaleError::LotteryNotDrawn,
+        PresaleError::LotteryAlreadyDrawn,
+        PresaleError::WinningTicket,
+        PresaleError::PositionsFrozen,
+        PresaleError::TicketNotTransferable,
+        PresaleError::InvalidTransfer,
+        PresaleError::InvalidMetadata,
+        PresaleError::AccountNeedsMigration,
+        PresaleError::UnsupportedAccountVersion,
+        PresaleError::AlreadyMigrated,
+        PresaleError::InvalidTiers,
+        PresaleError::TierAllocationExceeded,
+        PresaleError::InvalidStakeAmount,
+        PresaleError::StakeLocked,
+        PresaleError::StakeNotLocked,
+        PresaleError::TieredPositionNotTransferable,
+        PresaleError::InvalidGovernanceConfig,
+        PresaleError::NotGovernedPool,
+        PresaleError::GovernedPool,
+        PresaleError::GovernedPositionFrozen,
+        PresaleError::ProposalAlreadyOpen,
+        PresaleError::InvalidReleaseAmount,
+        PresaleError::ReleaseRejected,
+        PresaleError::VotingClosed,
+        PresaleError::VotingNotEnded,
+        PresaleError::ProposalAlreadyExecuted,
+        PresaleError::NoVotingWeight,
+        PresaleError::InvalidCurveConfig,
+        PresaleError::NotCurvePool,
+        PresaleError::CurvePool,
+        PresaleError::CurveSoldOut,
+        PresaleError::SlippageExceeded,
+        PresaleError::SellBackDisabled,
+        PresaleError::CurveMathOverflow,
+        PresaleError::InvalidGateConfig,
+        PresaleError::MissingGateAccount,
+        PresaleError::GateNotMet,
+        PresaleError::InvalidGateMetadata,
+        PresaleError::InvalidDistributionAccounts,
+        PresaleError::InvalidBonusConfig,
+        PresaleError::InvalidStreamConfig,
+        PresaleError::NotStreamingPool,
+        PresaleError::StreamingPool,
+        PresaleError::StreamHalted,
+        PresaleError::NothingToWithdraw,
+        PresaleError::StreamEnded,
+        PresaleError::InvalidUsdConfig,
+        PresaleError::MissingPriceOracle,
+        PresaleError::InvalidPriceOracle,
+        PresaleError::StalePrice,
+        PresaleError::BeneficiarySignatureRequired,
+        PresaleError::InvalidSlotGap,
+        PresaleError::NothingToRefund,
+        PresaleError::PoolAlreadySettled,
+        PresaleError::GovernanceWindowClosed,
+        PresaleError::LotterySeedPending,
+        PresaleError::RecordsNeedMigration,
+    ];
+
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|error| error.code() == code)
+    }
+
+    /// Extracts the program error from a failed transaction, if the failure
+    /// was a `presale_program` custom error.
+    pub fn from_transaction_error(error: &TransactionError) -> Option<Self> {
+        match error {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                Self::from_code(*code)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("account data is not a {0}")]
+    AccountDiscriminator(&'static str),
+    #[error("failed to decode {0}: {1}")]
+    Decode(&'static str, std::io::Error),
+    #[error(transparent)]
+    Program(#[from] PresaleError),
+    #[error("simulation failed: {0}")]
+    Simulation(TransactionError),
+    #[error("view returned no data")]
+    MissingReturnData,
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Rpc(#[from] Box<solana_client::client_error::ClientError>),
+}
+
+#[cfg(feature = "rpc")]
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(error: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(error))
+    }
+}
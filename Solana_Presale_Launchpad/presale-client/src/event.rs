@@ -0,0 +1,796 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    discriminator,
+    state::{CurveKind, GateKind, OracleKind, PoolPhase, TimeBasis},
+    ClientError,
+};
+
+/// Tail of every event. Events logged before headers existed decode with a
+/// zero header, i.e. version 0, timestamp 0 and slot 0; see `Legacy`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventHeader {
+    pub version: u8,
+    pub timestamp: i64,
+    pub slot: u64,
+}
+
+impl EventHeader {
+    pub const LEN: usize = 1 + 8 + 8;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolCreated {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub expiry_timestamp: i64,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub time_basis: TimeBasis,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolMetadataSet {
+    pub pool: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub links: Vec<String>,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositMade {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub total_deposit: u64,
+    pub pool_total: u64,
+    pub payer: Pubkey,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PositionTransferred {
+    pub pool: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub from_total: u64,
+    pub to_total: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FundsClaimed {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositRefunded {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AttestationAuthoritySet {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AntiBotConfigured {
+    pub pool: Pubkey,
+    pub whitelist_root: [u8; 32],
+    pub whitelist_end: i64,
+    pub min_slot_gap: u64,
+    pub reject_cpi: bool,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GateConfigured {
+    pub pool: Pubkey,
+    pub kind: GateKind,
+    pub mint: Pubkey,
+    pub min_amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UsdLimitsConfigured {
+    pub pool: Pubkey,
+    pub kind: OracleKind,
+    pub price_oracle: Pubkey,
+    pub max_price_age: i64,
+    /// In micro-USD
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotteryConfigured {
+    pub pool: Pubkey,
+    pub winner_count: u32,
+    pub commitment: [u8; 32],
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotteryRevealed {
+    pub pool: Pubkey,
+    pub secret: [u8; 32],
+    pub entropy: [u8; 32],
+    pub seed: [u8; 32],
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GovernanceConfigured {
+    pub pool: Pubkey,
+    pub quorum_bps: u16,
+    pub approval_bps: u16,
+    pub voting_period: i64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseProposed {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u32,
+    pub amount: u64,
+    pub voting_end: i64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VoteCast {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub approve: bool,
+    pub weight: u64,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProposalExecuted {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u32,
+    pub approved: bool,
+    pub amount: u64,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StreamConfigured {
+    pub pool: Pubkey,
+    pub duration: i64,
+    pub halt_bps: u16,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StreamWithdrawn {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub total_released: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HaltVoteCast {
+    pub pool: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub halt_votes: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StreamHalted {
+    pub pool: Pubkey,
+    pub paid_to_owner: u64,
+    /// Escrow left for `refund_escrow`
+    pub refundable: u64,
+    pub halt_votes: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CurveConfigured {
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub kind: CurveKind,
+    pub start_price: u64,
+    pub end_price: u64,
+    pub supply: u64,
+    pub sell_back: bool,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokensBought {
+    pub pool: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub cost: u64,
+    pub tokens_sold: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokensSold {
+    pub pool: Pubkey,
+    pub seller: Pubkey,
+    pub amount: u64,
+    pub proceeds: u64,
+    pub tokens_sold: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UnsoldTokensWithdrawn {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TiersConfigured {
+    pub pool: Pubkey,
+    pub stake_mint: Pubkey,
+    pub min_stakes: Vec<u64>,
+    pub max_allocations: Vec<u64>,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BonusConfigured {
+    pub pool: Pubkey,
+    pub ends: Vec<i64>,
+    pub bonus_bps: Vec<u16>,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BonusApplied {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub bonus_bps: u16,
+    pub weighted_amount: u64,
+    pub total_weighted: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositPriced {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    /// In micro-USD, at `price × 10^exponent` dollars per SOL
+    pub usd_value: u64,
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub total_raised_usd: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Staked {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Unstaked {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StakeReleased {
+    pub pool: Pubkey,
+    pub staker: Pubkey,
+    pub active_positions: u32,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotterySeeded {
+    pub pool: Pubkey,
+    pub slot: u64,
+    pub slot_hash: [u8; 32],
+    pub seed: [u8; 32],
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotterySeedRescheduled {
+    pub pool: Pubkey,
+    pub expired_slot: u64,
+    pub seed_slot: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotteryDrawn {
+    pub pool: Pubkey,
+    pub seed: [u8; 32],
+    pub winners: u32,
+    pub picks: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AccountMigrated {
+    pub pool: Pubkey,
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub header: EventHeader,
+}
+
+/// A transaction moved the pool to another phase; expiry alone emits nothing.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PhaseChanged {
+    pub pool: Pubkey,
+    pub from: PoolPhase,
+    pub to: PoolPhase,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolSettled {
+    pub pool: Pubkey,
+    /// `LaunchpadStats::active_pools` afterwards
+    pub active_pools: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StatsInitialized {
+    pub stats: Pubkey,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditFailed {
+    pub pool: Pubkey,
+    pub vault_lamports: u64,
+    pub liabilities: u64,
+    pub vault_solvent: bool,
+    pub claim_consistent: bool,
+    pub counters_consistent: bool,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolFinalized {
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_tokens: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokensClaimed {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokensDistributed {
+    pub pool: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub header: EventHeader,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BatchDistributed {
+    pub pool: Pubkey,
+    pub distributed: u32,
+    pub skipped: u32,
+    pub header: EventHeader,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PresaleEvent {
+    PoolCreated(PoolCreated),
+    PoolMetadataSet(PoolMetadataSet),
+    DepositMade(DepositMade),
+    PositionTransferred(PositionTransferred),
+    FundsClaimed(FundsClaimed),
+    DepositRefunded(DepositRefunded),
+    AttestationAuthoritySet(AttestationAuthoritySet),
+    AntiBotConfigured(AntiBotConfigured),
+    GateConfigured(GateConfigured),
+    UsdLimitsConfigured(UsdLimitsConfigured),
+    LotteryConfigured(LotteryConfigured),
+    LotteryRevealed(LotteryRevealed),
+    LotterySeeded(LotterySeeded),
+    LotterySeedRescheduled(LotterySeedRescheduled),
+    LotteryDrawn(LotteryDrawn),
+    TiersConfigured(TiersConfigured),
+    BonusConfigured(BonusConfigured),
+    BonusApplied(BonusApplied),
+    DepositPriced(DepositPriced),
+    Staked(Staked),
+    Unstaked(Unstaked),
+    StakeReleased(StakeReleased),
+    GovernanceConfigured(GovernanceConfigured),
+    ReleaseProposed(ReleaseProposed),
+    VoteCast(VoteCast),
+    ProposalExecuted(ProposalExecuted),
+    StreamConfigured(StreamConfigured),
+    StreamWithdrawn(StreamWithdrawn),
+    HaltVoteCast(HaltVoteCast),
+    StreamHalted(StreamHalted),
+    CurveConfigured(CurveConfigured),
+    TokensBought(TokensBought),
+    TokensSold(TokensSold),
+    UnsoldTokensWithdrawn(UnsoldTokensWithdrawn),
+    AccountMigrated(AccountMigrated),
+    PhaseChanged(PhaseChanged),
+    PoolSettled(PoolSettled),
+    StatsInitialized(StatsInitialized),
+    AuditFailed(AuditFailed),
+    PoolFinalized(PoolFinalized),
+    TokensClaimed(TokensClaimed),
+    TokensDistributed(TokensDistributed),
+    BatchDistributed(BatchDistributed),
+}
+
+impl PresaleEvent {
+    /// Decodes an `emit!` payload: the `event:<Name>` discriminator followed
+    /// by the borsh-encoded event, in the current layout or the one it had
+    /// before headers existed. Returns `None` for unknown discriminators.
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, ClientError> {
+        let Some((prefix, rest)) = data.split_at_checked(8) else {
+            return Ok(None);
+        };
+
+        macro_rules! decode {
+            ($($name:ident),*) => {
+                $(
+                    if prefix == discriminator("event", stringify!($name)) {
+                        return $name::try_from_slice(rest)
+                            .or_else(|error| $name::decode_v0(rest).map_err(|_| error))
+                            .map(|event| Some(PresaleEvent::$name(event)))
+                            .map_err(|e| ClientError::Decode(stringify!($name), e));
+                    }
+                )*
+            };
+        }
+        decode!(
+            PoolCreated,
+            PoolMetadataSet,
+            DepositMade,
+            PositionTransferred,
+            FundsClaimed,
+            DepositRefunded,
+            AttestationAuthoritySet,
+            AntiBotConfigured,
+            GateConfigured,
+            UsdLimitsConfigured,
+            LotteryConfigured,
+            LotteryRevealed,
+            LotterySeeded,
+            LotterySeedRescheduled,
+            LotteryDrawn,
+            TiersConfigured,
+            BonusConfigured,
+            BonusApplied,
+            DepositPriced,
+            Staked,
+            Unstaked,
+            StakeReleased,
+            GovernanceConfigured,
+            ReleaseProposed,
+            VoteCast,
+            ProposalExecuted,
+            StreamConfigured,
+            StreamWithdrawn,
+            HaltVoteCast,
+            StreamHalted,
+            CurveConfigured,
+            TokensBought,
+            TokensSold,
+            UnsoldTokensWithdrawn,
+            AccountMigrated,
+            PhaseChanged,
+            PoolSettled,
+            StatsInitialized,
+            AuditFailed,
+            PoolFinalized,
+            TokensClaimed,
+            TokensDistributed,
+            BatchDistributed
+        );
+
+        Ok(None)
+    }
+
+    /// Collects the events `presale_program` logged in a transaction.
+    ///
+    /// Tracks the invocation stack so `Program data:` lines written by other
+    /// programs in the same transaction are skipped. Fails on the first of
+    /// the program's events that does not decode, rather than dropping it.
+    pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Result<Vec<Self>, ClientError> {
+        let program_id = crate::ID.to_string();
+        let mut stack: Vec<&str> = Vec::new();
+        let mut events = Vec::new();
+
+        for line in logs.iter().map(AsRef::as_ref) {
+            if let Some(rest) = line.strip_prefix("Program ") {
+                let mut parts = rest.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some(id), Some("invoke")) => stack.push(id),
+                    (Some(_), Some("success" | "failed:")) => {
+                        stack.pop();
+                    }
+                    _ => {}
+                }
+            }
+
+            let Some(data) = line.strip_prefix("Program data: ") else {
+                continue;
+            };
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let Ok(bytes) = STANDARD.decode(data) else {
+                continue;
+            };
+            if let Some(event) = Self::decode(&bytes)? {
+                events.push(event);
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// Decoding of events logged before headers existed (version 0).
+trait Legacy: BorshDeserialize {
+    /// Most events only gained the header, which decodes as zero.
+    fn decode_v0(data: &[u8]) -> std::io::Result<Self> {
+        let mut padded = data.to_vec();
+        padded.resize(data.len() + EventHeader::LEN, 0);
+        Self::try_from_slice(&padded)
+    }
+}
+
+macro_rules! legacy {
+    ($($name:ident),*) => {
+        $(impl Legacy for $name {})*
+    };
+}
+legacy!(
+    PoolMetadataSet,
+    PositionTransferred,
+    FundsClaimed,
+    DepositRefunded,
+    AttestationAuthoritySet,
+    AntiBotConfigured,
+    GateConfigured,
+    UsdLimitsConfigured,
+    LotteryConfigured,
+    LotteryRevealed,
+    LotterySeeded,
+    LotterySeedRescheduled,
+    LotteryDrawn,
+    TiersConfigured,
+    BonusConfigured,
+    BonusApplied,
+    DepositPriced,
+    Staked,
+    Unstaked,
+    StakeReleased,
+    GovernanceConfigured,
+    ReleaseProposed,
+    VoteCast,
+    ProposalExecuted,
+    StreamConfigured,
+    StreamWithdrawn,
+    HaltVoteCast,
+    StreamHalted,
+    CurveConfigured,
+    TokensBought,
+    TokensSold,
+    UnsoldTokensWithdrawn,
+    AccountMigrated,
+    PhaseChanged,
+    PoolSettled,
+    StatsInitialized,
+    AuditFailed,
+    PoolFinalized,
+    TokensClaimed,
+    TokensDistributed,
+    BatchDistributed
+);
+
+impl Legacy for PoolCreated {
+    /// The caps and time basis came later; version 0 pools have none and
+    /// count in unix time.
+    fn decode_v0(data: &[u8]) -> std::io::Result<Self> {
+        #[derive(BorshDeserialize)]
+        struct V0 {
+            pool: Pubkey,
+            owner: Pubkey,
+            expiry_timestamp: i64,
+            min_deposit: u64,
+            max_deposit: u64,
+        }
+        let V0 { pool, owner, expiry_timestamp, min_deposit, max_deposit } = V0::try_from_slice(data)?;
+        Ok(PoolCreated {
+            pool,
+            owner,
+            expiry_timestamp,
+            min_deposit,
+            max_deposit,
+            soft_cap: 0,
+            hard_cap: 0,
+            time_basis: TimeBasis::UnixTimestamp,
+            header: EventHeader::default(),
+        })
+    }
+}
+
+impl Legacy for DepositMade {
+    /// Deposits could not be paid for by another wallet before `payer`.
+    fn decode_v0(data: &[u8]) -> std::io::Result<Self> {
+        #[derive(BorshDeserialize)]
+        struct V0 {
+            pool: Pubkey,
+            depositor: Pubkey,
+            amount: u64,
+            total_deposit: u64,
+            pool_total: u64,
+        }
+        let V0 { pool, depositor, amount, total_deposit, pool_total } = V0::try_from_slice(data)?;
+        Ok(DepositMade {
+            pool,
+            depositor,
+            amount,
+            total_deposit,
+            pool_total,
+            payer: depositor,
+            header: EventHeader::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `emit!` payload of `name` with the given fields.
+    fn payload(name: &str, fields: &impl BorshSerialize) -> Vec<u8> {
+        let mut data = discriminator("event", name).to_vec();
+        fields.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn program_log(data: &[u8]) -> Vec<String> {
+        vec![
+            format!("Program {} invoke [1]", crate::ID),
+            format!("Program data: {}", STANDARD.encode(data)),
+            format!("Program {} success", crate::ID),
+        ]
+    }
+
+    #[test]
+    fn baseline_events_decode() {
+        let (pool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // The three events of the first release, field for field
+        let created = payload("PoolCreated", &(pool, owner, 1_700_000_000i64, 100u64, 5_000u64));
+        assert_eq!(created.len(), 8 + 88);
+        let deposit = payload("DepositMade", &(pool, owner, 100u64, 300u64, 1_000u64));
+        let claimed = payload("FundsClaimed", &(pool, owner, 1_000u64));
+
+        assert_eq!(
+            PresaleEvent::decode(&created).unwrap(),
+            Some(PresaleEvent::PoolCreated(PoolCreated {
+                pool,
+                owner,
+                expiry_timestamp: 1_700_000_000,
+                min_deposit: 100,
+                max_deposit: 5_000,
+                soft_cap: 0,
+                hard_cap: 0,
+                time_basis: TimeBasis::UnixTimestamp,
+                header: EventHeader::default(),
+            }))
+        );
+        assert_eq!(
+            PresaleEvent::decode(&deposit).unwrap(),
+            Some(PresaleEvent::DepositMade(DepositMade {
+                pool,
+                depositor: owner,
+                amount: 100,
+                total_deposit: 300,
+                pool_total: 1_000,
+                payer: owner,
+                header: EventHeader::default(),
+            }))
+        );
+        assert_eq!(
+            PresaleEvent::decode(&claimed).unwrap(),
+            Some(PresaleEvent::FundsClaimed(FundsClaimed {
+                pool,
+                owner,
+                amount: 1_000,
+                header: EventHeader::default(),
+            }))
+        );
+    }
+
+    #[test]
+    fn current_events_keep_their_header() {
+        let header = EventHeader { version: 1, timestamp: 1_700_000_000, slot: 250_000_000 };
+        let event = DepositMade {
+            pool: Pubkey::new_unique(),
+            depositor: Pubkey::new_unique(),
+            amount: 100,
+            total_deposit: 100,
+            pool_total: 100,
+            payer: Pubkey::new_unique(),
+            header,
+        };
+        let logs = program_log(&payload("DepositMade", &event));
+        assert_eq!(PresaleEvent::parse_logs(&logs).unwrap(), vec![PresaleEvent::DepositMade(event)]);
+    }
+
+    #[test]
+    fn undecodable_events_are_reported() {
+        let mut truncated = payload("FundsClaimed", &(Pubkey::new_unique(), Pubkey::new_unique()));
+        truncated.truncate(truncated.len() - 1);
+        assert!(matches!(PresaleEvent::decode(&truncated), Err(ClientError::Decode("FundsClaimed", _))));
+        assert!(PresaleEvent::parse_logs(&program_log(&truncated)).is_err());
+
+        // Other programs' logs are not ours to decode
+        let logs = [
+            format!("Program {} invoke [1]", crate::ID),
+            "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+            format!("Program data: {}", STANDARD.encode(&truncated)),
+            "Program 11111111111111111111111111111111 success".to_string(),
+            format!("Program {} success", crate::ID),
+        ];
+        assert_eq!(PresaleEvent::parse_logs(&logs).unwrap(), Vec::new());
+    }
+}
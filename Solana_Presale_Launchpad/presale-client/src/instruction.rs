@@ -0,0 +1,787 @@
+//! Instruction builders, one per `presale_program` instruction.
+//!
+//! Account order and writability follow the program's `#[derive(Accounts)]`
+//! structs; data is the `global:<name>` discriminator followed by the
+//! borsh-encoded arguments.
+
+use borsh::BorshSerialize;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+use crate::{
+    discriminator,
+    merkle::Node,
+    pda::{
+        find_claim_bitmap_address, find_depositor_record_address, find_halt_vote_address, find_lottery_draw_address,
+        find_participant_address, find_pool_address, find_pool_metadata_address, find_proposal_address,
+        find_stake_record_address, find_stake_vault_address, find_stats_address, find_token_metadata_address,
+        find_token_vault_address, find_vault_address, find_vote_address,
+    },
+    state::{CurveKind, GateKind, OracleKind, TimeBasis},
+};
+
+pub const TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// What a depositor shows a gated pool: one of their token accounts and, for
+/// NFT-collection gates, the NFT's mint, whose metadata account is derived.
+#[derive(Clone, Copy, Debug)]
+pub struct GateAccounts {
+    pub token_account: Pubkey,
+    pub nft_mint: Option<Pubkey>,
+}
+
+/// A read-only optional account slot. Anchor reads the program id in an
+/// optional slot as "absent", so `None` passes that.
+fn optional(key: Option<Pubkey>) -> AccountMeta {
+    AccountMeta::new_readonly(key.unwrap_or(crate::ID), false)
+}
+
+/// The optional `gate_token_account` and `gate_metadata` slots.
+fn gate_metas(gate: Option<&GateAccounts>) -> [AccountMeta; 2] {
+    [
+        optional(gate.map(|gate| gate.token_account)),
+        optional(gate.and_then(|gate| gate.nft_mint).map(|mint| find_token_metadata_address(&mint).0)),
+    ]
+}
+
+fn data<T: BorshSerialize>(name: &str, args: T) -> Vec<u8> {
+    let mut data = discriminator("global", name).to_vec();
+    args.serialize(&mut data).expect("serializing into a Vec cannot fail");
+    data
+}
+
+/// `soft_cap` and `hard_cap` of zero leave the pool without that cap.
+/// `expiry_timestamp` is a unix timestamp, or a slot when `time_basis` is
+/// [`TimeBasis::Slot`].
+pub fn create_pool(
+    owner: &Pubkey,
+    expiry_timestamp: i64,
+    min_deposit: u64,
+    max_deposit: u64,
+    soft_cap: u64,
+    hard_cap: u64,
+    time_basis: TimeBasis,
+) -> Instruction {
+    let pool = find_pool_address(owner).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(pool, false),
+            AccountMeta::new(find_vault_address(&pool).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("create_pool", (expiry_timestamp, min_deposit, max_deposit, soft_cap, hard_cap, time_basis)),
+    }
+}
+
+/// `commitment` is [`crate::lottery::commitment`] of the owner's secret.
+pub fn configure_lottery(pool: &Pubkey, owner: &Pubkey, winner_count: u32, commitment: Node) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_lottery", (winner_count, commitment)),
+    }
+}
+
+/// Creates or replaces the pool's metadata; see [`crate::state::PoolMetadata`]
+/// for the length limits.
+pub fn set_pool_metadata(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    links: Vec<String>,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(find_pool_metadata_address(pool).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("set_pool_metadata", (name, symbol, uri, links)),
+    }
+}
+
+/// Deposits into gated pools must be preceded by
+/// [`crate::attestation::verify_instruction`] in the same transaction.
+/// `whitelist_proof` is only checked during a pool's whitelist window; pass
+/// an empty proof otherwise. Tiered pools need their `tier_mint`, so the
+/// depositor's stake record is passed along.
+pub fn deposit_sol(
+    pool: &Pubkey,
+    depositor: &Pubkey,
+    amount: u64,
+    whitelist_proof: Vec<Node>,
+    tier_mint: Option<&Pubkey>,
+    gate: Option<&GateAccounts>,
+    price_oracle: Option<&Pubkey>,
+) -> Instruction {
+    deposit_sol_for(pool, depositor, depositor, true, amount, whitelist_proof, tier_mint, gate, price_oracle)
+}
+
+/// Deposit funded by `payer` whose record, allocation and refund rights
+/// belong to `beneficiary`. Attestations, whitelist proofs, stake tiers and
+/// holder gates are for the beneficiary; `price_oracle` is the pool's, for
+/// pools with USD limits. Set `beneficiary_signs` for tiered pools, where
+/// the deposit locks the beneficiary's stake, and pools with a slot gap,
+/// where it would restart the beneficiary's wait; both need its signature.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_sol_for(
+    pool: &Pubkey,
+    payer: &Pubkey,
+    beneficiary: &Pubkey,
+    beneficiary_signs: bool,
+    amount: u64,
+    whitelist_proof: Vec<Node>,
+    tier_mint: Option<&Pubkey>,
+    gate: Option<&GateAccounts>,
+    price_oracle: Option<&Pubkey>,
+) -> Instruction {
+    let stake_record = match tier_mint {
+        Some(mint) => AccountMeta::new(find_stake_record_address(mint, beneficiary).0, false),
+        None => optional(None),
+    };
+    let [gate_token_account, gate_metadata] = gate_metas(gate);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, beneficiary).0, false),
+            AccountMeta::new_readonly(*beneficiary, beneficiary_signs),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new(find_stats_address().0, false),
+            AccountMeta::new(find_participant_address(beneficiary).0, false),
+            stake_record,
+            gate_token_account,
+            gate_metadata,
+            optional(price_oracle.copied()),
+        ],
+        data: data("deposit_sol", (amount, whitelist_proof)),
+    }
+}
+
+/// Moves `amount` of `owner`'s position to `recipient`. Attestations,
+/// whitelist proofs and holder gates are for the recipient, as for a deposit.
+pub fn transfer_position(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    whitelist_proof: Vec<Node>,
+    gate: Option<&GateAccounts>,
+) -> Instruction {
+    let [gate_token_account, gate_metadata] = gate_metas(gate);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_depositor_record_address(pool, owner).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, recipient).0, false),
+            AccountMeta::new_readonly(*recipient, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new(find_stats_address().0, false),
+            AccountMeta::new(find_participant_address(recipient).0, false),
+            gate_token_account,
+            gate_metadata,
+        ],
+        data: data("transfer_position", (amount, whitelist_proof)),
+    }
+}
+
+/// `whitelist_root` of all zeroes disables the whitelist window.
+pub fn configure_anti_bot(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    whitelist_root: Node,
+    whitelist_end: i64,
+    min_slot_gap: u64,
+    reject_cpi: bool,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_anti_bot", (whitelist_root, whitelist_end, min_slot_gap, reject_cpi)),
+    }
+}
+
+/// Gates deposits on holding at least `min_amount` of `mint`, or an NFT of
+/// the verified collection `mint`. [`GateKind::None`] lifts the gate.
+pub fn configure_gate(pool: &Pubkey, owner: &Pubkey, kind: GateKind, mint: &Pubkey, min_amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_gate", (kind, *mint, min_amount)),
+    }
+}
+
+/// Tier `i` lets wallets with at least `min_stakes[i]` of `stake_mint`
+/// staked deposit up to `max_allocations[i]` in total. Empty lists turn
+/// tiers off.
+pub fn configure_tiers(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    stake_mint: &Pubkey,
+    min_stakes: Vec<u64>,
+    max_allocations: Vec<u64>,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: data("configure_tiers", (min_stakes, max_allocations)),
+    }
+}
+
+/// Deposits before `ends[i]` (in the pool's time basis) weigh
+/// `bonus_bps[i]` extra in the token split. Empty lists turn the bonus off.
+pub fn configure_bonus(pool: &Pubkey, owner: &Pubkey, ends: Vec<i64>, bonus_bps: Vec<u16>) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_bonus", (ends, bonus_bps)),
+    }
+}
+
+pub fn stake(mint: &Pubkey, staker: &Pubkey, staker_token_account: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(find_stake_record_address(mint, staker).0, false),
+            AccountMeta::new(find_stake_vault_address(mint, staker).0, false),
+            AccountMeta::new(*staker_token_account, false),
+            AccountMeta::new(*staker, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("stake", amount),
+    }
+}
+
+pub fn unstake(mint: &Pubkey, staker: &Pubkey, staker_token_account: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(find_stake_record_address(mint, staker).0, false),
+            AccountMeta::new(find_stake_vault_address(mint, staker).0, false),
+            AccountMeta::new(*staker_token_account, false),
+            AccountMeta::new_readonly(*staker, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: data("unstake", amount),
+    }
+}
+
+/// Unlocks `depositor`'s stake behind its position in the expired, tiered
+/// `pool`. Anyone can send it.
+pub fn release_stake(pool: &Pubkey, tier_mint: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(find_stake_record_address(tier_mint, depositor).0, false),
+        ],
+        data: data("release_stake", ()),
+    }
+}
+
+/// `voting_period` is in the pool's time basis; zero turns governance off.
+pub fn configure_governance(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    quorum_bps: u16,
+    approval_bps: u16,
+    voting_period: i64,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_governance", (quorum_bps, approval_bps, voting_period)),
+    }
+}
+
+/// `id` is the pool's current `proposal_count`.
+pub fn open_release_proposal(pool: &Pubkey, owner: &Pubkey, id: u32, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_proposal_address(pool, id).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("open_release_proposal", amount),
+    }
+}
+
+pub fn cast_vote(pool: &Pubkey, voter: &Pubkey, id: u32, approve: bool) -> Instruction {
+    let proposal = find_proposal_address(pool, id).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(find_depositor_record_address(pool, voter).0, false),
+            AccountMeta::new(find_vote_address(&proposal, voter).0, false),
+            AccountMeta::new(*voter, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("cast_vote", approve),
+    }
+}
+
+/// Permissionless once voting has ended; approved releases go to `owner`.
+pub fn execute_proposal(pool: &Pubkey, owner: &Pubkey, id: u32) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_proposal_address(pool, id).0, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("execute_proposal", ()),
+    }
+}
+
+/// Limits in micro-USD on top of the lamport ones, valued at `price_oracle`
+/// read as `kind`. `OracleKind::None` with no oracle removes them.
+#[allow(clippy::too_many_arguments)]
+pub fn configure_usd_limits(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    kind: OracleKind,
+    price_oracle: Option<&Pubkey>,
+    max_price_age: i64,
+    min_deposit: u64,
+    max_deposit: u64,
+    soft_cap: u64,
+    hard_cap: u64,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            optional(price_oracle.copied()),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: data("configure_usd_limits", (kind, max_price_age, min_deposit, max_deposit, soft_cap, hard_cap)),
+    }
+}
+
+/// `duration` is in the pool's time basis; zero turns streaming off.
+pub fn configure_stream(pool: &Pubkey, owner: &Pubkey, duration: i64, halt_bps: u16) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("configure_stream", (duration, halt_bps)),
+    }
+}
+
+pub fn withdraw_stream(pool: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("withdraw_stream", ()),
+    }
+}
+
+/// `owner` receives the accrued stream if this vote halts it.
+pub fn vote_halt(pool: &Pubkey, owner: &Pubkey, voter: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new_readonly(find_depositor_record_address(pool, voter).0, false),
+            AccountMeta::new(find_halt_vote_address(pool, voter).0, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new(find_stats_address().0, false),
+            AccountMeta::new(*voter, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("vote_halt", ()),
+    }
+}
+
+pub fn refund_escrow(pool: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(*depositor, true),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("refund_escrow", ()),
+    }
+}
+
+/// Pass `Pubkey::default()` to turn attestation off.
+pub fn set_attestation_authority(pool: &Pubkey, owner: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new_readonly(*owner, true)],
+        data: data("set_attestation_authority", *authority),
+    }
+}
+
+pub fn claim_funds(pool: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("claim_funds", ()),
+    }
+}
+
+pub fn finalize_pool(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    token_mint: &Pubkey,
+    owner_token_account: &Pubkey,
+    merkle_root: Node,
+    total_tokens: u64,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_claim_bitmap_address(pool).0, false),
+            AccountMeta::new_readonly(*token_mint, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(*owner_token_account, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("finalize_pool", (merkle_root, total_tokens)),
+    }
+}
+
+/// Prices are lamports per whole token; `supply` moves from
+/// `owner_token_account` into the pool's token vault.
+#[allow(clippy::too_many_arguments)]
+pub fn configure_curve(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    token_mint: &Pubkey,
+    owner_token_account: &Pubkey,
+    kind: CurveKind,
+    start_price: u64,
+    end_price: u64,
+    supply: u64,
+    sell_back: bool,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new_readonly(*token_mint, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(*owner_token_account, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("configure_curve", (kind, start_price, end_price, supply, sell_back)),
+    }
+}
+
+/// `max_cost` bounds the lamports paid; see `Pool::curve_buy_cost`.
+pub fn buy_tokens(pool: &Pubkey, buyer: &Pubkey, buyer_token_account: &Pubkey, amount: u64, max_cost: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(*buyer_token_account, false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("buy_tokens", (amount, max_cost)),
+    }
+}
+
+/// `min_proceeds` bounds the lamports received; see `Pool::curve_sell_proceeds`.
+pub fn sell_tokens(
+    pool: &Pubkey,
+    seller: &Pubkey,
+    seller_token_account: &Pubkey,
+    amount: u64,
+    min_proceeds: u64,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(*seller_token_account, false),
+            AccountMeta::new(*seller, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: data("sell_tokens", (amount, min_proceeds)),
+    }
+}
+
+pub fn withdraw_unsold_tokens(pool: &Pubkey, owner: &Pubkey, owner_token_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(*owner_token_account, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: data("withdraw_unsold_tokens", ()),
+    }
+}
+
+pub fn claim_tokens(
+    pool: &Pubkey,
+    depositor: &Pubkey,
+    depositor_token_account: &Pubkey,
+    amount: u64,
+    proof: Vec<Node>,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(find_claim_bitmap_address(pool).0, false),
+            AccountMeta::new(find_token_vault_address(pool).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(*depositor_token_account, false),
+            AccountMeta::new_readonly(*depositor, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: data("claim_tokens", (amount, proof)),
+    }
+}
+
+/// Pays each `(depositor, token_account)` its entitlement from the token
+/// vault. Already paid, refunded and losing records are skipped on-chain, so
+/// a batch can be resent. `lottery` passes the pool's draw, which lottery
+/// pools require.
+pub fn distribute_batch(
+    pool: &Pubkey,
+    owner: &Pubkey,
+    lottery: bool,
+    recipients: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*pool, false),
+        AccountMeta::new(find_claim_bitmap_address(pool).0, false),
+        AccountMeta::new(find_token_vault_address(pool).0, false),
+        optional(lottery.then(|| find_lottery_draw_address(pool).0)),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+    ];
+    for (depositor, token_account) in recipients {
+        accounts.push(AccountMeta::new(find_depositor_record_address(pool, depositor).0, false));
+        accounts.push(AccountMeta::new(*token_account, false));
+    }
+    Instruction { program_id: crate::ID, accounts, data: data("distribute_batch", ()) }
+}
+
+pub fn refund(pool: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(*depositor, true),
+            AccountMeta::new(find_stats_address().0, false),
+        ],
+        data: data("refund", ()),
+    }
+}
+
+/// Permissionless; stops a failed pool counting as active before any refund.
+pub fn settle_failed_pool(pool: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new(*pool, false), AccountMeta::new(find_stats_address().0, false)],
+        data: data("settle_failed_pool", ()),
+    }
+}
+
+pub fn reveal_lottery(pool: &Pubkey, owner: &Pubkey, secret: Node) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_lottery_draw_address(pool).0, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("reveal_lottery", secret),
+    }
+}
+
+/// Permissionless; repeat until the pool's `lottery_drawn` is set. Fails with
+/// `LotterySeedPending` until the pool's `lottery_seed_slot` has passed.
+pub fn draw_winners(pool: &Pubkey, max_draws: u32) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(find_lottery_draw_address(pool).0, false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::ID, false),
+        ],
+        data: data("draw_winners", max_draws),
+    }
+}
+
+pub fn refund_losing_ticket(pool: &Pubkey, depositor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new_readonly(find_lottery_draw_address(pool).0, false),
+            AccountMeta::new(find_vault_address(pool).0, false),
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(*depositor, true),
+        ],
+        data: data("refund_losing_ticket", ()),
+    }
+}
+
+/// Creates the stats account after an upgrade from a program version
+/// without it, paid for by `payer`.
+pub fn init_stats(payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(find_stats_address().0, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("init_stats", ()),
+    }
+}
+
+/// Grows a pool written by an older program version to the current layout,
+/// paid for by `payer`.
+pub fn migrate_pool(pool: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("migrate_pool", ()),
+    }
+}
+
+/// Grows `depositor`'s record to the current layout, paid for by `payer`.
+/// Records from before indexes take the pool's next index, so the pool is
+/// migrated first.
+pub fn migrate_depositor_record(pool: &Pubkey, depositor: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(find_depositor_record_address(pool, depositor).0, false),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data("migrate_depositor_record", ()),
+    }
+}
+
+/// View instruction; simulate it and decode the return data with
+/// [`crate::state::PoolStatus::try_from_return_data`].
+pub fn get_pool_status(pool: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new_readonly(*pool, false)],
+        data: data("get_pool_status", ()),
+    }
+}
+
+/// Checks the pool's bookkeeping against its vault. Simulate it and decode
+/// the return data with [`crate::state::AuditReport::try_from_return_data`],
+/// or send it to log `AuditFailed` when a check fails. Set `finalized` for
+/// finalized pools, whose claim bitmap is checked too.
+pub fn audit_pool(pool: &Pubkey, finalized: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new_readonly(find_vault_address(pool).0, false),
+            optional(finalized.then(|| find_claim_bitmap_address(pool).0)),
+        ],
+        data: data("audit_pool", ()),
+    }
+}
+
+/// View instruction; simulate it and decode the return data with
+/// [`crate::state::DepositorInfo::try_from_return_data`]. Set `lottery_drawn`
+/// for lottery pools whose draw is complete, so losing tickets are reported.
+pub fn get_depositor_info(pool: &Pubkey, depositor: &Pubkey, lottery_drawn: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new_readonly(find_depositor_record_address(pool, depositor).0, false),
+            optional(lottery_drawn.then(|| find_lottery_draw_address(pool).0)),
+        ],
+        data: data("get_depositor_info", ()),
+    }
+}
+
+/// View instruction; simulate it and decode the return data with
+/// [`crate::state::LaunchpadStats::try_from_return_data`].
+pub fn get_launchpad_stats() -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new_readonly(find_stats_address().0, false)],
+        data: data("get_launchpad_stats", ()),
+    }
+}
+
+/// View instruction; simulate it and decode the return data with
+/// [`crate::state::PoolInfo::try_from_return_data`].
+pub fn get_pool_info(pool: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new_readonly(*pool, false)],
+        data: data("get_pool_info", ()),
+    }
+}
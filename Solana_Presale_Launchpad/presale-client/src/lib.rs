@@ -0,0 +1,35 @@
+//! Rust client SDK for `presale_program`.
+//!
+//! Derives the program's PDAs, builds its instructions, decodes its accounts,
+//! return values and events, and maps `PresaleError` codes to typed errors.
+//! It only depends on `solana-sdk` and `borsh`, so it works without an Anchor
+//! workspace; the `rpc` feature adds blocking helpers on top of
+//! `solana-client`.
+
+pub mod attestation;
+pub mod curve;
+pub mod error;
+pub mod event;
+pub mod instruction;
+pub mod lottery;
+pub mod merkle;
+pub mod oracle;
+pub mod pda;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod state;
+
+pub use error::{ClientError, PresaleError};
+pub use event::PresaleEvent;
+
+use solana_sdk::hash::hash;
+
+solana_sdk::declare_id!("FoHCiuKzML2o3b6GkG1kE27XaTRAiTAnzSSwobJpiR6S");
+
+/// First 8 bytes of sha256("<namespace>:<name>"), as Anchor derives its
+/// instruction, account and event discriminators.
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("{namespace}:{name}").as_bytes()).to_bytes()[..8]);
+    discriminator
+}
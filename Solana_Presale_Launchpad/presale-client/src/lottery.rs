@@ -0,0 +1,56 @@
+//! Commit-reveal helpers for lottery pools.
+//!
+//! The owner commits to [`commitment`] of a random secret with
+//! `configure_lottery`, every ticket folds itself into the pool's
+//! `lottery_entropy`, and after expiry `reveal_lottery` stores [`seed`]. The
+//! first `draw_winners` completes it with the hash of a later slot
+//! ([`seeded`]), which nobody knew at reveal time. [`replay_draw`] recomputes
+//! the winner set from that public data, so anyone can check the on-chain
+//! `LotteryDraw`.
+
+use solana_sdk::hash::hashv;
+
+use crate::merkle::Node;
+
+/// sha256(secret), published before the sale
+pub fn commitment(secret: &Node) -> Node {
+    hashv(&[secret]).to_bytes()
+}
+
+/// sha256("lottery" || secret || entropy), the seed as revealed
+pub fn seed(secret: &Node, entropy: &Node) -> Node {
+    hashv(&[b"lottery".as_ref(), secret, entropy]).to_bytes()
+}
+
+/// sha256(revealed seed || slot hash), the seed the draw uses
+pub fn seeded(seed: &Node, slot_hash: &Node) -> Node {
+    hashv(&[seed, slot_hash]).to_bytes()
+}
+
+/// Ticket index of pick `nonce`: sha256(seed || nonce) modulo the ticket count.
+pub fn pick(seed: &Node, nonce: u64, ticket_count: u32) -> u32 {
+    let hash = hashv(&[seed, &nonce.to_le_bytes()]).to_bytes();
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&hash[..8]);
+    (u64::from_le_bytes(value) % ticket_count as u64) as u32
+}
+
+/// Whether each ticket index won, replaying `draw_winners` pick by pick.
+pub fn replay_draw(seed: &Node, ticket_count: u32, winner_count: u32) -> Vec<bool> {
+    let winners = winner_count.min(ticket_count);
+    // The program samples whichever set is smaller
+    let selects_losers = winners * 2 > ticket_count;
+    let to_select = if selects_losers { ticket_count - winners } else { winners };
+
+    let mut selected = vec![false; ticket_count as usize];
+    let (mut count, mut nonce) = (0, 0);
+    while count < to_select {
+        let index = pick(seed, nonce, ticket_count) as usize;
+        nonce += 1;
+        if !selected[index] {
+            selected[index] = true;
+            count += 1;
+        }
+    }
+    selected.into_iter().map(|is_selected| is_selected != selects_losers).collect()
+}
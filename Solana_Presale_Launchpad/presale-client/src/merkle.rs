@@ -0,0 +1,278 @@
+//! Merkle allocation trees for `finalize_pool` / `claim_tokens`, and
+//! whitelist trees for `configure_anti_bot` / `deposit_sol`.
+//!
+//! Leaves and inner nodes are hashed exactly like the on-chain `merkle_leaf`,
+//! `whitelist_leaf` and `verify_merkle_proof` helpers, so every proof
+//! produced here verifies on-chain.
+
+use solana_sdk::{hash::hashv, pubkey::Pubkey};
+
+use crate::state::DepositorRecord;
+
+pub type Node = [u8; 32];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Allocation {
+    pub index: u32,
+    pub depositor: Pubkey,
+    pub deposited: u64,
+    pub amount: u64,
+}
+
+/// sha256(0x00 || index || depositor || amount)
+pub fn leaf_hash(index: u32, depositor: &Pubkey, amount: u64) -> Node {
+    hashv(&[&[0u8], &index.to_le_bytes(), depositor.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// sha256(0x02 || depositor)
+pub fn whitelist_leaf_hash(depositor: &Pubkey) -> Node {
+    hashv(&[&[2u8], depositor.as_ref()]).to_bytes()
+}
+
+/// sha256(0x01 || min(a, b) || max(a, b))
+pub fn hash_pair(a: &Node, b: &Node) -> Node {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[1u8], left, right]).to_bytes()
+}
+
+pub fn verify_proof(proof: &[Node], root: &Node, leaf: Node) -> bool {
+    proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling)) == *root
+}
+
+/// Splits `total_tokens` over the records in proportion to their deposits,
+/// weighted by the early-bird bonus in bonus pools.
+///
+/// Amounts round down, so the allocations never add up to more than the
+/// tokens funded into the pool's vault. The result is ordered by record index.
+pub fn pro_rata(records: &[DepositorRecord], total_tokens: u64) -> Vec<Allocation> {
+    let total_weight: u128 = records.iter().map(|r| r.allocation_weight() as u128).sum();
+    let mut allocations: Vec<Allocation> = records
+        .iter()
+        .map(|r| Allocation {
+            index: r.index,
+            depositor: r.depositor,
+            deposited: r.amount,
+            amount: (r.allocation_weight() as u128 * total_tokens as u128)
+                .checked_div(total_weight)
+                .unwrap_or(0) as u64,
+        })
+        .collect();
+    allocations.sort_by_key(|a| a.index);
+    allocations
+}
+
+pub struct MerkleTree {
+    levels: Vec<Vec<Node>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree bottom-up. An unpaired node is carried to the next
+    /// level unchanged, so it simply has no sibling in its proofs.
+    pub fn new(leaves: Vec<Node>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub fn from_allocations(allocations: &[Allocation]) -> Self {
+        Self::new(
+            allocations
+                .iter()
+                .map(|a| leaf_hash(a.index, &a.depositor, a.amount))
+                .collect(),
+        )
+    }
+
+    /// Tree over whitelisted wallets; proofs follow the order of `wallets`.
+    pub fn from_whitelist(wallets: &[Pubkey]) -> Self {
+        Self::new(wallets.iter().map(whitelist_leaf_hash).collect())
+    }
+
+    pub fn root(&self) -> Node {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sibling hashes from the leaf at `position` up to the root.
+    pub fn proof(&self, mut position: usize) -> Vec<Node> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        proof
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Parses a node written by [`to_hex`].
+pub fn from_hex(hex: &str) -> Option<Node> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut node = [0u8; 32];
+    for (byte, chunk) in node.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checked against the program's `merkle_leaf`, `whitelist_leaf` and
+    /// `verify_merkle_proof` by its `tests/merkle.rs`.
+    const VECTORS: &str = include_str!("../../Anchor Files/tests/merkle_vectors.txt");
+
+    /// Tree sizes the vectors cover: a single leaf, even and odd counts.
+    const SIZES: [u32; 5] = [1, 2, 3, 5, 7];
+
+    fn depositor(index: u32) -> Pubkey {
+        Pubkey::new_from_array([index as u8 + 1; 32])
+    }
+
+    fn allocations(count: u32) -> Vec<Allocation> {
+        (0..count)
+            .map(|index| Allocation {
+                index,
+                depositor: depositor(index),
+                deposited: 0,
+                amount: (index as u64 + 1) * 1_000,
+            })
+            .collect()
+    }
+
+    fn record(index: u32, amount: u64, weighted_amount: u64) -> DepositorRecord {
+        DepositorRecord {
+            depositor: depositor(index),
+            pool: Pubkey::default(),
+            amount,
+            timestamp: 0,
+            index,
+            tokens_claimed: 0,
+            is_refunded: false,
+            last_deposit_slot: 0,
+            version: DepositorRecord::VERSION,
+            stake_locked: false,
+            weighted_amount,
+        }
+    }
+
+    fn proof_hex(proof: &[Node]) -> String {
+        if proof.is_empty() {
+            return "-".to_string();
+        }
+        proof.iter().map(|node| to_hex(node)).collect::<Vec<_>>().join(",")
+    }
+
+    /// One line per proof of every tree in `SIZES`, plus a whitelist tree.
+    fn vectors() -> String {
+        let mut lines = Vec::new();
+        for size in SIZES {
+            let allocations = allocations(size);
+            let tree = MerkleTree::from_allocations(&allocations);
+            for (position, a) in allocations.iter().enumerate() {
+                lines.push(format!(
+                    "allocation {size} {} {} {} {} {}",
+                    a.index,
+                    a.depositor,
+                    a.amount,
+                    to_hex(&tree.root()),
+                    proof_hex(&tree.proof(position))
+                ));
+            }
+        }
+        let wallets: Vec<Pubkey> = (0..3).map(depositor).collect();
+        let tree = MerkleTree::from_whitelist(&wallets);
+        for (position, wallet) in wallets.iter().enumerate() {
+            lines.push(format!("whitelist 3 {wallet} {} {}", to_hex(&tree.root()), proof_hex(&tree.proof(position))));
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn proofs_match_the_vectors() {
+        let expected: Vec<&str> = VECTORS.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(vectors(), expected.join("\n"));
+    }
+
+    #[test]
+    #[ignore = "prints tests/merkle_vectors.txt of the program"]
+    fn print_vectors() {
+        println!("# allocation leaves index depositor amount root proof | whitelist leaves depositor root proof");
+        println!("# Built by presale-client's MerkleTree; tests/merkle.rs verifies them, `-` is an empty proof");
+        println!("{}", vectors());
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        for size in SIZES {
+            let allocations = allocations(size);
+            let tree = MerkleTree::from_allocations(&allocations);
+            for (position, a) in allocations.iter().enumerate() {
+                let proof = tree.proof(position);
+                assert!(verify_proof(&proof, &tree.root(), leaf_hash(a.index, &a.depositor, a.amount)));
+                // Another amount or index does not
+                assert!(!verify_proof(&proof, &tree.root(), leaf_hash(a.index, &a.depositor, a.amount + 1)));
+                assert!(!verify_proof(&proof, &tree.root(), leaf_hash(a.index + 1, &a.depositor, a.amount)));
+            }
+        }
+    }
+
+    #[test]
+    fn single_leaves_are_the_root() {
+        let allocations = allocations(1);
+        let tree = MerkleTree::from_allocations(&allocations);
+        assert_eq!(tree.root(), leaf_hash(0, &depositor(0), 1_000));
+        assert!(tree.proof(0).is_empty());
+        assert_eq!(MerkleTree::new(Vec::new()).root(), [0; 32]);
+    }
+
+    #[test]
+    fn unpaired_nodes_skip_a_level() {
+        // The fifth leaf has no sibling until it meets the first four's root
+        let tree = MerkleTree::from_allocations(&allocations(5));
+        assert_eq!(tree.proof(4).len(), 1);
+        assert_eq!(tree.proof(0).len(), 3);
+    }
+
+    #[test]
+    fn pro_rata_rounds_down_in_index_order() {
+        let records = [record(2, 300, 0), record(0, 100, 0), record(1, 200, 0)];
+        let allocations = pro_rata(&records, 1_000);
+        assert_eq!(allocations.iter().map(|a| a.index).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(allocations.iter().map(|a| a.amount).collect::<Vec<_>>(), [166, 333, 500]);
+        assert!(allocations.iter().map(|a| a.amount).sum::<u64>() <= 1_000);
+        assert_eq!(allocations[2].deposited, 300);
+    }
+
+    #[test]
+    fn pro_rata_uses_bonus_weights() {
+        // Both deposited 100, the first early enough for a 50% bonus
+        let allocations = pro_rata(&[record(0, 100, 150), record(1, 100, 0)], 500);
+        assert_eq!(allocations.iter().map(|a| a.amount).collect::<Vec<_>>(), [300, 200]);
+        assert!(pro_rata(&[], 500).is_empty());
+        assert_eq!(pro_rata(&[record(0, 0, 0)], 500)[0].amount, 0);
+    }
+}
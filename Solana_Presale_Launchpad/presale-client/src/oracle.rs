@@ -0,0 +1,77 @@
+//! SOL/USD prices for pools with USD limits.
+//!
+//! A pool reads its price account according to its [`OracleKind`]: a fully
+//! verified Pyth `PriceUpdateV2` account of the SOL/USD feed, or a
+//! [`MockPrice`] account that a local validator loads from a file with
+//! `solana-test-validator --account <ADDRESS> <FILE>`. USD limits and values
+//! are in micro-USD.
+//!
+//! [`OracleKind`]: crate::state::OracleKind
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::curve::{mul_div, Rounding};
+
+/// Owner of Pyth `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Pyth's Crypto.SOL/USD feed id, the only feed pools accept.
+pub const PYTH_SOL_USD_FEED_ID: [u8; 32] = [
+    239, 13, 139, 111, 218, 44, 235, 164, 29, 161, 93, 64, 149, 209, 218, 57, 42, 13, 47, 142, 208, 198, 199, 188,
+    15, 76, 250, 200, 194, 128, 181, 109,
+];
+
+/// Micro-USD in a dollar.
+pub const MICRO_USD: u64 = 1_000_000;
+
+/// Data of an `OracleKind::Mock` price account: a SOL/USD price of
+/// `price × 10^exponent` dollars per SOL, published at `publish_time` (unix
+/// seconds). The program accepts it whatever program owns the account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockPrice {
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl MockPrice {
+    pub const MAGIC: [u8; 8] = *b"mockprc1";
+    /// magic || price || exponent || publish time, all little-endian
+    pub const LEN: usize = 8 + 8 + 4 + 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[..8].copy_from_slice(&Self::MAGIC);
+        data[8..16].copy_from_slice(&self.price.to_le_bytes());
+        data[16..20].copy_from_slice(&self.exponent.to_le_bytes());
+        data[20..].copy_from_slice(&self.publish_time.to_le_bytes());
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::LEN || data[..8] != Self::MAGIC {
+            return None;
+        }
+        Some(MockPrice {
+            price: i64::from_le_bytes(data[8..16].try_into().ok()?),
+            exponent: i32::from_le_bytes(data[16..20].try_into().ok()?),
+            publish_time: i64::from_le_bytes(data[20..28].try_into().ok()?),
+        })
+    }
+}
+
+/// Value of `lamports` in micro-USD at `price × 10^exponent` dollars per SOL,
+/// rounded down as `deposit_sol` values deposits. `None` for non-positive
+/// prices and on overflow.
+pub fn usd_value(price: i64, exponent: i32, lamports: u64) -> Option<u64> {
+    // Lamports carry 9 decimals and micro-USD 6, so the scale is 10^(exponent - 3)
+    let price = u128::try_from(price).ok().filter(|&price| price > 0)?;
+    let shift = exponent.checked_sub(3)?;
+    let scale = 10u128.checked_pow(shift.unsigned_abs())?;
+    let value = if shift >= 0 {
+        (lamports as u128).checked_mul(price)?.checked_mul(scale)?
+    } else {
+        mul_div(lamports as u128, price, scale, Rounding::Down)?
+    };
+    u64::try_from(value).ok()
+}
@@ -0,0 +1,79 @@
+use solana_sdk::pubkey::Pubkey;
+
+/// Associated Token Account program, for wallets' default token accounts.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Token Metadata program, which owns the NFT metadata collection gates read.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub fn find_pool_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool", owner.as_ref()], &crate::ID)
+}
+
+pub fn find_vault_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_depositor_record_address(pool: &Pubkey, depositor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"depositor", pool.as_ref(), depositor.as_ref()], &crate::ID)
+}
+
+pub fn find_token_vault_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"token_vault", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_claim_bitmap_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"claims", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_pool_metadata_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"metadata", pool.as_ref()], &crate::ID)
+}
+
+pub fn find_stats_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stats"], &crate::ID)
+}
+
+pub fn find_participant_address(wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"participant", wallet.as_ref()], &crate::ID)
+}
+
+pub fn find_stake_record_address(mint: &Pubkey, staker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", mint.as_ref(), staker.as_ref()], &crate::ID)
+}
+
+pub fn find_stake_vault_address(mint: &Pubkey, staker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake_vault", mint.as_ref(), staker.as_ref()], &crate::ID)
+}
+
+pub fn find_proposal_address(pool: &Pubkey, id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"proposal", pool.as_ref(), &id.to_le_bytes()], &crate::ID)
+}
+
+pub fn find_vote_address(proposal: &Pubkey, voter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vote", proposal.as_ref(), voter.as_ref()], &crate::ID)
+}
+
+pub fn find_halt_vote_address(pool: &Pubkey, voter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"halt_vote", pool.as_ref(), voter.as_ref()], &crate::ID)
+}
+
+pub fn find_lottery_draw_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lottery", pool.as_ref()], &crate::ID)
+}
+
+/// `wallet`'s associated token account for `mint`.
+pub fn find_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), crate::instruction::TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+}
+
+/// Token Metadata account of `mint`, passed for NFT-collection gates.
+pub fn find_token_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+}
@@ -0,0 +1,129 @@
+//! Blocking RPC helpers, enabled with the `rpc` feature.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, transaction::Transaction};
+
+use crate::{
+    instruction,
+    pda::{find_depositor_record_address, find_pool_metadata_address, find_stats_address},
+    state::{
+        AccountState, AuditReport, DepositorInfo, DepositorRecord, LaunchpadStats, Pool, PoolInfo, PoolMetadata,
+        PoolStatus,
+    },
+    ClientError, PresaleError,
+};
+
+pub fn fetch_account<T: AccountState>(client: &RpcClient, address: &Pubkey) -> Result<T, ClientError> {
+    T::try_from_account_data(&client.get_account_data(address)?)
+}
+
+pub fn fetch_pool(client: &RpcClient, pool: &Pubkey) -> Result<Pool, ClientError> {
+    fetch_account(client, pool)
+}
+
+/// Program-wide totals, read straight from the stats account.
+pub fn fetch_launchpad_stats(client: &RpcClient) -> Result<LaunchpadStats, ClientError> {
+    fetch_account(client, &find_stats_address().0)
+}
+
+/// The pool's metadata, or `None` if the owner never set any.
+pub fn fetch_pool_metadata(client: &RpcClient, pool: &Pubkey) -> Result<Option<PoolMetadata>, ClientError> {
+    let address = find_pool_metadata_address(pool).0;
+    match client.get_account_with_commitment(&address, client.commitment())?.value {
+        Some(account) => Ok(Some(PoolMetadata::try_from_account_data(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Every `DepositorRecord` of a pool with its address, in deposit order,
+/// including records not yet migrated to the current layout.
+pub fn fetch_depositor_records(
+    client: &RpcClient,
+    pool: &Pubkey,
+) -> Result<Vec<(Pubkey, DepositorRecord)>, ClientError> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, DepositorRecord::discriminator().to_vec())),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                DepositorRecord::POOL_OFFSET,
+                pool.to_bytes().to_vec(),
+            )),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    let mut records = client
+        .get_program_accounts_with_config(&crate::ID, config)?
+        .into_iter()
+        .map(|(address, account)| Ok((address, DepositorRecord::try_from_account_data(&account.data)?)))
+        .collect::<Result<Vec<_>, ClientError>>()?;
+    records.sort_by_key(|(_, record)| record.index);
+    Ok(records)
+}
+
+/// Simulates a view instruction and returns its raw return data.
+///
+/// `payer` only has to exist on-chain; nothing is signed or sent.
+pub fn simulate_view(client: &RpcClient, ix: Instruction, payer: &Pubkey) -> Result<Vec<u8>, ClientError> {
+    let transaction = Transaction::new_with_payer(&[ix], Some(payer));
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(client.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = client.simulate_transaction_with_config(&transaction, config)?.value;
+
+    if let Some(err) = result.err {
+        return Err(match PresaleError::from_transaction_error(&err) {
+            Some(program_error) => ClientError::Program(program_error),
+            None => ClientError::Simulation(err),
+        });
+    }
+    let return_data = result.return_data.ok_or(ClientError::MissingReturnData)?;
+    STANDARD
+        .decode(return_data.data.0)
+        .map_err(|_| ClientError::MissingReturnData)
+}
+
+/// Calls `get_pool_info` through simulation, paid for by the pool owner.
+pub fn fetch_pool_info(client: &RpcClient, pool: &Pubkey) -> Result<PoolInfo, ClientError> {
+    let owner = fetch_pool(client, pool)?.owner;
+    PoolInfo::try_from_return_data(&simulate_view(client, instruction::get_pool_info(pool), &owner)?)
+}
+
+/// Calls `get_pool_status` through simulation, paid for by the pool owner.
+pub fn fetch_pool_status(client: &RpcClient, pool: &Pubkey) -> Result<PoolStatus, ClientError> {
+    let owner = fetch_pool(client, pool)?.owner;
+    PoolStatus::try_from_return_data(&simulate_view(client, instruction::get_pool_status(pool), &owner)?)
+}
+
+/// Runs `audit_pool` through simulation, paid for by the pool owner.
+pub fn fetch_pool_audit(client: &RpcClient, pool: &Pubkey) -> Result<AuditReport, ClientError> {
+    let state = fetch_pool(client, pool)?;
+    let ix = instruction::audit_pool(pool, state.is_finalized);
+    AuditReport::try_from_return_data(&simulate_view(client, ix, &state.owner)?)
+}
+
+/// Calls `get_depositor_info` through simulation, paid for by the depositor.
+pub fn fetch_depositor_info(
+    client: &RpcClient,
+    pool: &Pubkey,
+    depositor: &Pubkey,
+) -> Result<DepositorInfo, ClientError> {
+    // Surface a missing record as an RPC error rather than a failed simulation
+    client.get_account_data(&find_depositor_record_address(pool, depositor).0)?;
+    let lottery_drawn = fetch_pool(client, pool)?.lottery_drawn;
+    let ix = instruction::get_depositor_info(pool, depositor, lottery_drawn);
+    DepositorInfo::try_from_return_data(&simulate_view(client, ix, depositor)?)
+}
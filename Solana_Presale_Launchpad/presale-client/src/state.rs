@@ -0,0 +1,618 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{curve::Curve, discriminator, ClientError};
+
+/// Program-owned account types, prefixed on-chain with Anchor's 8-byte
+/// `account:<Name>` discriminator.
+pub trait AccountState: BorshDeserialize {
+    const NAME: &'static str;
+    /// Current size of an append-only, versioned layout. Shorter accounts
+    /// written by older program versions read with the missing fields zeroed,
+    /// as the program reads them. Zero for other accounts.
+    const VERSIONED_SPACE: usize = 0;
+
+    fn discriminator() -> [u8; 8] {
+        discriminator("account", Self::NAME)
+    }
+
+    fn try_from_account_data(data: &[u8]) -> Result<Self, ClientError> {
+        match data.split_at_checked(8) {
+            Some((prefix, rest)) if prefix == Self::discriminator() => {
+                let mut rest = rest.to_vec();
+                rest.resize(rest.len().max(Self::VERSIONED_SPACE.saturating_sub(8)), 0);
+                Self::deserialize(&mut rest.as_slice()).map_err(|e| ClientError::Decode(Self::NAME, e))
+            }
+            _ => Err(ClientError::AccountDiscriminator(Self::NAME)),
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Pool {
+    pub owner: Pubkey,
+    /// Unix timestamp, or slot for slot-based pools
+    pub expiry_timestamp: i64,
+    pub total_raised: u64,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub is_claimed: bool,
+    pub depositor_count: u32,
+    pub bump: u8,
+    pub token_mint: Pubkey,
+    pub total_tokens: u64,
+    pub merkle_root: [u8; 32],
+    pub is_finalized: bool,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub total_refunded: u64,
+    pub attestation_authority: Pubkey,
+    pub whitelist_root: [u8; 32],
+    pub whitelist_end: i64,
+    pub min_slot_gap: u64,
+    pub reject_cpi: bool,
+    pub lottery_winners: u32,
+    pub lottery_commitment: [u8; 32],
+    pub lottery_entropy: [u8; 32],
+    pub lottery_seed: [u8; 32],
+    pub lottery_drawn: bool,
+    /// Layout version; zero for pools created before versioning
+    pub version: u8,
+    pub time_basis: TimeBasis,
+    /// Staked token sizing allocations (default = no tiers)
+    pub tier_mint: Pubkey,
+    pub tier_count: u8,
+    pub tier_min_stakes: [u64; 4],
+    pub tier_max_allocations: [u64; 4],
+    /// Depositor governance over the escrow (zero `voting_period` = none)
+    pub quorum_bps: u16,
+    pub approval_bps: u16,
+    pub voting_period: i64,
+    pub proposal_count: u32,
+    pub proposal_open: bool,
+    pub total_released: u64,
+    pub release_rejected: bool,
+    /// Bonding-curve sale of `token_mint` (`CurveKind::None` = deposit pool)
+    pub curve_kind: CurveKind,
+    /// Lamports per whole token at the start and end of the curve
+    pub curve_start_price: u64,
+    pub curve_end_price: u64,
+    pub curve_supply: u64,
+    pub curve_sold: u64,
+    pub curve_decimals: u8,
+    pub curve_sell_back: bool,
+    /// Holding required of depositors (`GateKind::None` = ungated)
+    pub gate_kind: GateKind,
+    /// SPL mint for token-holder gates, collection mint for NFT gates
+    pub gate_mint: Pubkey,
+    pub gate_min_amount: u64,
+    /// Early-bird bonus steps (zero `bonus_count` = no bonus)
+    pub bonus_count: u8,
+    /// Step ends in the pool's time basis, ascending
+    pub bonus_ends: [i64; 4],
+    pub bonus_bps: [u16; 4],
+    pub total_weighted: u64,
+    /// Linear payout of the raise from expiry (zero `stream_duration` = claim_funds)
+    pub stream_duration: i64,
+    pub halt_bps: u16,
+    pub halt_votes: u64,
+    /// USD limits in micro-USD, valued at `price_oracle` (`OracleKind::None` = none)
+    pub oracle_kind: OracleKind,
+    pub price_oracle: Pubkey,
+    /// Oldest price deposits accept, in seconds
+    pub max_price_age: i64,
+    pub usd_min_deposit: u64,
+    pub usd_max_deposit: u64,
+    pub usd_soft_cap: u64,
+    pub usd_hard_cap: u64,
+    pub total_raised_usd: u64,
+    /// No longer counted in `LaunchpadStats::active_pools`
+    pub settled: bool,
+    /// Slot whose hash completes the revealed lottery seed (0 = not revealed)
+    pub lottery_seed_slot: u64,
+    pub lottery_slot_hash: [u8; 32],
+    /// Records from before indexes that still need `migrate_depositor_record`
+    pub unindexed_records: u32,
+    /// Counted in `LaunchpadStats::active_pools` when created
+    pub counted: bool,
+}
+
+impl Pool {
+    pub const VERSION: u8 = 12;
+    pub const MAX_TIERS: usize = 4;
+    pub const MAX_BONUS_STEPS: usize = 4;
+    /// Time after expiry the owner of a governed pool has to open release proposals
+    pub const GOVERNANCE_WINDOW: i64 = 180 * 24 * 60 * 60;
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1 + 4 + 1 + 32 + 8 + 32 + 1 + 8 + 8 + 8 + 32 + 32 + 8 + 8 + 1
+        + 4 + 32 + 32 + 32 + 1 + 1 + 1 + 32 + 1 + 8 * Self::MAX_TIERS + 8 * Self::MAX_TIERS
+        + 2 + 2 + 8 + 4 + 1 + 8 + 1
+        + 1 + 8 + 8 + 8 + 8 + 1 + 1
+        + 1 + 32 + 8
+        + 1 + 8 * Self::MAX_BONUS_STEPS + 2 * Self::MAX_BONUS_STEPS + 8
+        + 8 + 2 + 8
+        + 1 + 32 + 8 + 8 + 8 + 8 + 8 + 8
+        + 1 + 8 + 32
+        + 4 + 1;
+
+    pub fn is_lottery(&self) -> bool {
+        self.lottery_winners > 0
+    }
+
+    /// Whether `draw_winners` has mixed the seed slot's hash into `lottery_seed`.
+    pub fn lottery_seeded(&self) -> bool {
+        self.lottery_slot_hash != [0; 32] || (self.lottery_seed_slot == 0 && self.lottery_seed != [0; 32])
+    }
+
+    pub fn is_tiered(&self) -> bool {
+        self.tier_count > 0
+    }
+
+    pub fn is_governed(&self) -> bool {
+        self.voting_period > 0
+    }
+
+    pub fn soft_cap_reached(&self) -> bool {
+        self.total_raised >= self.soft_cap && self.total_raised_usd >= self.usd_soft_cap
+    }
+
+    /// After this, in the pool's time basis, unreleased escrow of a governed
+    /// pool is refunded without a vote.
+    pub fn governance_deadline(&self) -> i64 {
+        self.expiry_timestamp.saturating_add(self.time_basis.from_seconds(Self::GOVERNANCE_WINDOW))
+    }
+
+    pub fn is_gated(&self) -> bool {
+        self.gate_kind != GateKind::None
+    }
+
+    pub fn is_curve(&self) -> bool {
+        self.curve_kind != CurveKind::None
+    }
+
+    pub fn has_bonus(&self) -> bool {
+        self.bonus_count > 0
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.stream_duration > 0
+    }
+
+    pub fn has_usd_limits(&self) -> bool {
+        self.oracle_kind != OracleKind::None
+    }
+
+    /// Part of the raise streamed to the owner by `now` (in the pool's time
+    /// basis), withdrawn or not, as the program computes it.
+    pub fn stream_accrued(&self, now: i64) -> u64 {
+        if !self.is_streaming() {
+            return 0;
+        }
+        let elapsed = (now - self.expiry_timestamp).clamp(0, self.stream_duration);
+        (self.total_raised as u128 * elapsed as u128 / self.stream_duration as u128) as u64
+    }
+
+    /// Lamports `withdraw_stream` would pay at `now`.
+    pub fn stream_withdrawable(&self, now: i64) -> u64 {
+        if self.release_rejected {
+            return 0;
+        }
+        self.stream_accrued(now).saturating_sub(self.total_released)
+    }
+
+    /// `(end, bonus_bps)` of each configured bonus step, ascending.
+    pub fn bonus_steps(&self) -> impl Iterator<Item = (i64, u16)> + '_ {
+        let steps = self.bonus_count as usize;
+        self.bonus_ends[..steps].iter().copied().zip(self.bonus_bps[..steps].iter().copied())
+    }
+
+    /// Bonus `deposit_sol` grants at `now`, in the pool's time basis.
+    pub fn bonus_bps_at(&self, now: i64) -> u16 {
+        self.bonus_steps().find(|&(end, _)| now < end).map_or(0, |(_, bps)| bps)
+    }
+
+    pub fn curve(&self) -> Curve {
+        Curve {
+            kind: self.curve_kind,
+            start_price: self.curve_start_price,
+            end_price: self.curve_end_price,
+            supply: self.curve_supply,
+            decimals: self.curve_decimals,
+        }
+    }
+
+    /// Lamports `buy_tokens` currently charges for `amount` tokens.
+    pub fn curve_buy_cost(&self, amount: u64) -> Option<u64> {
+        self.curve().buy_cost(self.curve_sold, amount)
+    }
+
+    /// Lamports `sell_tokens` currently pays for `amount` tokens.
+    pub fn curve_sell_proceeds(&self, amount: u64) -> Option<u64> {
+        self.curve().sell_proceeds(self.curve_sold, amount)
+    }
+
+    /// Deposits not yet released to the owner through proposals or the stream.
+    pub fn escrow_remaining(&self) -> u64 {
+        self.total_raised - self.total_released
+    }
+
+    /// `(min_stake, max_allocation)` of each configured tier, ascending.
+    pub fn tiers(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let tiers = self.tier_count as usize;
+        self.tier_min_stakes[..tiers].iter().copied().zip(self.tier_max_allocations[..tiers].iter().copied())
+    }
+
+    /// Maximum total deposit for a wallet with `staked` tokens, as the
+    /// program computes it.
+    pub fn tier_allocation(&self, staked: u64) -> u64 {
+        self.tiers()
+            .filter(|&(min_stake, _)| staked >= min_stake)
+            .last()
+            .map_or(0, |(_, max_allocation)| max_allocation)
+    }
+
+    /// Tokens a finalized pool owes a record's `allocation_weight`, as
+    /// `distribute_batch` pays it. Lottery pools split the supply over the
+    /// winning tickets only, bonus pools over the weighted total.
+    pub fn entitlement(&self, weight: u64) -> u64 {
+        let allocated_raise = if self.is_lottery() {
+            self.lottery_winners.min(self.depositor_count) as u64 * self.max_deposit
+        } else if self.has_bonus() {
+            self.total_weighted
+        } else {
+            self.total_raised
+        };
+        if !self.is_finalized || allocated_raise == 0 {
+            return 0;
+        }
+        (weight as u128 * self.total_tokens as u128 / allocated_raise as u128) as u64
+    }
+}
+
+impl AccountState for Pool {
+    const NAME: &'static str = "Pool";
+    const VERSIONED_SPACE: usize = Self::SPACE;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolMetadata {
+    pub pool: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub links: Vec<String>,
+}
+
+impl PoolMetadata {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_SYMBOL_LEN: usize = 10;
+    pub const MAX_URI_LEN: usize = 200;
+    pub const MAX_LINKS: usize = 4;
+    pub const MAX_LINK_LEN: usize = 100;
+}
+
+impl AccountState for PoolMetadata {
+    const NAME: &'static str = "PoolMetadata";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositorRecord {
+    pub depositor: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub index: u32,
+    pub tokens_claimed: u64,
+    pub is_refunded: bool,
+    pub last_deposit_slot: u64,
+    /// Layout version; zero for records created before versioning
+    pub version: u8,
+    /// Counted in the staker's `active_positions` until `release_stake`
+    pub stake_locked: bool,
+    /// Deposits plus their early-bird bonus; zero outside bonus pools
+    pub weighted_amount: u64,
+}
+
+impl DepositorRecord {
+    pub const VERSION: u8 = 3;
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 8 + 4 + 8 + 1 + 8 + 1 + 1 + 8;
+    /// Offset of `pool` in the account data, for `getProgramAccounts` filters.
+    pub const POOL_OFFSET: usize = 8 + 32;
+
+    /// Weight of the position in the token split, as the program computes it.
+    pub fn allocation_weight(&self) -> u64 {
+        if self.weighted_amount > 0 {
+            self.weighted_amount
+        } else {
+            self.amount
+        }
+    }
+}
+
+impl AccountState for DepositorRecord {
+    const NAME: &'static str = "DepositorRecord";
+    const VERSIONED_SPACE: usize = Self::SPACE;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseProposal {
+    pub pool: Pubkey,
+    pub id: u32,
+    pub amount: u64,
+    /// In the pool's time basis
+    pub voting_end: i64,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub executed: bool,
+    pub approved: bool,
+    pub bump: u8,
+}
+
+impl AccountState for ReleaseProposal {
+    const NAME: &'static str = "ReleaseProposal";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Vote {
+    /// The release proposal, or the pool for stream halt votes
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub approve: bool,
+}
+
+impl AccountState for Vote {
+    const NAME: &'static str = "Vote";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StakeRecord {
+    pub mint: Pubkey,
+    pub staker: Pubkey,
+    pub amount: u64,
+    /// Positions in tiered pools that still lock the stake
+    pub active_positions: u32,
+    pub bump: u8,
+}
+
+impl AccountState for StakeRecord {
+    const NAME: &'static str = "StakeRecord";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ClaimBitmap {
+    pub pool: Pubkey,
+    pub bits: Vec<u8>,
+}
+
+impl ClaimBitmap {
+    pub fn is_claimed(&self, index: u32) -> bool {
+        self.bits
+            .get((index / 8) as usize)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+}
+
+impl AccountState for ClaimBitmap {
+    const NAME: &'static str = "ClaimBitmap";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LotteryDraw {
+    pub pool: Pubkey,
+    pub selects_losers: bool,
+    pub to_select: u32,
+    pub selected: u32,
+    pub nonce: u64,
+    pub bits: Vec<u8>,
+}
+
+impl LotteryDraw {
+    pub fn is_complete(&self) -> bool {
+        self.selected == self.to_select
+    }
+
+    pub fn is_winner(&self, index: u32) -> bool {
+        let selected = self
+            .bits
+            .get((index / 8) as usize)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0);
+        selected != self.selects_losers
+    }
+}
+
+impl AccountState for LotteryDraw {
+    const NAME: &'static str = "LotteryDraw";
+}
+
+/// Program-wide totals, counted from the program version that introduced
+/// them. Also the return value of `get_launchpad_stats`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LaunchpadStats {
+    pub total_pools: u64,
+    /// Pools neither claimed by their owner nor refunding
+    pub active_pools: u64,
+    /// Every deposit, including ones later refunded
+    pub total_raised: u64,
+    pub total_claimed: u64,
+    pub unique_depositors: u64,
+    pub bump: u8,
+}
+
+impl LaunchpadStats {
+    pub fn try_from_return_data(data: &[u8]) -> Result<Self, ClientError> {
+        decode_return_data("LaunchpadStats", data)
+    }
+}
+
+impl AccountState for LaunchpadStats {
+    const NAME: &'static str = "LaunchpadStats";
+}
+
+/// Return value of `get_pool_info`, read from the simulated transaction's
+/// return data.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolInfo {
+    pub owner: Pubkey,
+    pub expiry_timestamp: i64,
+    pub total_raised: u64,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub is_claimed: bool,
+    pub depositor_count: u32,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub attestation_authority: Pubkey,
+    pub time_basis: TimeBasis,
+    /// Expiry as a slot; `expiry_timestamp` is the same deadline in unix
+    /// time. Whichever is not the pool's time basis is an estimate.
+    pub expiry_slot: u64,
+}
+
+impl PoolInfo {
+    pub fn try_from_return_data(data: &[u8]) -> Result<Self, ClientError> {
+        decode_return_data("PoolInfo", data)
+    }
+}
+
+/// Unit of a pool's deadlines (`expiry_timestamp`, `whitelist_end`).
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeBasis {
+    #[default]
+    UnixTimestamp,
+    Slot,
+}
+
+impl TimeBasis {
+    /// Target slot duration the program converts between bases with.
+    pub const ESTIMATED_SLOT_MS: i64 = 400;
+
+    /// `seconds` expressed in this basis' unit.
+    pub fn from_seconds(self, seconds: i64) -> i64 {
+        match self {
+            TimeBasis::UnixTimestamp => seconds,
+            TimeBasis::Slot => seconds * 1000 / Self::ESTIMATED_SLOT_MS,
+        }
+    }
+}
+
+/// Shape of a curve pool's price between its start and end price.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CurveKind {
+    /// Not a curve pool
+    #[default]
+    None,
+    Linear,
+    Exponential,
+}
+
+/// What a gated pool requires its depositors to hold.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GateKind {
+    /// Not gated
+    #[default]
+    None,
+    /// At least `gate_min_amount` of `gate_mint`
+    TokenHolder,
+    /// An NFT of the verified collection `gate_mint`
+    NftCollection,
+}
+
+/// Layout of a pool's SOL/USD price account; see [`crate::oracle`].
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OracleKind {
+    /// No USD limits
+    #[default]
+    None,
+    /// A [`crate::oracle::MockPrice`] account
+    Mock,
+    /// A Pyth `PriceUpdateV2` account of the SOL/USD feed
+    Pyth,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolPhase {
+    /// Accepting deposits
+    Open,
+    /// Hard cap reached before expiry
+    Filled,
+    /// Expired with the soft cap met, awaiting finalization
+    Succeeded,
+    /// Expired below the soft cap, deposits are refundable
+    Failed,
+    /// Allocations published, tokens claimable
+    Finalized,
+}
+
+/// Return value of `get_pool_status`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub phase: PoolPhase,
+    pub seconds_remaining: i64,
+    pub total_raised: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub soft_cap_bps: u16,
+    pub hard_cap_bps: u16,
+    pub depositor_count: u32,
+    pub owner_can_claim: bool,
+    pub refunds_open: bool,
+    pub whitelist_only: bool,
+    pub slots_remaining: u64,
+}
+
+impl PoolStatus {
+    pub fn try_from_return_data(data: &[u8]) -> Result<Self, ClientError> {
+        decode_return_data("PoolStatus", data)
+    }
+}
+
+/// Return value of `audit_pool`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditReport {
+    pub vault_lamports: u64,
+    /// Refunds, sell-backs and owner payouts the vault still has to cover
+    pub liabilities: u64,
+    pub vault_solvent: bool,
+    pub claim_consistent: bool,
+    pub counters_consistent: bool,
+}
+
+impl AuditReport {
+    pub fn try_from_return_data(data: &[u8]) -> Result<Self, ClientError> {
+        decode_return_data("AuditReport", data)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.vault_solvent && self.claim_consistent && self.counters_consistent
+    }
+}
+
+/// Return value of `get_depositor_info`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DepositorInfo {
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub share_bps: u16,
+    pub entitlement: u64,
+    pub tokens_claimed: u64,
+    pub is_refunded: bool,
+    pub can_claim_tokens: bool,
+    pub can_refund: bool,
+    pub won_lottery: bool,
+    /// Deposit including the early-bird bonus
+    pub weighted_amount: u64,
+}
+
+impl DepositorInfo {
+    pub fn try_from_return_data(data: &[u8]) -> Result<Self, ClientError> {
+        decode_return_data("DepositorInfo", data)
+    }
+}
+
+/// Return data comes back from simulation with trailing zero bytes trimmed,
+/// so pad it before decoding a fixed-size view.
+fn decode_return_data<T: BorshDeserialize>(name: &'static str, data: &[u8]) -> Result<T, ClientError> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + 512, 0);
+    T::deserialize(&mut &padded[..]).map_err(|e| ClientError::Decode(name, e))
+}
@@ -0,0 +1,63 @@
+//! Read-only JSON query API.
+//!
+//! ```text
+//! GET /status                    sync cursor
+//! GET /pools                     all pools, newest first
+//! GET /pools/<address>           one pool
+//! GET /pools/<address>/deposits  deposits in slot order
+//! GET /pools/<address>/transfers position transfers in slot order
+//! GET /pools/<address>/claims    fund claims, token claims and refunds in slot order
+//! GET /pools/<address>/trades    bonding-curve buys and sells in slot order
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::db::Store;
+
+pub fn serve(listen: &str, db_path: &Path) -> Result<()> {
+    let server = Server::http(listen).map_err(|err| anyhow::anyhow!("binding {listen}: {err}"))?;
+    let store = Store::open(db_path)?;
+    eprintln!("query API listening on http://{listen}");
+
+    for request in server.incoming_requests() {
+        let response = route(&store, &request);
+        let (status, body) = match response {
+            Ok(Some(body)) => (200, body),
+            Ok(None) => (404, r#"{"error":"not found"}"#.to_string()),
+            Err(err) => (500, serde_json::json!({ "error": err.to_string() }).to_string()),
+        };
+        let header = Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+        let _ = request.respond(Response::from_string(body).with_status_code(status).with_header(header));
+    }
+    Ok(())
+}
+
+fn route(store: &Store, request: &Request) -> Result<Option<String>> {
+    if request.method() != &Method::Get {
+        return Ok(None);
+    }
+    let path = request.url().split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match segments.as_slice() {
+        ["status"] => json(&store.cursor()?),
+        ["pools"] => json(&store.pools()?),
+        ["pools", address] => match store.pool(address)? {
+            Some(pool) => json(&pool),
+            None => Ok(None),
+        },
+        ["pools", address, "deposits"] => json(&store.deposits(address)?),
+        ["pools", address, "transfers"] => json(&store.transfers(address)?),
+        ["pools", address, "claims"] => json(&store.claims(address)?),
+        ["pools", address, "trades"] => json(&store.trades(address)?),
+        _ => Ok(None),
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Option<String>> {
+    Ok(Some(serde_json::to_string(value)?))
+}
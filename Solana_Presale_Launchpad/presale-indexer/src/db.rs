@@ -0,0 +1,1085 @@
+//! SQLite storage for indexed pools, deposits, transfers, claims and curve trades.
+//!
+//! Every event row is keyed by `(signature, event_index)`, so replaying a
+//! transaction after a crash is a no-op. The sync cursor is written in the
+//! same SQL transaction as the events it covers.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use presale_client::{
+    merkle::to_hex,
+    state::{CurveKind, GateKind, OracleKind, TimeBasis},
+    PresaleEvent,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// Schema changes in order; a database at `user_version` n has had the first
+/// n applied. Only ever append to this list.
+const MIGRATIONS: &[Migration] = &[
+    // 1: the first release's tables
+    Migration {
+        columns: &[],
+        sql: "
+CREATE TABLE IF NOT EXISTS pools (
+    address           TEXT PRIMARY KEY,
+    owner             TEXT NOT NULL,
+    expiry_timestamp  INTEGER NOT NULL,
+    min_deposit       INTEGER NOT NULL,
+    max_deposit       INTEGER NOT NULL,
+    total_raised      INTEGER NOT NULL DEFAULT 0,
+    depositor_count   INTEGER NOT NULL DEFAULT 0,
+    is_claimed        INTEGER NOT NULL DEFAULT 0,
+    claimed_amount    INTEGER NOT NULL DEFAULT 0,
+    token_mint        TEXT,
+    total_tokens      INTEGER,
+    merkle_root       TEXT,
+    created_slot      INTEGER NOT NULL,
+    created_signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS deposits (
+    signature     TEXT NOT NULL,
+    event_index   INTEGER NOT NULL,
+    slot          INTEGER NOT NULL,
+    block_time    INTEGER,
+    pool          TEXT NOT NULL,
+    depositor     TEXT NOT NULL,
+    amount        INTEGER NOT NULL,
+    total_deposit INTEGER NOT NULL,
+    pool_total    INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS deposits_pool ON deposits (pool, slot);
+
+CREATE TABLE IF NOT EXISTS claims (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot        INTEGER NOT NULL,
+    block_time  INTEGER,
+    pool        TEXT NOT NULL,
+    kind        TEXT NOT NULL,
+    claimant    TEXT NOT NULL,
+    amount      INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS claims_pool ON claims (pool, slot);
+
+CREATE TABLE IF NOT EXISTS cursor (
+    id        INTEGER PRIMARY KEY CHECK (id = 1),
+    slot      INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+",
+    },
+    // 2: pool configuration, payers, transfers and curve trades
+    Migration {
+        columns: &[
+            ("pools", "name", "TEXT"),
+            ("pools", "symbol", "TEXT"),
+            ("pools", "uri", "TEXT"),
+            ("pools", "links", "TEXT NOT NULL DEFAULT '[]'"),
+            ("pools", "time_basis", "TEXT NOT NULL DEFAULT 'unix_timestamp'"),
+            ("pools", "soft_cap", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "hard_cap", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "total_refunded", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "attestation_authority", "TEXT"),
+            ("pools", "whitelist_root", "TEXT"),
+            ("pools", "whitelist_end", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "min_slot_gap", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "reject_cpi", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "lottery_winners", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "lottery_seed", "TEXT"),
+            ("pools", "lottery_drawn", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "tier_mint", "TEXT"),
+            ("pools", "tiers", "TEXT NOT NULL DEFAULT '[]'"),
+            ("pools", "quorum_bps", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "approval_bps", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "voting_period", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "total_released", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "release_rejected", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "curve_kind", "TEXT"),
+            ("pools", "curve_start_price", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "curve_end_price", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "curve_supply", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "curve_sold", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "curve_sell_back", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "gate_kind", "TEXT"),
+            ("pools", "gate_mint", "TEXT"),
+            ("pools", "gate_min_amount", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "bonus_steps", "TEXT NOT NULL DEFAULT '[]'"),
+            ("pools", "total_weighted", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "stream_duration", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "halt_bps", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "halt_votes", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "oracle_kind", "TEXT"),
+            ("pools", "price_oracle", "TEXT"),
+            ("pools", "max_price_age", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "usd_min_deposit", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "usd_max_deposit", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "usd_soft_cap", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "usd_hard_cap", "INTEGER NOT NULL DEFAULT 0"),
+            ("pools", "total_raised_usd", "INTEGER NOT NULL DEFAULT 0"),
+            ("deposits", "payer", "TEXT NOT NULL DEFAULT ''"),
+        ],
+        sql: "
+-- Deposits indexed before payers existed were paid by the depositor
+UPDATE deposits SET payer = depositor WHERE payer = '';
+
+CREATE TABLE IF NOT EXISTS transfers (
+    signature       TEXT NOT NULL,
+    event_index     INTEGER NOT NULL,
+    slot            INTEGER NOT NULL,
+    block_time      INTEGER,
+    pool            TEXT NOT NULL,
+    sender          TEXT NOT NULL,
+    recipient       TEXT NOT NULL,
+    amount          INTEGER NOT NULL,
+    sender_total    INTEGER NOT NULL,
+    recipient_total INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS transfers_pool ON transfers (pool, slot);
+
+CREATE TABLE IF NOT EXISTS trades (
+    signature   TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot        INTEGER NOT NULL,
+    block_time  INTEGER,
+    pool        TEXT NOT NULL,
+    side        TEXT NOT NULL,
+    trader      TEXT NOT NULL,
+    amount      INTEGER NOT NULL,
+    lamports    INTEGER NOT NULL,
+    tokens_sold INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS trades_pool ON trades (pool, slot);
+",
+    },
+];
+
+/// One step of the schema. Databases written before migrations existed
+/// (`user_version` 0) may already have some of its columns, so a column is
+/// only added where it is missing.
+struct Migration {
+    /// `(table, column, definition)` appended to existing tables
+    columns: &'static [(&'static str, &'static str, &'static str)],
+    sql: &'static str,
+}
+
+/// Position of the last fully processed transaction.
+#[derive(Clone, Debug, Serialize)]
+pub struct Cursor {
+    pub slot: u64,
+    pub signature: String,
+}
+
+/// A confirmed transaction and the program events it logged.
+pub struct IndexedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub events: Vec<PresaleEvent>,
+}
+
+#[derive(Serialize)]
+pub struct PoolRow {
+    pub address: String,
+    pub owner: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+    pub links: Vec<String>,
+    pub expiry_timestamp: i64,
+    /// `unix_timestamp` or `slot`, the unit of `expiry_timestamp` and `whitelist_end`
+    pub time_basis: String,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub total_raised: u64,
+    pub total_refunded: u64,
+    pub attestation_authority: Option<String>,
+    pub whitelist_root: Option<String>,
+    pub whitelist_end: i64,
+    pub min_slot_gap: u64,
+    pub reject_cpi: bool,
+    pub lottery_winners: u32,
+    pub lottery_seed: Option<String>,
+    pub lottery_drawn: bool,
+    pub tier_mint: Option<String>,
+    pub tiers: Vec<TierRow>,
+    pub quorum_bps: u16,
+    pub approval_bps: u16,
+    /// Zero when the owner claims without a depositor vote
+    pub voting_period: i64,
+    pub total_released: u64,
+    pub release_rejected: bool,
+    /// `linear` or `exponential` for bonding-curve pools
+    pub curve_kind: Option<String>,
+    pub curve_start_price: u64,
+    pub curve_end_price: u64,
+    pub curve_supply: u64,
+    pub curve_sold: u64,
+    pub curve_sell_back: bool,
+    /// `token_holder` or `nft_collection` for gated pools
+    pub gate_kind: Option<String>,
+    pub gate_mint: Option<String>,
+    pub gate_min_amount: u64,
+    pub bonus_steps: Vec<BonusStepRow>,
+    /// Deposits weighted by their early-bird bonus, zero without one
+    pub total_weighted: u64,
+    /// Zero when the owner claims the raise at once
+    pub stream_duration: i64,
+    pub halt_bps: u16,
+    pub halt_votes: u64,
+    /// `None` without USD limits; USD amounts are in micro-USD
+    pub oracle_kind: Option<String>,
+    pub price_oracle: Option<String>,
+    pub max_price_age: i64,
+    pub usd_min_deposit: u64,
+    pub usd_max_deposit: u64,
+    pub usd_soft_cap: u64,
+    pub usd_hard_cap: u64,
+    pub total_raised_usd: u64,
+    pub depositor_count: u32,
+    pub is_claimed: bool,
+    pub claimed_amount: u64,
+    pub token_mint: Option<String>,
+    pub total_tokens: Option<u64>,
+    pub merkle_root: Option<String>,
+    pub created_slot: u64,
+    pub created_signature: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TierRow {
+    pub min_stake: u64,
+    pub max_allocation: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BonusStepRow {
+    /// In the pool's time basis
+    pub end: i64,
+    pub bonus_bps: u16,
+}
+
+#[derive(Serialize)]
+pub struct DepositRow {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub depositor: String,
+    pub payer: String,
+    pub amount: u64,
+    pub total_deposit: u64,
+    pub pool_total: u64,
+}
+
+#[derive(Serialize)]
+pub struct TransferRow {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub sender: String,
+    pub recipient: String,
+    pub amount: u64,
+    pub sender_total: u64,
+    pub recipient_total: u64,
+}
+
+/// A bonding-curve trade; `lamports` is the cost of a buy or the proceeds of a sell.
+#[derive(Serialize)]
+pub struct TradeRow {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub side: String,
+    pub trader: String,
+    pub amount: u64,
+    pub lamports: u64,
+    pub tokens_sold: u64,
+}
+
+#[derive(Serialize)]
+pub struct ClaimRow {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: String,
+    pub claimant: String,
+    pub amount: u64,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+        Ok(Self { conn })
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>> {
+        Ok(self
+            .conn
+            .query_row("SELECT slot, signature FROM cursor WHERE id = 1", [], |row| {
+                Ok(Cursor { slot: row.get(0)?, signature: row.get(1)? })
+            })
+            .optional()?)
+    }
+
+    /// Stores a transaction's events and advances the cursor past it.
+    pub fn apply(&mut self, tx: &IndexedTransaction) -> Result<()> {
+        let db = self.conn.transaction()?;
+
+        for (event_index, event) in tx.events.iter().enumerate() {
+            let event_index = event_index as u32;
+            match event {
+                PresaleEvent::PoolCreated(e) => {
+                    db.execute(
+                        "INSERT OR IGNORE INTO pools
+                            (address, owner, expiry_timestamp, time_basis, min_deposit, max_deposit, soft_cap,
+                             hard_cap, created_slot, created_signature)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        params![
+                            e.pool.to_string(),
+                            e.owner.to_string(),
+                            e.expiry_timestamp,
+                            match e.time_basis {
+                                TimeBasis::UnixTimestamp => "unix_timestamp",
+                                TimeBasis::Slot => "slot",
+                            },
+                            e.min_deposit,
+                            e.max_deposit,
+                            e.soft_cap,
+                            e.hard_cap,
+                            tx.slot,
+                            tx.signature
+                        ],
+                    )?;
+                }
+                PresaleEvent::PoolMetadataSet(e) => {
+                    db.execute(
+                        "UPDATE pools SET name = ?2, symbol = ?3, uri = ?4, links = ?5 WHERE address = ?1",
+                        params![e.pool.to_string(), e.name, e.symbol, e.uri, serde_json::to_string(&e.links)?],
+                    )?;
+                }
+                PresaleEvent::DepositMade(e) => {
+                    let inserted = db.execute(
+                        "INSERT OR IGNORE INTO deposits
+                            (signature, event_index, slot, block_time, pool, depositor, payer, amount, total_deposit,
+                             pool_total)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        params![
+                            tx.signature,
+                            event_index,
+                            tx.slot,
+                            tx.block_time,
+                            e.pool.to_string(),
+                            e.depositor.to_string(),
+                            e.payer.to_string(),
+                            e.amount,
+                            e.total_deposit,
+                            e.pool_total
+                        ],
+                    )?;
+                    if inserted > 0 {
+                        db.execute(
+                            "UPDATE pools SET total_raised = ?2 WHERE address = ?1",
+                            params![e.pool.to_string(), e.pool_total],
+                        )?;
+                        update_depositor_count(&db, &e.pool.to_string())?;
+                    }
+                }
+                PresaleEvent::PositionTransferred(e) => {
+                    let inserted = db.execute(
+                        "INSERT OR IGNORE INTO transfers
+                            (signature, event_index, slot, block_time, pool, sender, recipient, amount, sender_total,
+                             recipient_total)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        params![
+                            tx.signature,
+                            event_index,
+                            tx.slot,
+                            tx.block_time,
+                            e.pool.to_string(),
+                            e.from.to_string(),
+                            e.to.to_string(),
+                            e.amount,
+                            e.from_total,
+                            e.to_total
+                        ],
+                    )?;
+                    if inserted > 0 {
+                        update_depositor_count(&db, &e.pool.to_string())?;
+                    }
+                }
+                PresaleEvent::FundsClaimed(e) => {
+                    insert_claim(&db, tx, event_index, &e.pool.to_string(), "funds", &e.owner.to_string(), e.amount)?;
+                    db.execute(
+                        "UPDATE pools SET is_claimed = 1, claimed_amount = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), e.amount],
+                    )?;
+                }
+                PresaleEvent::DepositRefunded(e) => {
+                    let inserted = insert_claim(
+                        &db,
+                        tx,
+                        event_index,
+                        &e.pool.to_string(),
+                        "refund",
+                        &e.depositor.to_string(),
+                        e.amount,
+                    )?;
+                    if inserted {
+                        db.execute(
+                            "UPDATE pools SET total_refunded = total_refunded + ?2 WHERE address = ?1",
+                            params![e.pool.to_string(), e.amount],
+                        )?;
+                    }
+                }
+                PresaleEvent::AttestationAuthoritySet(e) => {
+                    // The default key means gating was turned off
+                    let authority = (e.authority != Pubkey::default()).then(|| e.authority.to_string());
+                    db.execute(
+                        "UPDATE pools SET attestation_authority = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), authority],
+                    )?;
+                }
+                PresaleEvent::AntiBotConfigured(e) => {
+                    let whitelist_root = (e.whitelist_root != [0; 32]).then(|| to_hex(&e.whitelist_root));
+                    db.execute(
+                        "UPDATE pools SET whitelist_root = ?2, whitelist_end = ?3, min_slot_gap = ?4, reject_cpi = ?5
+                         WHERE address = ?1",
+                        params![e.pool.to_string(), whitelist_root, e.whitelist_end, e.min_slot_gap, e.reject_cpi],
+                    )?;
+                }
+                PresaleEvent::GateConfigured(e) => {
+                    let kind = match e.kind {
+                        GateKind::None => None,
+                        GateKind::TokenHolder => Some("token_holder"),
+                        GateKind::NftCollection => Some("nft_collection"),
+                    };
+                    let mint = kind.is_some().then(|| e.mint.to_string());
+                    db.execute(
+                        "UPDATE pools SET gate_kind = ?2, gate_mint = ?3, gate_min_amount = ?4 WHERE address = ?1",
+                        params![e.pool.to_string(), kind, mint, e.min_amount],
+                    )?;
+                }
+                PresaleEvent::LotteryConfigured(e) => {
+                    db.execute(
+                        "UPDATE pools SET lottery_winners = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), e.winner_count],
+                    )?;
+                }
+                PresaleEvent::TiersConfigured(e) => {
+                    let tier_mint = (!e.min_stakes.is_empty()).then(|| e.stake_mint.to_string());
+                    let tiers: Vec<TierRow> = e
+                        .min_stakes
+                        .iter()
+                        .zip(&e.max_allocations)
+                        .map(|(&min_stake, &max_allocation)| TierRow { min_stake, max_allocation })
+                        .collect();
+                    db.execute(
+                        "UPDATE pools SET tier_mint = ?2, tiers = ?3 WHERE address = ?1",
+                        params![e.pool.to_string(), tier_mint, serde_json::to_string(&tiers)?],
+                    )?;
+                }
+                PresaleEvent::BonusConfigured(e) => {
+                    let steps: Vec<BonusStepRow> = e
+                        .ends
+                        .iter()
+                        .zip(&e.bonus_bps)
+                        .map(|(&end, &bonus_bps)| BonusStepRow { end, bonus_bps })
+                        .collect();
+                    db.execute(
+                        "UPDATE pools SET bonus_steps = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), serde_json::to_string(&steps)?],
+                    )?;
+                }
+                PresaleEvent::BonusApplied(e) => {
+                    db.execute(
+                        "UPDATE pools SET total_weighted = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), e.total_weighted],
+                    )?;
+                }
+                PresaleEvent::GovernanceConfigured(e) => {
+                    db.execute(
+                        "UPDATE pools SET quorum_bps = ?2, approval_bps = ?3, voting_period = ?4 WHERE address = ?1",
+                        params![e.pool.to_string(), e.quorum_bps, e.approval_bps, e.voting_period],
+                    )?;
+                }
+                // Proposals and votes only matter once executed
+                PresaleEvent::ReleaseProposed(_) | PresaleEvent::VoteCast(_) => {}
+                PresaleEvent::ProposalExecuted(e) if e.approved => {
+                    let pool = e.pool.to_string();
+                    let owner = pool_owner(&db, &pool)?;
+                    let inserted = insert_claim(&db, tx, event_index, &pool, "release", &owner, e.amount)?;
+                    if inserted {
+                        db.execute(
+                            "UPDATE pools SET total_released = total_released + ?2,
+                                claimed_amount = claimed_amount + ?2 WHERE address = ?1",
+                            params![pool, e.amount],
+                        )?;
+                    }
+                }
+                PresaleEvent::ProposalExecuted(e) => {
+                    db.execute("UPDATE pools SET release_rejected = 1 WHERE address = ?1", [e.pool.to_string()])?;
+                }
+                PresaleEvent::StreamConfigured(e) => {
+                    db.execute(
+                        "UPDATE pools SET stream_duration = ?2, halt_bps = ?3 WHERE address = ?1",
+                        params![e.pool.to_string(), e.duration, e.halt_bps],
+                    )?;
+                }
+                PresaleEvent::StreamWithdrawn(e) => {
+                    let pool = e.pool.to_string();
+                    let owner = e.owner.to_string();
+                    let inserted = insert_claim(&db, tx, event_index, &pool, "stream", &owner, e.amount)?;
+                    if inserted {
+                        db.execute(
+                            "UPDATE pools SET total_released = total_released + ?2,
+                                claimed_amount = claimed_amount + ?2 WHERE address = ?1",
+                            params![pool, e.amount],
+                        )?;
+                    }
+                }
+                PresaleEvent::HaltVoteCast(e) => {
+                    db.execute(
+                        "UPDATE pools SET halt_votes = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), e.halt_votes],
+                    )?;
+                }
+                PresaleEvent::StreamHalted(e) => {
+                    let pool = e.pool.to_string();
+                    let owner = pool_owner(&db, &pool)?;
+                    let inserted = e.paid_to_owner > 0
+                        && insert_claim(&db, tx, event_index, &pool, "stream", &owner, e.paid_to_owner)?;
+                    if inserted {
+                        db.execute(
+                            "UPDATE pools SET total_released = total_released + ?2,
+                                claimed_amount = claimed_amount + ?2 WHERE address = ?1",
+                            params![pool, e.paid_to_owner],
+                        )?;
+                    }
+                    db.execute("UPDATE pools SET release_rejected = 1 WHERE address = ?1", [pool])?;
+                }
+                PresaleEvent::UsdLimitsConfigured(e) => {
+                    let kind = match e.kind {
+                        OracleKind::None => None,
+                        OracleKind::Mock => Some("mock"),
+                        OracleKind::Pyth => Some("pyth"),
+                    };
+                    let oracle = kind.is_some().then(|| e.price_oracle.to_string());
+                    db.execute(
+                        "UPDATE pools SET oracle_kind = ?2, price_oracle = ?3, max_price_age = ?4, usd_min_deposit = ?5,
+                            usd_max_deposit = ?6, usd_soft_cap = ?7, usd_hard_cap = ?8 WHERE address = ?1",
+                        params![
+                            e.pool.to_string(),
+                            kind,
+                            oracle,
+                            e.max_price_age,
+                            e.min_deposit,
+                            e.max_deposit,
+                            e.soft_cap,
+                            e.hard_cap
+                        ],
+                    )?;
+                }
+                PresaleEvent::DepositPriced(e) => {
+                    db.execute(
+                        "UPDATE pools SET total_raised_usd = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), e.total_raised_usd],
+                    )?;
+                }
+                PresaleEvent::CurveConfigured(e) => {
+                    let kind = match e.kind {
+                        CurveKind::None => None,
+                        CurveKind::Linear => Some("linear"),
+                        CurveKind::Exponential => Some("exponential"),
+                    };
+                    db.execute(
+                        "UPDATE pools SET curve_kind = ?2, token_mint = ?3, curve_start_price = ?4, curve_end_price = ?5,
+                            curve_supply = ?6, curve_sell_back = ?7 WHERE address = ?1",
+                        params![
+                            e.pool.to_string(),
+                            kind,
+                            e.token_mint.to_string(),
+                            e.start_price,
+                            e.end_price,
+                            e.supply,
+                            e.sell_back
+                        ],
+                    )?;
+                }
+                PresaleEvent::TokensBought(e) => {
+                    let trade = (e.buyer, e.amount, e.cost, e.tokens_sold);
+                    if insert_trade(&db, tx, event_index, &e.pool.to_string(), "buy", trade)? {
+                        db.execute(
+                            "UPDATE pools SET total_raised = total_raised + ?2, curve_sold = ?3 WHERE address = ?1",
+                            params![e.pool.to_string(), e.cost, e.tokens_sold],
+                        )?;
+                    }
+                }
+                PresaleEvent::TokensSold(e) => {
+                    let trade = (e.seller, e.amount, e.proceeds, e.tokens_sold);
+                    if insert_trade(&db, tx, event_index, &e.pool.to_string(), "sell", trade)? {
+                        db.execute(
+                            "UPDATE pools SET total_refunded = total_refunded + ?2, curve_sold = ?3 WHERE address = ?1",
+                            params![e.pool.to_string(), e.proceeds, e.tokens_sold],
+                        )?;
+                    }
+                }
+                PresaleEvent::UnsoldTokensWithdrawn(e) => {
+                    let pool = e.pool.to_string();
+                    let owner = pool_owner(&db, &pool)?;
+                    insert_claim(&db, tx, event_index, &pool, "unsold", &owner, e.amount)?;
+                }
+                // Stakes belong to wallets, not pools
+                PresaleEvent::Staked(_) | PresaleEvent::Unstaked(_) | PresaleEvent::StakeReleased(_) => {}
+                PresaleEvent::LotteryRevealed(e) => {
+                    db.execute(
+                        "UPDATE pools SET lottery_seed = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), to_hex(&e.seed)],
+                    )?;
+                }
+                // The slot hash completes the revealed seed
+                PresaleEvent::LotterySeeded(e) => {
+                    db.execute(
+                        "UPDATE pools SET lottery_seed = ?2 WHERE address = ?1",
+                        params![e.pool.to_string(), to_hex(&e.seed)],
+                    )?;
+                }
+                // The seed stays partial until a later slot's hash completes it
+                PresaleEvent::LotterySeedRescheduled(_) => {}
+                PresaleEvent::LotteryDrawn(e) => {
+                    db.execute("UPDATE pools SET lottery_drawn = 1 WHERE address = ?1", [e.pool.to_string()])?;
+                }
+                // Layout upgrades leave every indexed value unchanged
+                PresaleEvent::AccountMigrated(_) => {}
+                PresaleEvent::PoolFinalized(e) => {
+                    db.execute(
+                        "UPDATE pools SET token_mint = ?2, total_tokens = ?3, merkle_root = ?4 WHERE address = ?1",
+                        params![e.pool.to_string(), e.token_mint.to_string(), e.total_tokens, to_hex(&e.merkle_root)],
+                    )?;
+                }
+                PresaleEvent::TokensClaimed(e) => {
+                    insert_claim(&db, tx, event_index, &e.pool.to_string(), "tokens", &e.depositor.to_string(), e.amount)?;
+                }
+                PresaleEvent::TokensDistributed(e) => {
+                    let (pool, depositor) = (e.pool.to_string(), e.depositor.to_string());
+                    insert_claim(&db, tx, event_index, &pool, "distribution", &depositor, e.amount)?;
+                }
+                // Totals of the batch's TokensDistributed events
+                PresaleEvent::BatchDistributed(_) => {}
+                // Phases follow from the expiry, caps and finalization columns
+                PresaleEvent::PhaseChanged(_) => {}
+                // Program-wide counters; the indexer keeps no stats of its own
+                PresaleEvent::PoolSettled(_) | PresaleEvent::StatsInitialized(_) => {}
+                // Alerts for monitors; an audit changes no state
+                PresaleEvent::AuditFailed(_) => {}
+            }
+        }
+
+        db.execute(
+            "INSERT INTO cursor (id, slot, signature) VALUES (1, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE SET slot = excluded.slot, signature = excluded.signature",
+            params![tx.slot, tx.signature],
+        )?;
+        db.commit()?;
+        Ok(())
+    }
+
+    pub fn pools(&self) -> Result<Vec<PoolRow>> {
+        let mut stmt = self.conn.prepare(&format!("{POOL_SELECT} ORDER BY created_slot DESC"))?;
+        let rows = stmt.query_map([], pool_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn pool(&self, address: &str) -> Result<Option<PoolRow>> {
+        Ok(self
+            .conn
+            .query_row(&format!("{POOL_SELECT} WHERE address = ?1"), [address], pool_row)
+            .optional()?)
+    }
+
+    pub fn deposits(&self, pool: &str) -> Result<Vec<DepositRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, block_time, depositor, payer, amount, total_deposit, pool_total
+             FROM deposits WHERE pool = ?1 ORDER BY slot, signature, event_index",
+        )?;
+        let rows = stmt.query_map([pool], |row| {
+            Ok(DepositRow {
+                signature: row.get(0)?,
+                slot: row.get(1)?,
+                block_time: row.get(2)?,
+                depositor: row.get(3)?,
+                payer: row.get(4)?,
+                amount: row.get(5)?,
+                total_deposit: row.get(6)?,
+                pool_total: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn transfers(&self, pool: &str) -> Result<Vec<TransferRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, block_time, sender, recipient, amount, sender_total, recipient_total
+             FROM transfers WHERE pool = ?1 ORDER BY slot, signature, event_index",
+        )?;
+        let rows = stmt.query_map([pool], |row| {
+            Ok(TransferRow {
+                signature: row.get(0)?,
+                slot: row.get(1)?,
+                block_time: row.get(2)?,
+                sender: row.get(3)?,
+                recipient: row.get(4)?,
+                amount: row.get(5)?,
+                sender_total: row.get(6)?,
+                recipient_total: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn trades(&self, pool: &str) -> Result<Vec<TradeRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, block_time, side, trader, amount, lamports, tokens_sold
+             FROM trades WHERE pool = ?1 ORDER BY slot, signature, event_index",
+        )?;
+        let rows = stmt.query_map([pool], |row| {
+            Ok(TradeRow {
+                signature: row.get(0)?,
+                slot: row.get(1)?,
+                block_time: row.get(2)?,
+                side: row.get(3)?,
+                trader: row.get(4)?,
+                amount: row.get(5)?,
+                lamports: row.get(6)?,
+                tokens_sold: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn claims(&self, pool: &str) -> Result<Vec<ClaimRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, block_time, kind, claimant, amount
+             FROM claims WHERE pool = ?1 ORDER BY slot, signature, event_index",
+        )?;
+        let rows = stmt.query_map([pool], |row| {
+            Ok(ClaimRow {
+                signature: row.get(0)?,
+                slot: row.get(1)?,
+                block_time: row.get(2)?,
+                kind: row.get(3)?,
+                claimant: row.get(4)?,
+                amount: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+const POOL_SELECT: &str = "SELECT address, owner, name, symbol, uri, links, expiry_timestamp, time_basis, min_deposit, max_deposit, soft_cap,
+    hard_cap, total_raised, total_refunded, attestation_authority, whitelist_root, whitelist_end,
+    min_slot_gap, reject_cpi, lottery_winners, lottery_seed, lottery_drawn, tier_mint, tiers, quorum_bps, approval_bps,
+    voting_period, total_released, release_rejected, curve_kind, curve_start_price, curve_end_price, curve_supply,
+    curve_sold, curve_sell_back, gate_kind, gate_mint, gate_min_amount, bonus_steps, total_weighted, stream_duration,
+    halt_bps, halt_votes, oracle_kind, price_oracle, max_price_age, usd_min_deposit, usd_max_deposit, usd_soft_cap,
+    usd_hard_cap, total_raised_usd, depositor_count, is_claimed, claimed_amount, token_mint, total_tokens, merkle_root,
+    created_slot, created_signature FROM pools";
+
+/// Brings the schema up to the last of `MIGRATIONS`, each step in its own
+/// SQL transaction together with the `user_version` it reaches.
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!("database schema version {version} is newer than this indexer's {}", MIGRATIONS.len());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let db = conn.transaction()?;
+        for (table, column, definition) in migration.columns {
+            let exists: bool = db.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+                params![table, column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                db.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))?;
+            }
+        }
+        db.execute_batch(migration.sql)?;
+        db.pragma_update(None, "user_version", index + 1)?;
+        db.commit()?;
+    }
+    Ok(())
+}
+
+fn pool_row(row: &Row) -> rusqlite::Result<PoolRow> {
+    Ok(PoolRow {
+        address: row.get(0)?,
+        owner: row.get(1)?,
+        name: row.get(2)?,
+        symbol: row.get(3)?,
+        uri: row.get(4)?,
+        links: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+        expiry_timestamp: row.get(6)?,
+        time_basis: row.get(7)?,
+        min_deposit: row.get(8)?,
+        max_deposit: row.get(9)?,
+        soft_cap: row.get(10)?,
+        hard_cap: row.get(11)?,
+        total_raised: row.get(12)?,
+        total_refunded: row.get(13)?,
+        attestation_authority: row.get(14)?,
+        whitelist_root: row.get(15)?,
+        whitelist_end: row.get(16)?,
+        min_slot_gap: row.get(17)?,
+        reject_cpi: row.get(18)?,
+        lottery_winners: row.get(19)?,
+        lottery_seed: row.get(20)?,
+        lottery_drawn: row.get(21)?,
+        tier_mint: row.get(22)?,
+        tiers: serde_json::from_str(&row.get::<_, String>(23)?).unwrap_or_default(),
+        quorum_bps: row.get(24)?,
+        approval_bps: row.get(25)?,
+        voting_period: row.get(26)?,
+        total_released: row.get(27)?,
+        release_rejected: row.get(28)?,
+        curve_kind: row.get(29)?,
+        curve_start_price: row.get(30)?,
+        curve_end_price: row.get(31)?,
+        curve_supply: row.get(32)?,
+        curve_sold: row.get(33)?,
+        curve_sell_back: row.get(34)?,
+        gate_kind: row.get(35)?,
+        gate_mint: row.get(36)?,
+        gate_min_amount: row.get(37)?,
+        bonus_steps: serde_json::from_str(&row.get::<_, String>(38)?).unwrap_or_default(),
+        total_weighted: row.get(39)?,
+        stream_duration: row.get(40)?,
+        halt_bps: row.get(41)?,
+        halt_votes: row.get(42)?,
+        oracle_kind: row.get(43)?,
+        price_oracle: row.get(44)?,
+        max_price_age: row.get(45)?,
+        usd_min_deposit: row.get(46)?,
+        usd_max_deposit: row.get(47)?,
+        usd_soft_cap: row.get(48)?,
+        usd_hard_cap: row.get(49)?,
+        total_raised_usd: row.get(50)?,
+        depositor_count: row.get(51)?,
+        is_claimed: row.get(52)?,
+        claimed_amount: row.get(53)?,
+        token_mint: row.get(54)?,
+        total_tokens: row.get(55)?,
+        merkle_root: row.get(56)?,
+        created_slot: row.get(57)?,
+        created_signature: row.get(58)?,
+    })
+}
+
+/// Wallets that ever held a record, whether by depositing or receiving a transfer.
+fn update_depositor_count(db: &rusqlite::Transaction, pool: &str) -> Result<()> {
+    db.execute(
+        "UPDATE pools SET depositor_count = (
+            SELECT COUNT(*) FROM (
+                SELECT depositor FROM deposits WHERE pool = ?1
+                UNION SELECT recipient FROM transfers WHERE pool = ?1
+            )
+         ) WHERE address = ?1",
+        [pool],
+    )?;
+    Ok(())
+}
+
+/// Claims by the owner are keyed by the indexed pool's owner.
+fn pool_owner(db: &rusqlite::Transaction, pool: &str) -> Result<String> {
+    let owner = db
+        .query_row("SELECT owner FROM pools WHERE address = ?1", [pool], |row| row.get(0))
+        .optional()?;
+    Ok(owner.unwrap_or_default())
+}
+
+/// Inserts a `(trader, amount, lamports, tokens_sold)` trade; false if already indexed.
+fn insert_trade(
+    db: &rusqlite::Transaction,
+    tx: &IndexedTransaction,
+    event_index: u32,
+    pool: &str,
+    side: &str,
+    (trader, amount, lamports, tokens_sold): (Pubkey, u64, u64, u64),
+) -> Result<bool> {
+    let inserted = db.execute(
+        "INSERT OR IGNORE INTO trades
+            (signature, event_index, slot, block_time, pool, side, trader, amount, lamports, tokens_sold)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            tx.signature,
+            event_index,
+            tx.slot,
+            tx.block_time,
+            pool,
+            side,
+            trader.to_string(),
+            amount,
+            lamports,
+            tokens_sold
+        ],
+    )?;
+    Ok(inserted > 0)
+}
+
+fn insert_claim(
+    db: &rusqlite::Transaction,
+    tx: &IndexedTransaction,
+    event_index: u32,
+    pool: &str,
+    kind: &str,
+    claimant: &str,
+    amount: u64,
+) -> Result<bool> {
+    let inserted = db.execute(
+        "INSERT OR IGNORE INTO claims (signature, event_index, slot, block_time, pool, kind, claimant, amount)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![tx.signature, event_index, tx.slot, tx.block_time, pool, kind, claimant, amount],
+    )?;
+    Ok(inserted > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use presale_client::event::{DepositMade, EventHeader, PoolCreated};
+
+    use super::*;
+
+    /// A fresh database file named after the test.
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("presale-indexer-{name}-{}.sqlite", std::process::id()));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+        path
+    }
+
+    fn user_version(path: &Path) -> usize {
+        Connection::open(path).unwrap().pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    fn created(pool: Pubkey, owner: Pubkey) -> PresaleEvent {
+        PresaleEvent::PoolCreated(PoolCreated {
+            pool,
+            owner,
+            expiry_timestamp: 1_700_000_000,
+            min_deposit: 100,
+            max_deposit: 1_000,
+            soft_cap: 500,
+            hard_cap: 0,
+            time_basis: TimeBasis::Slot,
+            header: EventHeader::default(),
+        })
+    }
+
+    fn deposited(pool: Pubkey, depositor: Pubkey, payer: Pubkey) -> PresaleEvent {
+        PresaleEvent::DepositMade(DepositMade {
+            pool,
+            depositor,
+            amount: 300,
+            total_deposit: 300,
+            pool_total: 300,
+            payer,
+            header: EventHeader::default(),
+        })
+    }
+
+    fn transaction(signature: &str, slot: u64, events: Vec<PresaleEvent>) -> IndexedTransaction {
+        IndexedTransaction { signature: signature.to_string(), slot, block_time: None, events }
+    }
+
+    #[test]
+    fn new_databases_get_every_migration() {
+        let path = path("new");
+        let mut store = Store::open(&path).unwrap();
+        assert_eq!(user_version(&path), MIGRATIONS.len());
+
+        let (pool, owner, payer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let events = vec![created(pool, owner), deposited(pool, owner, payer)];
+        store.apply(&transaction("sig", 10, events)).unwrap();
+        let row = store.pool(&pool.to_string()).unwrap().unwrap();
+        assert_eq!((row.time_basis.as_str(), row.soft_cap, row.total_raised), ("slot", 500, 300));
+        assert_eq!(store.deposits(&pool.to_string()).unwrap()[0].payer, payer.to_string());
+
+        // Reopening applies nothing twice
+        drop(store);
+        Store::open(&path).unwrap();
+        assert_eq!(user_version(&path), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn first_release_databases_are_upgraded() {
+        let path = path("first-release");
+        let (pool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        {
+            // Written by the first release: its tables, no user_version
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+            conn.execute(
+                "INSERT INTO pools (address, owner, expiry_timestamp, min_deposit, max_deposit, total_raised,
+                    created_slot, created_signature)
+                 VALUES (?1, ?2, 1700000000, 100, 1000, 300, 1, 'old')",
+                params![pool.to_string(), owner.to_string()],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO deposits (signature, event_index, slot, pool, depositor, amount, total_deposit, pool_total)
+                 VALUES ('old', 1, 1, ?1, ?2, 300, 300, 300)",
+                params![pool.to_string(), owner.to_string()],
+            )
+            .unwrap();
+        }
+
+        let mut store = Store::open(&path).unwrap();
+        assert_eq!(user_version(&path), MIGRATIONS.len());
+        let row = store.pool(&pool.to_string()).unwrap().unwrap();
+        assert_eq!((row.time_basis.as_str(), row.total_raised, row.lottery_winners), ("unix_timestamp", 300, 0));
+        assert_eq!(store.deposits(&pool.to_string()).unwrap()[0].payer, owner.to_string());
+
+        let depositor = Pubkey::new_unique();
+        store.apply(&transaction("new", 2, vec![deposited(pool, depositor, depositor)])).unwrap();
+        assert_eq!(store.deposits(&pool.to_string()).unwrap().len(), 2);
+        assert!(store.transfers(&pool.to_string()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unversioned_columns_are_kept() {
+        // Builds between releases already created some of the later columns
+        let path = path("unversioned");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+            conn.execute_batch("ALTER TABLE pools ADD COLUMN soft_cap INTEGER NOT NULL DEFAULT 0").unwrap();
+            conn.execute_batch("ALTER TABLE deposits ADD COLUMN payer TEXT NOT NULL DEFAULT ''").unwrap();
+        }
+        Store::open(&path).unwrap();
+        assert_eq!(user_version(&path), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn newer_schemas_are_refused() {
+        let path = path("newer");
+        Connection::open(&path).unwrap().pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        assert!(Store::open(&path).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+mod api;
+mod db;
+mod sync;
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::db::Store;
+
+#[derive(Parser)]
+#[command(name = "presale-indexer", version, about = "Index presale_program events into SQLite")]
+struct Cli {
+    /// SQLite database file
+    #[arg(long, global = true, default_value = "presale-index.sqlite")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Follow the program and serve the query API
+    Run {
+        /// RPC endpoint, e.g. http://127.0.0.1:8899 for a local test validator
+        #[arg(short = 'u', long, default_value = "https://api.devnet.solana.com")]
+        url: String,
+        /// Address for the query API
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Seconds between polls for new transactions
+        #[arg(long, default_value_t = 5)]
+        poll_interval: u64,
+    },
+    /// Catch up to the current tip once and exit
+    Sync {
+        #[arg(short = 'u', long, default_value = "https://api.devnet.solana.com")]
+        url: String,
+    },
+    /// Serve the query API over an existing database without syncing
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Run { url, listen, poll_interval } => {
+            let mut store = Store::open(&cli.db)?;
+            let db_path = cli.db.clone();
+            thread::spawn(move || {
+                if let Err(err) = api::serve(&listen, &db_path) {
+                    eprintln!("query API stopped: {err:#}");
+                }
+            });
+
+            let client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+            loop {
+                match sync::sync_once(&client, &mut store) {
+                    Ok(0) => {}
+                    Ok(count) => eprintln!("indexed {count} transaction(s)"),
+                    // Keep polling; the cursor only advances past stored transactions.
+                    Err(err) => eprintln!("sync failed: {err:#}"),
+                }
+                thread::sleep(Duration::from_secs(poll_interval));
+            }
+        }
+        Command::Sync { url } => {
+            let mut store = Store::open(&cli.db)?;
+            let client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+            let count = sync::sync_once(&client, &mut store)?;
+            let cursor = store.cursor()?;
+            println!(
+                "indexed {count} transaction(s); cursor at slot {}",
+                cursor.map_or(0, |cursor| cursor.slot)
+            );
+        }
+        Command::Serve { listen } => api::serve(&listen, &cli.db)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Follows `presale_program` transactions through `getSignaturesForAddress`
+//! and decodes the events each one logged.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use presale_client::PresaleEvent;
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::db::{IndexedTransaction, Store};
+
+const PAGE_SIZE: usize = 1000;
+
+/// Processes every program transaction newer than the stored cursor, oldest
+/// first, and returns how many were applied.
+pub fn sync_once(client: &RpcClient, store: &mut Store) -> Result<usize> {
+    let until = store
+        .cursor()?
+        .map(|cursor| Signature::from_str(&cursor.signature))
+        .transpose()
+        .context("stored cursor signature is invalid")?;
+
+    // Signatures come back newest first; page backwards down to the cursor.
+    let mut pending = Vec::new();
+    let mut before = None;
+    loop {
+        let page = client.get_signatures_for_address_with_config(
+            &presale_client::ID,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(PAGE_SIZE),
+                commitment: Some(client.commitment()),
+            },
+        )?;
+        let last = page.last().map(|status| status.signature.clone());
+        let full = page.len() == PAGE_SIZE;
+        pending.extend(page);
+        match last {
+            Some(signature) if full => before = Some(Signature::from_str(&signature)?),
+            _ => break,
+        }
+    }
+
+    let count = pending.len();
+    for status in pending.into_iter().rev() {
+        let signature = Signature::from_str(&status.signature)?;
+        // Failed transactions still advance the cursor, they just carry no events.
+        let events = if status.err.is_none() {
+            fetch_events(client, &signature)?
+        } else {
+            Vec::new()
+        };
+        store.apply(&IndexedTransaction {
+            signature: status.signature,
+            slot: status.slot,
+            block_time: status.block_time,
+            events,
+        })?;
+    }
+    Ok(count)
+}
+
+fn fetch_events(client: &RpcClient, signature: &Signature) -> Result<Vec<PresaleEvent>> {
+    let transaction = client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(client.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .with_context(|| format!("fetching transaction {signature}"))?;
+
+    let logs: Option<Vec<String>> = transaction
+        .transaction
+        .meta
+        .and_then(|meta| meta.log_messages.into());
+    PresaleEvent::parse_logs(&logs.unwrap_or_default()).with_context(|| format!("decoding events of {signature}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use presale_client::{instruction, pda::find_pool_address, state::TimeBasis};
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, signature::Keypair, signer::Signer,
+        transaction::Transaction,
+    };
+
+    use super::*;
+
+    fn send(client: &RpcClient, instructions: &[solana_sdk::instruction::Instruction], signer: &Keypair) {
+        let blockhash = client.get_latest_blockhash().unwrap();
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&signer.pubkey()), &[signer], blockhash);
+        client.send_and_confirm_transaction(&tx).unwrap();
+    }
+
+    fn funded(client: &RpcClient) -> Keypair {
+        let keypair = Keypair::new();
+        let signature = client.request_airdrop(&keypair.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+        while !client.confirm_transaction(&signature).unwrap() {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+        keypair
+    }
+
+    /// Needs a `solana-test-validator` with the program deployed, e.g.
+    /// `solana-test-validator --bpf-program <PROGRAM_ID> presale_program.so`,
+    /// at `PRESALE_TEST_VALIDATOR` (default http://127.0.0.1:8899).
+    #[test]
+    #[ignore = "needs a local validator running the program"]
+    fn indexes_a_validator() {
+        let url = std::env::var("PRESALE_TEST_VALIDATOR").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+        let client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+        let (owner, depositor) = (funded(&client), funded(&client));
+
+        let pool = find_pool_address(&owner.pubkey()).0;
+        let expiry = client.get_block_time(client.get_slot().unwrap()).unwrap() + 3600;
+        let (min, max) = (LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL);
+        let create = instruction::create_pool(&owner.pubkey(), expiry, min, max, 0, 0, TimeBasis::UnixTimestamp);
+        send(&client, &[create], &owner);
+        let amount = LAMPORTS_PER_SOL / 2;
+        let deposit = instruction::deposit_sol(&pool, &depositor.pubkey(), amount, Vec::new(), None, None, None);
+        send(&client, &[deposit], &depositor);
+
+        let path = std::env::temp_dir().join(format!("presale-indexer-validator-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut store = Store::open(&path).unwrap();
+        assert!(sync_once(&client, &mut store).unwrap() >= 2);
+        // Caught up, so a second pass finds nothing new
+        assert_eq!(sync_once(&client, &mut store).unwrap(), 0);
+
+        let row = store.pool(&pool.to_string()).unwrap().expect("indexed pool");
+        assert_eq!(row.owner, owner.pubkey().to_string());
+        assert_eq!((row.total_raised, row.depositor_count), (amount, 1));
+        let deposits = store.deposits(&pool.to_string()).unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].payer, depositor.pubkey().to_string());
+    }
+}
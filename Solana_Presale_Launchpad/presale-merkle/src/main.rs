@@ -0,0 +1,197 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use presale_client::{
+    merkle::{pro_rata, to_hex, MerkleTree},
+    pda::find_lottery_draw_address,
+    rpc::{fetch_account, fetch_depositor_records, fetch_pool},
+    state::{DepositorRecord, LotteryDraw},
+};
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+/// Reads every depositor record of a pool and writes the allocation tree
+/// root plus one proof per depositor, ready for `finalize_pool` and
+/// `claim_tokens`. Lottery pools only allocate to their drawn winners.
+#[derive(Parser)]
+#[command(name = "presale-merkle", version)]
+struct Args {
+    /// RPC endpoint to read the pool from
+    #[arg(long, default_value = "https://api.devnet.solana.com")]
+    url: String,
+
+    /// Pool account address
+    #[arg(long)]
+    pool: String,
+
+    /// Tokens to split pro-rata across depositors (base units)
+    #[arg(long)]
+    total_tokens: u64,
+
+    /// Where to write the JSON output, stdout when omitted
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Output {
+    pool: String,
+    total_tokens: u64,
+    allocated: u64,
+    total_raised: u64,
+    merkle_root: String,
+    claims: Vec<Claim>,
+}
+
+#[derive(Serialize)]
+struct Claim {
+    index: u32,
+    depositor: String,
+    deposited: u64,
+    amount: u64,
+    proof: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let pool = Pubkey::from_str(&args.pool).context("invalid pool address")?;
+    let client = RpcClient::new(args.url);
+
+    let records: Vec<DepositorRecord> = fetch_depositor_records(&client, &pool)
+        .context("fetching depositor records")?
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect();
+    if records.is_empty() {
+        bail!("pool {pool} has no depositor records");
+    }
+
+    let pool_state = fetch_pool(&client, &pool).context("fetching pool")?;
+    let draw: Option<LotteryDraw> = if pool_state.is_lottery() {
+        if !pool_state.lottery_drawn {
+            bail!("lottery winners of pool {pool} have not been drawn yet");
+        }
+        Some(fetch_account(&client, &find_lottery_draw_address(&pool).0).context("fetching lottery draw")?)
+    } else {
+        None
+    };
+    let output = build(&pool, records, draw.as_ref(), args.total_tokens);
+
+    let json = serde_json::to_string_pretty(&output)?;
+    match args.output {
+        Some(path) => fs::write(&path, json).with_context(|| format!("writing {}", path.display()))?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+/// Splits `total_tokens` over the records, only the winners of `draw` in a
+/// lottery pool, and proves every allocation.
+fn build(pool: &Pubkey, mut records: Vec<DepositorRecord>, draw: Option<&LotteryDraw>, total_tokens: u64) -> Output {
+    if let Some(draw) = draw {
+        records.retain(|record| draw.is_winner(record.index));
+    }
+
+    let allocations = pro_rata(&records, total_tokens);
+    let tree = MerkleTree::from_allocations(&allocations);
+
+    Output {
+        pool: pool.to_string(),
+        total_tokens,
+        allocated: allocations.iter().map(|a| a.amount).sum(),
+        total_raised: records.iter().map(|r| r.amount).sum(),
+        merkle_root: to_hex(&tree.root()),
+        claims: allocations
+            .iter()
+            .enumerate()
+            .map(|(position, a)| Claim {
+                index: a.index,
+                depositor: a.depositor.to_string(),
+                deposited: a.deposited,
+                amount: a.amount,
+                proof: tree.proof(position).iter().map(|n| to_hex(n)).collect(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use presale_client::merkle::{from_hex, leaf_hash, verify_proof};
+
+    use super::*;
+
+    fn records(count: u32) -> Vec<DepositorRecord> {
+        (0..count)
+            .map(|index| DepositorRecord {
+                depositor: Pubkey::new_unique(),
+                pool: Pubkey::default(),
+                amount: 100,
+                timestamp: 0,
+                index,
+                tokens_claimed: 0,
+                is_refunded: false,
+                last_deposit_slot: 0,
+                version: DepositorRecord::VERSION,
+                stake_locked: false,
+                weighted_amount: 0,
+            })
+            .collect()
+    }
+
+    fn draw(selects_losers: bool, bits: u8) -> LotteryDraw {
+        LotteryDraw {
+            pool: Pubkey::default(),
+            selects_losers,
+            to_select: bits.count_ones(),
+            selected: bits.count_ones(),
+            nonce: 0,
+            bits: vec![bits],
+        }
+    }
+
+    /// Indexes of the claims, after checking each one's proof.
+    fn verified_claims(output: &Output) -> Vec<u32> {
+        let root = from_hex(&output.merkle_root).unwrap();
+        output
+            .claims
+            .iter()
+            .map(|claim| {
+                let proof: Vec<_> = claim.proof.iter().map(|node| from_hex(node).unwrap()).collect();
+                let leaf = leaf_hash(claim.index, &Pubkey::from_str(&claim.depositor).unwrap(), claim.amount);
+                assert!(verify_proof(&proof, &root, leaf), "claim {}", claim.index);
+                claim.index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_depositor_claims_outside_lotteries() {
+        let output = build(&Pubkey::new_unique(), records(5), None, 1_000);
+        assert_eq!(verified_claims(&output), [0, 1, 2, 3, 4]);
+        assert_eq!(output.allocated, 1_000);
+        assert_eq!(output.total_raised, 500);
+    }
+
+    #[test]
+    fn lotteries_allocate_to_winners_only() {
+        let output = build(&Pubkey::new_unique(), records(5), Some(&draw(false, 0b00101)), 1_000);
+        assert_eq!(verified_claims(&output), [0, 2]);
+        assert!(output.claims.iter().all(|claim| claim.amount == 500));
+
+        // A draw that picked the losers instead
+        let output = build(&Pubkey::new_unique(), records(5), Some(&draw(true, 0b00101)), 900);
+        assert_eq!(verified_claims(&output), [1, 3, 4]);
+        assert_eq!(output.allocated, 900);
+        assert_eq!(output.total_raised, 300);
+    }
+
+    #[test]
+    fn a_single_winner_needs_no_proof() {
+        let output = build(&Pubkey::new_unique(), records(3), Some(&draw(false, 0b010)), 1_000);
+        assert_eq!(verified_claims(&output), [1]);
+        assert!(output.claims[0].proof.is_empty());
+    }
+}